@@ -0,0 +1,103 @@
+//! Compare the grid neighbor search used for bond perception with checking
+//! every pair, on random systems at roughly liquid density.
+//!
+//! Run with `cargo bench`. Brute force is skipped above `BRUTE_MAX` atoms (or
+//! the first argument) and its time extrapolated instead.
+
+use std::time::{Duration, Instant};
+
+// its unit tests come along, but only run as part of the main crate
+#[path = "../src/neighbors.rs"]
+#[allow(dead_code)]
+mod neighbors;
+
+use neighbors::Grid;
+
+/// atoms per Å³, about that of liquid water
+const DENSITY: f32 = 0.1;
+const CUTOFF: f32 = 1.6;
+const BRUTE_MAX: usize = 100_000;
+
+/// a small xorshift generator so the benchmark needs no dependencies
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+fn system(n: usize) -> Vec<[f32; 3]> {
+    let side = (n as f32 / DENSITY).cbrt();
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    (0..n)
+        .map(|_| [rng.next() * side, rng.next() * side, rng.next() * side])
+        .collect()
+}
+
+fn grid_pairs(points: &[[f32; 3]]) -> Vec<(usize, usize)> {
+    let mut ret = Vec::new();
+    Grid::new(points, CUTOFF).for_each_pair(points, CUTOFF, |i, j, _| {
+        ret.push((i, j));
+    });
+    ret.sort_unstable();
+    ret
+}
+
+fn brute_pairs(points: &[[f32; 3]]) -> Vec<(usize, usize)> {
+    let mut ret = Vec::new();
+    for i in 0..points.len() {
+        for j in i + 1..points.len() {
+            let [xi, yi, zi] = points[i];
+            let [xj, yj, zj] = points[j];
+            let d = ((xi - xj).powi(2) + (yi - yj).powi(2) + (zi - zj).powi(2))
+                .sqrt();
+            if d < CUTOFF {
+                ret.push((i, j));
+            }
+        }
+    }
+    ret
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let ret = f();
+    (ret, start.elapsed())
+}
+
+fn main() {
+    let brute_max = std::env::args()
+        .skip(1)
+        .find_map(|a| a.parse().ok())
+        .unwrap_or(BRUTE_MAX);
+    println!(
+        "{:>9} {:>9} {:>12} {:>12}",
+        "atoms", "pairs", "grid", "brute"
+    );
+    // (atoms, seconds) of the largest brute-force run, for extrapolation
+    let mut last_brute: Option<(usize, f64)> = None;
+    for n in [1_000, 10_000, 100_000, 1_000_000] {
+        let points = system(n);
+        let (pairs, grid) = time(|| grid_pairs(&points));
+        let brute = if n <= brute_max {
+            let (brute_pairs, t) = time(|| brute_pairs(&points));
+            assert_eq!(pairs, brute_pairs, "grid and brute force disagree");
+            last_brute = Some((n, t.as_secs_f64()));
+            format!("{:.3?}", t)
+        } else if let Some((m, t)) = last_brute {
+            let ratio = n as f64 / m as f64;
+            format!("~{:.0?}", Duration::from_secs_f64(t * ratio * ratio))
+        } else {
+            String::from("skipped")
+        };
+        println!(
+            "{n:>9} {:>9} {:>12} {brute:>12}",
+            pairs.len(),
+            format!("{grid:.3?}")
+        );
+    }
+}
@@ -0,0 +1,607 @@
+//! Bond perception from interatomic distances, and bond order inference from
+//! bond lengths and valences
+
+use std::{collections::HashMap, str::FromStr};
+
+use crate::{
+    cell::Cell,
+    element::{self, ELEMENTS},
+    molecule::{Bond, BondOrder, Molecule},
+    neighbors::Grid,
+};
+
+/// a maximum bond length for a particular pair of elements, written
+/// `A-B=LENGTH` on the command line
+#[derive(Clone, Debug, PartialEq)]
+pub struct PairOverride {
+    pub pair: (u8, u8),
+    /// maximum bond length in Å. Zero prevents these elements from bonding
+    pub length: f32,
+}
+
+impl FromStr for PairOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("expected A-B=LENGTH, found `{s}`");
+        let (pair, length) = s.split_once('=').ok_or_else(err)?;
+        let (a, b) = pair.split_once('-').ok_or_else(err)?;
+        let elem = |sym: &str| {
+            element::lookup(sym.trim())
+                .ok_or_else(|| format!("unknown element `{sym}`"))
+        };
+        let length = length
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid bond length `{length}`"))?;
+        Ok(Self {
+            pair: (elem(a)?, elem(b)?),
+            length,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BondOptions {
+    /// slack in Å added to the sum of covalent radii
+    pub tolerance: f32,
+    /// atoms closer than this in Å are treated as overlapping rather than
+    /// bonded
+    pub min_distance: f32,
+    pub overrides: Vec<PairOverride>,
+}
+
+impl Default for BondOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.45,
+            min_distance: 0.4,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl BondOptions {
+    /// the longest distance at which atoms with atomic numbers `a` and `b`
+    /// are bonded
+    pub fn max_length(&self, a: u8, b: u8) -> f32 {
+        if a == 0 || b == 0 {
+            return 0.0;
+        }
+        match self
+            .overrides
+            .iter()
+            .rev()
+            .find(|o| o.pair == (a, b) || o.pair == (b, a))
+        {
+            Some(o) => o.length,
+            None => {
+                ELEMENTS[a as usize].covalent_radius
+                    + ELEMENTS[b as usize].covalent_radius
+                    + self.tolerance
+            }
+        }
+    }
+}
+
+/// every pair of `points` with `i < j` that has periodic images closer than
+/// `cutoff` in `cell`, with the shortest distance between them. The points
+/// are wrapped into the cell and surrounded by those of their images that
+/// are within `cutoff` of it, so that a [Grid] finds the pairs across each
+/// face
+fn periodic_pairs(
+    cell: &Cell,
+    points: &[[f32; 3]],
+    cutoff: f32,
+) -> Vec<(usize, usize, f32)> {
+    // how far outside the cell an image can be, in fractional coordinates
+    let widths = cell.widths();
+    let margin =
+        [0, 1, 2].map(|k| if cell.pbc[k] { cutoff / widths[k] } else { 0.0 });
+    let reach = margin.map(|m| m.ceil() as i32);
+    let wrapped: Vec<[f32; 3]> = points
+        .iter()
+        .map(|&p| cell.fractional(cell.wrap(p)))
+        .collect();
+    let mut all: Vec<[f32; 3]> =
+        wrapped.iter().map(|&f| cell.cartesian(f)).collect();
+    let mut owner: Vec<usize> = (0..points.len()).collect();
+    for i in -reach[0]..=reach[0] {
+        for j in -reach[1]..=reach[1] {
+            for k in -reach[2]..=reach[2] {
+                if (i, j, k) == (0, 0, 0) {
+                    continue;
+                }
+                let shift = [i, j, k].map(|n| n as f32);
+                for (n, f) in wrapped.iter().enumerate() {
+                    let g = [0, 1, 2].map(|c| f[c] + shift[c]);
+                    let near = (0..3).all(|c| {
+                        !cell.pbc[c]
+                            || (-margin[c]..=1.0 + margin[c]).contains(&g[c])
+                    });
+                    if near {
+                        all.push(cell.cartesian(g));
+                        owner.push(n);
+                    }
+                }
+            }
+        }
+    }
+
+    // pairs of two images are the same as a pair of a point and an image
+    let n = points.len();
+    let mut shortest: HashMap<(usize, usize), f32> = HashMap::new();
+    Grid::new(&all, cutoff).for_each_pair(&all, cutoff, |i, j, d| {
+        let (a, b) = (owner[i], owner[j]);
+        if a == b || (i >= n && j >= n) {
+            return;
+        }
+        let e = shortest.entry((a.min(b), a.max(b))).or_insert(d);
+        *e = e.min(d);
+    });
+    let mut ret: Vec<(usize, usize, f32)> =
+        shortest.into_iter().map(|((i, j), d)| (i, j, d)).collect();
+    ret.sort_unstable_by_key(|&(i, j, _)| (i, j));
+    ret
+}
+
+/// find the bonds in `mol` from its current coordinates. Two atoms are
+/// bonded when their distance falls between `opts.min_distance` and
+/// [BondOptions::max_length], measured to the nearest periodic image if `mol`
+/// has a periodic cell. Dummy atoms are never bonded
+pub fn perceive_bonds(mol: &Molecule, opts: &BondOptions) -> Vec<Bond> {
+    let atoms = &mol.atoms;
+    // the grid only needs to be as fine as the longest possible bond between
+    // the elements actually present
+    let mut present: Vec<u8> = atoms.iter().map(|a| a.w).collect();
+    present.sort_unstable();
+    present.dedup();
+    let cutoff = present
+        .iter()
+        .flat_map(|&a| present.iter().map(move |&b| (a, b)))
+        .map(|(a, b)| opts.max_length(a, b))
+        .fold(0.0, f32::max);
+    if cutoff <= 0.0 {
+        return Vec::new();
+    }
+
+    let points = mol.positions();
+    let mut pairs = Vec::new();
+    match mol.cell.as_ref().filter(|c| c.is_periodic()) {
+        Some(cell) => pairs = periodic_pairs(cell, &points, cutoff),
+        None => Grid::new(&points, cutoff).for_each_pair(
+            &points,
+            cutoff,
+            |i, j, d| pairs.push((i, j, d)),
+        ),
+    }
+    let mut bonds: Vec<(usize, usize)> = pairs
+        .into_iter()
+        .filter(|&(i, j, dist)| {
+            dist > opts.min_distance
+                && dist < opts.max_length(atoms[i].w, atoms[j].w)
+        })
+        .map(|(i, j, _)| (i, j))
+        .collect();
+    bonds.sort_unstable();
+    bonds
+        .into_iter()
+        .map(|(i, j)| Bond::new(i, j, BondOrder::Single))
+        .collect()
+}
+
+/// bonds shorter than this fraction of the sum of single-bond covalent radii
+/// are candidates for double bonds
+const DOUBLE_RATIO: f32 = 0.94;
+
+/// and shorter than this for triple bonds
+const TRIPLE_RATIO: f32 = 0.83;
+
+/// the largest number of bonds (counting multiplicity) an atom with atomic
+/// number `z` can form, or 0 for elements that aren't given multiple bonds
+fn max_valence(z: u8) -> usize {
+    match z {
+        1 | 9 | 17 | 35 | 53 => 1,
+        8 | 34 => 2,
+        5 | 7 => 3,
+        6 | 14 => 4,
+        15 | 33 => 5,
+        16 => 6,
+        _ => 0,
+    }
+}
+
+/// whether an atom can take part in an aromatic ring
+fn ring_candidate(z: u8, degree: usize) -> bool {
+    matches!(z, 5 | 6 | 7 | 8 | 15 | 16 | 34) && degree <= 3
+}
+
+/// find the simple rings of `len` atoms made up of atoms for which `allowed`
+/// is true, each listed once starting from its lowest index
+fn rings(
+    neighbors: &[Vec<usize>],
+    len: usize,
+    allowed: &[bool],
+) -> Vec<Vec<usize>> {
+    fn extend(
+        neighbors: &[Vec<usize>],
+        len: usize,
+        allowed: &[bool],
+        path: &mut Vec<usize>,
+        out: &mut Vec<Vec<usize>>,
+    ) {
+        let (start, last) = (path[0], *path.last().unwrap());
+        for &n in &neighbors[last] {
+            if path.len() == len {
+                // only keep one of the two directions around the ring
+                if n == start && path[1] < path[len - 1] {
+                    out.push(path.clone());
+                }
+                continue;
+            }
+            if n > start && allowed[n] && !path.contains(&n) {
+                path.push(n);
+                extend(neighbors, len, allowed, path, out);
+                path.pop();
+            }
+        }
+    }
+
+    let mut ret = Vec::new();
+    for start in (0..neighbors.len()).filter(|&i| allowed[i]) {
+        extend(neighbors, len, allowed, &mut vec![start], &mut ret);
+    }
+    ret
+}
+
+/// try to extend the double bond matching from atom `u` along an alternating
+/// path of unmatched and matched bonds ending at an atom with valence to
+/// spare. On success the bonds along the path are flipped, and the caller
+/// must count the extra bond to `u`
+fn augment(
+    u: usize,
+    bonds: &[Bond],
+    candidates: &[Vec<usize>],
+    free: &[usize],
+    used: &mut [usize],
+    matched: &mut [bool],
+    visited: &mut [bool],
+) -> bool {
+    let other = |k: usize, a: usize| {
+        if bonds[k].i == a {
+            bonds[k].j
+        } else {
+            bonds[k].i
+        }
+    };
+    for &e in &candidates[u] {
+        let v = other(e, u);
+        if matched[e] || visited[v] {
+            continue;
+        }
+        visited[v] = true;
+        if used[v] < free[v] {
+            matched[e] = true;
+            used[v] += 1;
+            return true;
+        }
+        for &f in &candidates[v] {
+            let w = other(f, v);
+            if !matched[f] || visited[w] {
+                continue;
+            }
+            visited[w] = true;
+            // `w` trades `f` for a bond further along the path, so its count
+            // stays the same
+            if augment(w, bonds, candidates, free, used, matched, visited) {
+                matched[f] = false;
+                matched[e] = true;
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// assign bond orders to the single `bonds` of `mol`. Bonds noticeably
+/// shorter than a single bond are made double or triple, shortest first,
+/// while both atoms have valence to spare. Five- and six-membered rings of
+/// alternating single and double bonds are then marked aromatic
+pub fn infer_orders(mol: &Molecule, bonds: &mut [Bond]) {
+    let atoms = &mol.atoms;
+    let ratio = |b: &Bond| {
+        let (a, c) = (&atoms[b.i], &atoms[b.j]);
+        let d = mol.separation(b.i, b.j).map(|x| x * x).iter().sum::<f32>();
+        d.sqrt() / (a.element().covalent_radius + c.element().covalent_radius)
+    };
+    let mut free: Vec<usize> = atoms.iter().map(|a| max_valence(a.w)).collect();
+    for b in bonds.iter() {
+        free[b.i] = free[b.i].saturating_sub(1);
+        free[b.j] = free[b.j].saturating_sub(1);
+    }
+
+    let mut order: Vec<(f32, usize)> = bonds
+        .iter()
+        .enumerate()
+        .map(|(k, b)| (ratio(b), k))
+        .filter(|&(r, _)| r < DOUBLE_RATIO)
+        .collect();
+    order.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // triple bonds are rarely ambiguous, so just take them in order
+    for &(r, k) in &order {
+        let Bond { i, j, .. } = bonds[k];
+        if r < TRIPLE_RATIO && free[i] >= 2 && free[j] >= 2 {
+            bonds[k].order = BondOrder::Triple;
+            free[i] -= 2;
+            free[j] -= 2;
+        }
+    }
+
+    // double bonds form a matching on the short bonds, where each atom can
+    // take part in up to `free` of them. Start greedily from the shortest and
+    // then look for augmenting paths, which is needed to kekulize rings whose
+    // bonds all look alike
+    let mut candidates = vec![Vec::new(); atoms.len()];
+    for &(_, k) in &order {
+        let Bond { i, j, order } = bonds[k];
+        if order == BondOrder::Single && free[i] > 0 && free[j] > 0 {
+            candidates[i].push(k);
+            candidates[j].push(k);
+        }
+    }
+    let mut used = vec![0; atoms.len()];
+    let mut matched = vec![false; bonds.len()];
+    for &(_, k) in &order {
+        let Bond { i, j, .. } = bonds[k];
+        if candidates[i].contains(&k) && used[i] < free[i] && used[j] < free[j]
+        {
+            matched[k] = true;
+            used[i] += 1;
+            used[j] += 1;
+        }
+    }
+    for u in 0..atoms.len() {
+        if used[u] < free[u] && !candidates[u].is_empty() {
+            let mut visited = vec![false; atoms.len()];
+            visited[u] = true;
+            if augment(
+                u,
+                bonds,
+                &candidates,
+                &free,
+                &mut used,
+                &mut matched,
+                &mut visited,
+            ) {
+                used[u] += 1;
+            }
+        }
+    }
+    for (bond, matched) in bonds.iter_mut().zip(matched) {
+        if matched {
+            bond.order = BondOrder::Double;
+        }
+    }
+
+    let mut neighbors = vec![Vec::new(); atoms.len()];
+    for b in bonds.iter() {
+        neighbors[b.i].push(b.j);
+        neighbors[b.j].push(b.i);
+    }
+    let allowed: Vec<bool> = atoms
+        .iter()
+        .zip(&neighbors)
+        .map(|(a, n)| ring_candidate(a.w, n.len()))
+        .collect();
+    let index: HashMap<(usize, usize), usize> = bonds
+        .iter()
+        .enumerate()
+        .map(|(k, b)| ((b.i.min(b.j), b.i.max(b.j)), k))
+        .collect();
+    let ring_bonds = |ring: &[usize]| -> Vec<usize> {
+        (0..ring.len())
+            .map(|k| {
+                let (i, j) = (ring[k], ring[(k + 1) % ring.len()]);
+                index[&(i.min(j), i.max(j))]
+            })
+            .collect()
+    };
+    let rings: Vec<(Vec<usize>, Vec<usize>)> = [6, 5]
+        .into_iter()
+        .flat_map(|len| rings(&neighbors, len, &allowed))
+        .map(|ring| {
+            let rb = ring_bonds(&ring);
+            (ring, rb)
+        })
+        .collect();
+
+    // repeat so that rings fused to an aromatic ring can borrow its double
+    // bonds, as in naphthalene
+    let mut aromatic = vec![false; rings.len()];
+    loop {
+        let mut changed = false;
+        for (k, (ring, rb)) in rings.iter().enumerate() {
+            if aromatic[k] {
+                continue;
+            }
+            // an atom is satisfied by a double bond within the ring or by
+            // belonging to an aromatic ring already
+            let satisfied = |a: usize| {
+                rb.iter().any(|&b| {
+                    let b = &bonds[b];
+                    (b.i == a || b.j == a)
+                        && matches!(
+                            b.order,
+                            BondOrder::Double | BondOrder::Aromatic
+                        )
+                })
+            };
+            let unsatisfied: Vec<usize> =
+                ring.iter().copied().filter(|&a| !satisfied(a)).collect();
+            let ok = match (ring.len(), &unsatisfied[..]) {
+                (6, []) => true,
+                // a heteroatom donating its lone pair, as in pyrrole
+                (5, &[a]) => matches!(atoms[a].w, 7 | 8 | 16 | 34),
+                _ => false,
+            };
+            if ok {
+                for &b in rb {
+                    bonds[b].order = BondOrder::Aromatic;
+                }
+                aromatic[k] = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{load, LoadOptions};
+
+    fn acetaldehyde() -> Molecule {
+        let path =
+            concat!(env!("CARGO_MANIFEST_DIR"), "/testfiles/acetaldehyde.xyz");
+        let mut trajs = load(path, &LoadOptions::default()).unwrap();
+        trajs.remove(0).frames.remove(0)
+    }
+
+    fn pairs(bonds: &[Bond]) -> Vec<(usize, usize)> {
+        bonds.iter().map(|b| (b.i, b.j)).collect()
+    }
+
+    #[test]
+    fn covalent_radii() {
+        let mol = acetaldehyde();
+        let bonds = perceive_bonds(&mol, &BondOptions::default());
+        assert_eq!(
+            pairs(&bonds),
+            [(0, 1), (0, 4), (0, 5), (0, 6), (1, 2), (1, 3)]
+        );
+    }
+
+    #[test]
+    fn pair_overrides() {
+        let o: PairOverride = "C-H=0".parse().unwrap();
+        assert_eq!(o, PairOverride { pair: (6, 1), length: 0.0 });
+        assert!("C-H".parse::<PairOverride>().is_err());
+        assert!("C-Q=1.0".parse::<PairOverride>().is_err());
+
+        let opts = BondOptions {
+            overrides: vec![o],
+            ..Default::default()
+        };
+        assert_eq!(opts.max_length(1, 6), 0.0);
+        let bonds = perceive_bonds(&acetaldehyde(), &opts);
+        assert_eq!(pairs(&bonds), [(0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn overlapping_atoms_are_not_bonded() {
+        let opts = BondOptions {
+            min_distance: 1.2,
+            ..Default::default()
+        };
+        // leaves just the C-C and C=O bonds, at 1.51 and 1.22 Å
+        let bonds = perceive_bonds(&acetaldehyde(), &opts);
+        assert_eq!(pairs(&bonds), [(0, 1), (1, 2)]);
+    }
+
+    fn molecule(atoms: &[(u8, [f32; 3])]) -> Molecule {
+        Molecule {
+            atoms: atoms
+                .iter()
+                .map(|&(w, [x, y, z])| crate::molecule::Atom { x, y, z, w })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn orders(mol: &Molecule) -> Vec<(usize, usize, BondOrder)> {
+        let mut bonds = perceive_bonds(mol, &BondOptions::default());
+        infer_orders(mol, &mut bonds);
+        bonds.iter().map(|b| (b.i, b.j, b.order)).collect()
+    }
+
+    #[test]
+    fn carbonyl_double_bond() {
+        let orders = orders(&acetaldehyde());
+        let multiple: Vec<_> = orders
+            .into_iter()
+            .filter(|&(_, _, o)| o != BondOrder::Single)
+            .collect();
+        assert_eq!(multiple, [(1, 2, BondOrder::Double)]);
+    }
+
+    #[test]
+    fn triple_and_cumulated_double_bonds() {
+        let hcn = molecule(&[
+            (1, [0.0, 0.0, -1.066]),
+            (6, [0.0, 0.0, 0.0]),
+            (7, [0.0, 0.0, 1.156]),
+        ]);
+        assert_eq!(
+            orders(&hcn),
+            [(0, 1, BondOrder::Single), (1, 2, BondOrder::Triple)]
+        );
+        let co2 = molecule(&[
+            (8, [0.0, 0.0, -1.16]),
+            (6, [0.0, 0.0, 0.0]),
+            (8, [0.0, 0.0, 1.16]),
+        ]);
+        assert_eq!(
+            orders(&co2),
+            [(0, 1, BondOrder::Double), (1, 2, BondOrder::Double)]
+        );
+    }
+
+    #[test]
+    fn benzene_is_aromatic() {
+        let atoms: Vec<(u8, [f32; 3])> = (0..6)
+            .flat_map(|k| {
+                let t = k as f32 * std::f32::consts::PI / 3.0;
+                let (s, c) = t.sin_cos();
+                [
+                    (6, [1.39 * c, 1.39 * s, 0.0]),
+                    (1, [2.48 * c, 2.48 * s, 0.0]),
+                ]
+            })
+            .collect();
+        let orders = orders(&molecule(&atoms));
+        assert_eq!(orders.len(), 12);
+        for (i, j, order) in orders {
+            let ring = atoms[i].0 == 6 && atoms[j].0 == 6;
+            let expected = if ring {
+                BondOrder::Aromatic
+            } else {
+                BondOrder::Single
+            };
+            assert_eq!(order, expected, "{i}-{j}");
+        }
+    }
+
+    #[test]
+    fn bonds_across_the_cell() {
+        // hydrogens 0.2 Å inside either x face of a 3 Å cube, and a third
+        // atom far from both
+        let mut mol = molecule(&[
+            (1, [0.2, 1.0, 1.0]),
+            (1, [2.6, 1.0, 1.0]),
+            (1, [1.5, 2.5, 2.5]),
+        ]);
+        let opts = BondOptions::default();
+        assert!(perceive_bonds(&mol, &opts).is_empty());
+        let mut cell =
+            Cell::new([[3.0, 0.0, 0.0], [0.0, 3.0, 0.0], [0.0, 0.0, 3.0]]);
+        mol.cell = Some(cell.clone());
+        assert_eq!(pairs(&perceive_bonds(&mol, &opts)), [(0, 1)]);
+        cell.pbc = [false, true, true];
+        mol.cell = Some(cell);
+        assert!(perceive_bonds(&mol, &opts).is_empty());
+    }
+}
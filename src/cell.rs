@@ -0,0 +1,202 @@
+use crate::vector::{cross3, dot3};
+
+/// a periodic unit cell. The rows of `vectors` are the lattice vectors a, b
+/// and c
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cell {
+    pub vectors: [[f32; 3]; 3],
+    /// whether the system is periodic along each lattice vector
+    pub pbc: [bool; 3],
+}
+
+impl Cell {
+    pub fn new(vectors: [[f32; 3]; 3]) -> Self {
+        Self { vectors, pbc: [true; 3] }
+    }
+
+    /// build a cell from lengths a, b, c and angles α, β, γ in degrees, with
+    /// a along x and b in the xy plane
+    pub fn from_parameters(lengths: [f32; 3], angles: [f32; 3]) -> Self {
+        let [a, b, c] = lengths;
+        let [alpha, beta, gamma] = angles.map(f32::to_radians);
+        let cx = c * beta.cos();
+        let cy = c * (alpha.cos() - beta.cos() * gamma.cos()) / gamma.sin();
+        let cz = (c * c - cx * cx - cy * cy).max(0.0).sqrt();
+        Self::new([
+            [a, 0.0, 0.0],
+            [b * gamma.cos(), b * gamma.sin(), 0.0],
+            [cx, cy, cz],
+        ])
+    }
+
+    /// the Cartesian position of the fractional coordinates `f`
+    pub fn cartesian(&self, f: [f32; 3]) -> [f32; 3] {
+        let v = self.vectors;
+        [0, 1, 2].map(|c| f[0] * v[0][c] + f[1] * v[1][c] + f[2] * v[2][c])
+    }
+
+    /// the fractional coordinates of the Cartesian position `p`
+    pub fn fractional(&self, p: [f32; 3]) -> [f32; 3] {
+        let [a, b, c] = self.vectors;
+        let volume = dot3(a, cross3(b, c));
+        [cross3(b, c), cross3(c, a), cross3(a, b)].map(|r| dot3(p, r) / volume)
+    }
+
+    pub fn is_periodic(&self) -> bool {
+        self.pbc.contains(&true)
+    }
+
+    /// the distance between opposite faces of the cell across each lattice
+    /// vector
+    pub fn widths(&self) -> [f32; 3] {
+        let [a, b, c] = self.vectors;
+        let volume = dot3(a, cross3(b, c)).abs();
+        [cross3(b, c), cross3(c, a), cross3(a, b)]
+            .map(|r| volume / dot3(r, r).sqrt())
+    }
+
+    /// `p` moved into the cell along the periodic directions
+    pub fn wrap(&self, p: [f32; 3]) -> [f32; 3] {
+        let mut f = self.fractional(p);
+        for (x, _) in f.iter_mut().zip(self.pbc).filter(|&(_, pbc)| pbc) {
+            *x -= x.floor();
+        }
+        self.cartesian(f)
+    }
+
+    /// the shortest vector that differs from `d` by a lattice translation
+    /// along the periodic directions
+    pub fn minimum_image(&self, d: [f32; 3]) -> [f32; 3] {
+        let mut f = self.fractional(d);
+        for (x, _) in f.iter_mut().zip(self.pbc).filter(|&(_, pbc)| pbc) {
+            *x -= x.round();
+        }
+        // in a skewed cell the nearest image may be one cell further along
+        let range = |k: usize| if self.pbc[k] { -1..=1 } else { 0..=0 };
+        let mut best = self.cartesian(f);
+        for i in range(0) {
+            for j in range(1) {
+                for k in range(2) {
+                    let shift = [i, j, k].map(|n| n as f32);
+                    let g = [0, 1, 2].map(|c| f[c] + shift[c]);
+                    let v = self.cartesian(g);
+                    if dot3(v, v) < dot3(best, best) {
+                        best = v;
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// the corners at either end of each of the 12 edges of the cell
+    pub fn edges(&self) -> Vec<([f32; 3], [f32; 3])> {
+        let corner =
+            |n: usize| self.cartesian([0, 1, 2].map(|k| (n >> k & 1) as f32));
+        (0..8)
+            .flat_map(|n| {
+                (0..3)
+                    .filter(move |k| n >> k & 1 == 0)
+                    .map(move |k| (corner(n), corner(n | 1 << k)))
+            })
+            .collect()
+    }
+
+    /// the cell lengths a, b, c and the angles α, β, γ in degrees
+    pub fn parameters(&self) -> ([f32; 3], [f32; 3]) {
+        let [a, b, c] = self.vectors;
+        let lengths = [dot3(a, a).sqrt(), dot3(b, b).sqrt(), dot3(c, c).sqrt()];
+        let angle = |u: [f32; 3], v: [f32; 3], lu: f32, lv: f32| {
+            (dot3(u, v) / (lu * lv))
+                .clamp(-1.0, 1.0)
+                .acos()
+                .to_degrees()
+        };
+        let [la, lb, lc] = lengths;
+        let angles = [
+            angle(b, c, lb, lc),
+            angle(a, c, la, lc),
+            angle(a, b, la, lb),
+        ];
+        (lengths, angles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triclinic() -> Cell {
+        Cell::from_parameters([5.0, 6.0, 7.0], [70.0, 80.0, 115.0])
+    }
+
+    fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+        (0..3).all(|c| (a[c] - b[c]).abs() < 1e-4)
+    }
+
+    #[test]
+    fn parameters_round_trip() {
+        let cell = triclinic();
+        let (lengths, angles) = cell.parameters();
+        assert!(close(lengths, [5.0, 6.0, 7.0]));
+        assert!(close(angles.map(|a| a / 100.0), [0.7, 0.8, 1.15]));
+        let p = [1.0, -2.0, 3.5];
+        assert!(close(cell.cartesian(cell.fractional(p)), p));
+        assert_eq!(cell.edges().len(), 12);
+        // a cube is as wide as it is long
+        let cube =
+            Cell::new([[4.0, 0.0, 0.0], [0.0, 4.0, 0.0], [0.0, 0.0, 4.0]]);
+        assert_eq!(cube.widths(), [4.0; 3]);
+    }
+
+    #[test]
+    fn wrap_only_along_periodic_directions() {
+        let mut cell = triclinic();
+        let p = cell.cartesian([1.25, -0.5, 2.0]);
+        assert!(close(cell.fractional(cell.wrap(p)), [0.25, 0.5, 0.0]));
+        cell.pbc = [true, false, true];
+        assert!(close(cell.fractional(cell.wrap(p)), [0.25, -0.5, 0.0]));
+    }
+
+    #[test]
+    fn minimum_image_matches_brute_force() {
+        let cell = triclinic();
+        let norm = |v: [f32; 3]| dot3(v, v).sqrt();
+        let mut seed = 0x9e37_79b9_7f4a_7c15_u64;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed >> 40) as f32 / (1u64 << 24) as f32 * 4.0 - 2.0
+        };
+        for _ in 0..200 {
+            let d = cell.cartesian([next(), next(), next()]);
+            let mut best = f32::INFINITY;
+            for i in -4..=4 {
+                for j in -4..=4 {
+                    for k in -4..=4 {
+                        let shift = cell.cartesian([i, j, k].map(|n| n as f32));
+                        best =
+                            best.min(norm([0, 1, 2].map(|c| d[c] + shift[c])));
+                    }
+                }
+            }
+            let image = cell.minimum_image(d);
+            assert!((norm(image) - best).abs() < 1e-4, "{d:?}");
+            // and it is the same vector up to a lattice translation
+            let f = cell.fractional([0, 1, 2].map(|c| image[c] - d[c]));
+            assert!(f.iter().all(|x| (x - x.round()).abs() < 1e-4));
+        }
+    }
+
+    #[test]
+    fn minimum_image_of_slabs() {
+        let mut cell =
+            Cell::new([[4.0, 0.0, 0.0], [0.0, 4.0, 0.0], [0.0, 0.0, 4.0]]);
+        cell.pbc = [true, true, false];
+        assert!(close(
+            cell.minimum_image([3.5, -3.0, 3.5]),
+            [-0.5, 1.0, 3.5]
+        ));
+    }
+}
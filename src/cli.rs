@@ -0,0 +1,361 @@
+use std::{fmt::Display, path::PathBuf, str::FromStr};
+
+use donkey::vector3;
+use raylib_sys::Vector3;
+
+use crate::{formats::LoadOptions, render::Style};
+
+pub const USAGE: &str = "\
+usage: review [OPTIONS] FILE...
+
+Display the molecules in FILE... .
+
+options:
+  -f, --format FORMAT   read every FILE as FORMAT instead of guessing from
+                        the file name: xyz, pdb, cif, mmcif, sdf, mol2,
+                        gaussian, orca, psi4, cfour, cfour-zmat,
+                        cfour-fcm, cube, molden, poscar, outcar, xdatcar
+                        or zmatrix
+  -u, --units UNITS     length units of the coordinates in FILE...: bohr or
+                        angstrom. Guessed from the bond lengths by default.
+                        Ignored for formats that specify their own units
+      --bond-tolerance ANGSTROM
+                        bond atoms closer than the sum of their covalent
+                        radii plus ANGSTROM (default 0.45)
+      --bond-length A-B=ANGSTROM
+                        bond elements A and B when closer than ANGSTROM,
+                        instead of using their covalent radii. 0 never bonds
+                        them. May be repeated
+      --wrap            move the atoms of periodic structures into their
+                        unit cell
+      --replicate NxMxK repeat periodic structures N, M and K times along
+                        their lattice vectors, as in 2x2x1
+  -s, --style STYLE     render style: ball-and-stick (default), spacefill,
+                        sticks or wireframe
+      --color-by NAME   color atoms by the per-atom property NAME, such as
+                        the charges or forces in an extended XYZ file
+      --orbital ORBITAL evaluate ORBITAL on a grid for files with orbitals
+                        but no grids, such as Molden files: homo, homo-N,
+                        lumo, lumo+N, an orbital number, or density for the
+                        electron density. May be repeated (default homo and
+                        lumo)
+      --isovalue VALUE  isovalue of the surfaces drawn for volume data
+                        (default 0.02, or half the largest value of grids
+                        with smaller values)
+      --zmatrix         print a Z-matrix of each molecule, built along its
+                        bonds, instead of opening a window. Trajectories
+                        give their last frame
+      --width PIXELS    window width (default 800)
+      --height PIXELS   window height (default 600)
+      --title TITLE     window title (default review)
+      --fps FPS         target frame rate (default 30)
+      --camera X,Y,Z    initial camera position (default 0,0,-5)
+      --target X,Y,Z    initial camera target (default 0,0,0)
+      --fov DEGREES     vertical field of view (default 90)
+      --orthographic    use an orthographic instead of a perspective camera
+  -h, --help            print this help and exit
+
+keys:
+  PageDown, PageUp      switch between files and the molecules of SD and
+                        MOL2 files
+  C                     cycle coloring through the per-atom properties
+  I                     toggle the info panel
+  B                     toggle the unit cell box
+
+volume keys:
+  V                     cycle the volume drawn as isosurfaces, then none
+  [, ]                  decrease/increase the isovalue
+
+normal mode keys:
+  N, P                  animate the next/previous normal mode, then none
+  M                     toggle the list of normal modes; click a mode to
+                        animate it
+  ,, .                  decrease/increase the amplitude of the vibration
+  J, K                  slow down/speed up the vibration
+  R                     toggle drawing the displacements as arrows instead
+                        of animating them
+
+trajectory keys:
+  Space                 play/pause
+  Left, Right           step one frame back/forward
+  Home, End             jump to the first/last frame
+  L                     toggle looping
+  -, =                  halve/double the playback speed
+";
+
+pub struct Args {
+    pub files: Vec<PathBuf>,
+    pub load: LoadOptions,
+    pub style: Style,
+    pub color_by: Option<String>,
+    pub isovalue: Option<f32>,
+    /// print Z-matrices instead of showing the molecules
+    pub zmatrix: bool,
+    pub width: i32,
+    pub height: i32,
+    pub title: String,
+    pub fps: i32,
+    pub camera: Vector3,
+    pub target: Vector3,
+    pub fovy: f32,
+    pub orthographic: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            load: LoadOptions::default(),
+            style: Style::default(),
+            color_by: None,
+            isovalue: None,
+            zmatrix: false,
+            width: 800,
+            height: 600,
+            title: String::from("review"),
+            fps: 30,
+            camera: vector3!(0.0, 0.0, -5.0),
+            target: vector3!(0.0, 0.0, 0.0),
+            fovy: 90.0,
+            orthographic: false,
+        }
+    }
+}
+
+pub enum Command {
+    Help,
+    Run(Box<Args>),
+}
+
+/// parse `value` as the argument to `flag`, reporting both on failure
+fn parse_value<T>(flag: &str, value: &str) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|e| format!("invalid value `{value}` for {flag}: {e}"))
+}
+
+fn parse_positive(flag: &str, value: &str) -> Result<i32, String> {
+    match parse_value(flag, value)? {
+        n if n > 0 => Ok(n),
+        _ => Err(format!("{flag} must be positive, got `{value}`")),
+    }
+}
+
+fn parse_isovalue(flag: &str, value: &str) -> Result<f32, String> {
+    match parse_value(flag, value)? {
+        x if x > 0.0 && f32::is_finite(x) => Ok(x),
+        _ => Err(format!("{flag} must be positive, got `{value}`")),
+    }
+}
+
+fn parse_fov(flag: &str, value: &str) -> Result<f32, String> {
+    match parse_value(flag, value)? {
+        x if x > 0.0 && x < 180.0 => Ok(x),
+        _ => Err(format!(
+            "{flag} must be between 0 and 180 degrees, got `{value}`"
+        )),
+    }
+}
+
+fn parse_replicate(flag: &str, value: &str) -> Result<[usize; 3], String> {
+    let sp: Vec<&str> = value.split(['x', 'X']).map(str::trim).collect();
+    let [n, m, k] = sp[..] else {
+        return Err(format!(
+            "invalid value `{value}` for {flag}: expected NxMxK"
+        ));
+    };
+    let count = |s: &str| match parse_value(flag, s)? {
+        n if n > 0 => Ok(n),
+        _ => Err(format!("{flag} counts must be positive, got `{value}`")),
+    };
+    Ok([count(n)?, count(m)?, count(k)?])
+}
+
+fn parse_vec3(flag: &str, value: &str) -> Result<Vector3, String> {
+    let sp: Vec<&str> = value.split(',').map(str::trim).collect();
+    let [x, y, z] = sp[..] else {
+        return Err(format!(
+            "invalid value `{value}` for {flag}: expected X,Y,Z"
+        ));
+    };
+    Ok(vector3!(
+        parse_value(flag, x)?,
+        parse_value(flag, y)?,
+        parse_value(flag, z)?
+    ))
+}
+
+impl Command {
+    /// parse the command line, not including the program name
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self, String> {
+        let mut ret = Args::default();
+        let mut args = args.into_iter();
+        let mut only_files = false;
+        while let Some(arg) = args.next() {
+            if only_files || !arg.starts_with('-') {
+                ret.files.push(arg.into());
+                continue;
+            }
+            if arg == "--" {
+                only_files = true;
+                continue;
+            }
+            // support both `--flag value` and `--flag=value`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((f, v)) if f.starts_with("--") => {
+                    (f.to_owned(), Some(v.to_owned()))
+                }
+                _ => (arg, None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("missing value for {flag}"))
+            };
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                "-f" | "--format" => {
+                    ret.load.format = Some(parse_value(&flag, &value()?)?)
+                }
+                "-u" | "--units" => {
+                    ret.load.units = Some(parse_value(&flag, &value()?)?)
+                }
+                "--bond-tolerance" => {
+                    ret.load.bonds.tolerance = parse_value(&flag, &value()?)?
+                }
+                "--bond-length" => ret
+                    .load
+                    .bonds
+                    .overrides
+                    .push(parse_value(&flag, &value()?)?),
+                "-s" | "--style" => ret.style = parse_value(&flag, &value()?)?,
+                "--color-by" => ret.color_by = Some(value()?),
+                "--wrap" if inline.is_none() => ret.load.wrap = true,
+                "--wrap" => {
+                    return Err(format!("{flag} does not take a value"))
+                }
+                "--replicate" => {
+                    ret.load.replicate =
+                        Some(parse_replicate(&flag, &value()?)?)
+                }
+                "--orbital" => {
+                    ret.load.orbitals.push(parse_value(&flag, &value()?)?)
+                }
+                "--isovalue" => {
+                    ret.isovalue = Some(parse_isovalue(&flag, &value()?)?)
+                }
+                "--zmatrix" if inline.is_none() => ret.zmatrix = true,
+                "--zmatrix" => {
+                    return Err(format!("{flag} does not take a value"))
+                }
+                "--width" => ret.width = parse_positive(&flag, &value()?)?,
+                "--height" => ret.height = parse_positive(&flag, &value()?)?,
+                "--title" => ret.title = value()?,
+                "--fps" => ret.fps = parse_positive(&flag, &value()?)?,
+                "--camera" => ret.camera = parse_vec3(&flag, &value()?)?,
+                "--target" => ret.target = parse_vec3(&flag, &value()?)?,
+                "--fov" => ret.fovy = parse_fov(&flag, &value()?)?,
+                "--orthographic" if inline.is_none() => ret.orthographic = true,
+                "--orthographic" => {
+                    return Err(format!("{flag} does not take a value"))
+                }
+                _ => return Err(format!("unrecognized option `{flag}`")),
+            }
+        }
+        if ret.files.is_empty() {
+            return Err(String::from("no input files"));
+        }
+        Ok(Command::Run(Box::new(ret)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        Command::parse(args.iter().map(|&a| String::from(a)))
+    }
+
+    fn run(args: &[&str]) -> Args {
+        match parse(args) {
+            Ok(Command::Run(args)) => *args,
+            Ok(Command::Help) => panic!("help for {args:?}"),
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    fn error(args: &[&str]) -> String {
+        match parse(args) {
+            Err(e) => e,
+            Ok(_) => panic!("no error for {args:?}"),
+        }
+    }
+
+    #[test]
+    fn flags_and_files() {
+        let args = run(&[
+            "a.xyz",
+            "--width=1024",
+            "--height",
+            "768",
+            "--camera",
+            "1, 2,3",
+            "--fov",
+            "45",
+            "--orthographic",
+            "--",
+            "-b.xyz",
+        ]);
+        assert_eq!(args.files, [PathBuf::from("a.xyz"), "-b.xyz".into()]);
+        assert_eq!((args.width, args.height), (1024, 768));
+        let c = args.camera;
+        assert_eq!([c.x, c.y, c.z], [1.0, 2.0, 3.0]);
+        assert_eq!(args.fovy, 45.0);
+        assert!(args.orthographic);
+        assert!(matches!(parse(&["a.xyz", "-h"]), Ok(Command::Help)));
+    }
+
+    #[test]
+    fn bad_arguments() {
+        assert_eq!(
+            error(&["--frobnicate"]),
+            "unrecognized option `--frobnicate`"
+        );
+        assert_eq!(error(&["a.xyz", "--title"]), "missing value for --title");
+        assert_eq!(error(&[]), "no input files");
+        assert_eq!(
+            error(&["a.xyz", "--orthographic=yes"]),
+            "--orthographic does not take a value"
+        );
+        assert_eq!(
+            error(&["a.xyz", "--zmatrix=x"]),
+            "--zmatrix does not take a value"
+        );
+        assert_eq!(
+            error(&["a.xyz", "--width", "0"]),
+            "--width must be positive, got `0`"
+        );
+        assert!(error(&["a.xyz", "--fps", "fast"])
+            .starts_with("invalid value `fast` for --fps"));
+        assert_eq!(
+            error(&["a.xyz", "--camera", "1,2"]),
+            "invalid value `1,2` for --camera: expected X,Y,Z"
+        );
+        assert!(error(&["a.xyz", "--target", "1,y,3"])
+            .starts_with("invalid value `y` for --target"));
+        for fov in ["0", "-30", "180", "nan"] {
+            assert_eq!(
+                error(&["a.xyz", "--fov", fov]),
+                format!("--fov must be between 0 and 180 degrees, got `{fov}`")
+            );
+        }
+    }
+}
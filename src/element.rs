@@ -0,0 +1,220 @@
+//! Per-element data for Z = 1–118, indexed by atomic number. Index 0 is the
+//! dummy atom `X`.
+
+use donkey::colors::color;
+use raylib_sys::Color;
+
+pub const NELEMENTS: usize = 119;
+
+pub struct Element {
+    pub symbol: &'static str,
+    #[allow(dead_code)]
+    pub name: &'static str,
+    /// standard atomic weight in amu, or the mass number of the longest-lived
+    /// isotope for elements without one
+    pub mass: f32,
+    /// single-bond covalent radius in Å (Cordero 2008, Pyykkö 2009 past Cm)
+    pub covalent_radius: f32,
+    /// van der Waals radius in Å (Bondi, then Alvarez 2013; 2.0 if unknown)
+    pub vdw_radius: f32,
+    /// default CPK color as 0xRRGGBB, following Jmol
+    pub color: u32,
+}
+
+impl Element {
+    pub fn color(&self) -> Color {
+        color(self.color << 8 | 0xff)
+    }
+}
+
+const fn el(
+    symbol: &'static str,
+    name: &'static str,
+    mass: f32,
+    covalent_radius: f32,
+    vdw_radius: f32,
+    color: u32,
+) -> Element {
+    Element {
+        symbol,
+        name,
+        mass,
+        covalent_radius,
+        vdw_radius,
+        color,
+    }
+}
+
+/// look up an atomic number by element symbol, ignoring case
+pub fn lookup(symbol: &str) -> Option<u8> {
+    ELEMENTS
+        .iter()
+        .position(|e| e.symbol.eq_ignore_ascii_case(symbol))
+        .map(|z| z as u8)
+}
+
+/// look up an atomic number from either an element symbol or the number
+/// itself
+pub fn parse(token: &str) -> Option<u8> {
+    match token.parse::<u8>() {
+        Ok(z) if (z as usize) < NELEMENTS => Some(z),
+        Ok(_) => None,
+        Err(_) => lookup(token),
+    }
+}
+
+pub static ELEMENTS: [Element; NELEMENTS] = [
+    el("X", "Dummy", 0.0, 0.00, 1.00, 0x000000),
+    el("H", "Hydrogen", 1.008, 0.31, 1.20, 0xFFFFFF),
+    el("He", "Helium", 4.0026, 0.28, 1.40, 0xD9FFFF),
+    el("Li", "Lithium", 6.94, 1.28, 1.82, 0xCC80FF),
+    el("Be", "Beryllium", 9.0122, 0.96, 1.53, 0xC2FF00),
+    el("B", "Boron", 10.81, 0.84, 1.92, 0xFFB5B5),
+    el("C", "Carbon", 12.011, 0.76, 1.70, 0x909090),
+    el("N", "Nitrogen", 14.007, 0.71, 1.55, 0x3050F8),
+    el("O", "Oxygen", 15.999, 0.66, 1.52, 0xFF0D0D),
+    el("F", "Fluorine", 18.998, 0.57, 1.47, 0x90E050),
+    el("Ne", "Neon", 20.180, 0.58, 1.54, 0xB3E3F5),
+    el("Na", "Sodium", 22.990, 1.66, 2.27, 0xAB5CF2),
+    el("Mg", "Magnesium", 24.305, 1.41, 1.73, 0x8AFF00),
+    el("Al", "Aluminium", 26.982, 1.21, 1.84, 0xBFA6A6),
+    el("Si", "Silicon", 28.085, 1.11, 2.10, 0xF0C8A0),
+    el("P", "Phosphorus", 30.974, 1.07, 1.80, 0xFF8000),
+    el("S", "Sulfur", 32.06, 1.05, 1.80, 0xFFFF30),
+    el("Cl", "Chlorine", 35.45, 1.02, 1.75, 0x1FF01F),
+    el("Ar", "Argon", 39.948, 1.06, 1.88, 0x80D1E3),
+    el("K", "Potassium", 39.098, 2.03, 2.75, 0x8F40D4),
+    el("Ca", "Calcium", 40.078, 1.76, 2.31, 0x3DFF00),
+    el("Sc", "Scandium", 44.956, 1.70, 2.58, 0xE6E6E6),
+    el("Ti", "Titanium", 47.867, 1.60, 2.46, 0xBFC2C7),
+    el("V", "Vanadium", 50.942, 1.53, 2.42, 0xA6A6AB),
+    el("Cr", "Chromium", 51.996, 1.39, 2.45, 0x8A99C7),
+    el("Mn", "Manganese", 54.938, 1.39, 2.45, 0x9C7AC7),
+    el("Fe", "Iron", 55.845, 1.32, 2.44, 0xE06633),
+    el("Co", "Cobalt", 58.933, 1.26, 2.40, 0xF090A0),
+    el("Ni", "Nickel", 58.693, 1.24, 1.63, 0x50D050),
+    el("Cu", "Copper", 63.546, 1.32, 1.40, 0xC88033),
+    el("Zn", "Zinc", 65.38, 1.22, 1.39, 0x7D80B0),
+    el("Ga", "Gallium", 69.723, 1.22, 1.87, 0xC28F8F),
+    el("Ge", "Germanium", 72.630, 1.20, 2.11, 0x668F8F),
+    el("As", "Arsenic", 74.922, 1.19, 1.85, 0xBD80E3),
+    el("Se", "Selenium", 78.971, 1.20, 1.90, 0xFFA100),
+    el("Br", "Bromine", 79.904, 1.20, 1.85, 0xA62929),
+    el("Kr", "Krypton", 83.798, 1.16, 2.02, 0x5CB8D1),
+    el("Rb", "Rubidium", 85.468, 2.20, 3.03, 0x702EB0),
+    el("Sr", "Strontium", 87.62, 1.95, 2.49, 0x00FF00),
+    el("Y", "Yttrium", 88.906, 1.90, 2.75, 0x94FFFF),
+    el("Zr", "Zirconium", 91.224, 1.75, 2.52, 0x94E0E0),
+    el("Nb", "Niobium", 92.906, 1.64, 2.56, 0x73C2C9),
+    el("Mo", "Molybdenum", 95.95, 1.54, 2.45, 0x54B5B5),
+    el("Tc", "Technetium", 98.0, 1.47, 2.44, 0x3B9E9E),
+    el("Ru", "Ruthenium", 101.07, 1.46, 2.46, 0x248F8F),
+    el("Rh", "Rhodium", 102.91, 1.42, 2.44, 0x0A7D8C),
+    el("Pd", "Palladium", 106.42, 1.39, 1.63, 0x006985),
+    el("Ag", "Silver", 107.87, 1.45, 1.72, 0xC0C0C0),
+    el("Cd", "Cadmium", 112.41, 1.44, 1.58, 0xFFD98F),
+    el("In", "Indium", 114.82, 1.42, 1.93, 0xA67573),
+    el("Sn", "Tin", 118.71, 1.39, 2.17, 0x668080),
+    el("Sb", "Antimony", 121.76, 1.39, 2.06, 0x9E63B5),
+    el("Te", "Tellurium", 127.60, 1.38, 2.06, 0xD47A00),
+    el("I", "Iodine", 126.90, 1.39, 1.98, 0x940094),
+    el("Xe", "Xenon", 131.29, 1.40, 2.16, 0x429EB0),
+    el("Cs", "Caesium", 132.91, 2.44, 3.43, 0x57178F),
+    el("Ba", "Barium", 137.33, 2.15, 2.68, 0x00C900),
+    el("La", "Lanthanum", 138.91, 2.07, 2.98, 0x70D4FF),
+    el("Ce", "Cerium", 140.12, 2.04, 2.88, 0xFFFFC7),
+    el("Pr", "Praseodymium", 140.91, 2.03, 2.92, 0xD9FFC7),
+    el("Nd", "Neodymium", 144.24, 2.01, 2.95, 0xC7FFC7),
+    el("Pm", "Promethium", 145.0, 1.99, 2.90, 0xA3FFC7),
+    el("Sm", "Samarium", 150.36, 1.98, 2.90, 0x8FFFC7),
+    el("Eu", "Europium", 151.96, 1.98, 2.87, 0x61FFC7),
+    el("Gd", "Gadolinium", 157.25, 1.96, 2.83, 0x45FFC7),
+    el("Tb", "Terbium", 158.93, 1.94, 2.79, 0x30FFC7),
+    el("Dy", "Dysprosium", 162.50, 1.92, 2.87, 0x1FFFC7),
+    el("Ho", "Holmium", 164.93, 1.92, 2.81, 0x00FF9C),
+    el("Er", "Erbium", 167.26, 1.89, 2.83, 0x00E675),
+    el("Tm", "Thulium", 168.93, 1.90, 2.79, 0x00D452),
+    el("Yb", "Ytterbium", 173.05, 1.87, 2.80, 0x00BF38),
+    el("Lu", "Lutetium", 174.97, 1.87, 2.74, 0x00AB24),
+    el("Hf", "Hafnium", 178.49, 1.75, 2.63, 0x4DC2FF),
+    el("Ta", "Tantalum", 180.95, 1.70, 2.53, 0x4DA6FF),
+    el("W", "Tungsten", 183.84, 1.62, 2.57, 0x2194D6),
+    el("Re", "Rhenium", 186.21, 1.51, 2.49, 0x267DAB),
+    el("Os", "Osmium", 190.23, 1.44, 2.48, 0x266696),
+    el("Ir", "Iridium", 192.22, 1.41, 2.41, 0x175487),
+    el("Pt", "Platinum", 195.08, 1.36, 1.75, 0xD0D0E0),
+    el("Au", "Gold", 196.97, 1.36, 1.66, 0xFFD123),
+    el("Hg", "Mercury", 200.59, 1.32, 1.55, 0xB8B8D0),
+    el("Tl", "Thallium", 204.38, 1.45, 1.96, 0xA6544D),
+    el("Pb", "Lead", 207.2, 1.46, 2.02, 0x575961),
+    el("Bi", "Bismuth", 208.98, 1.48, 2.07, 0x9E4FB5),
+    el("Po", "Polonium", 209.0, 1.40, 1.97, 0xAB5C00),
+    el("At", "Astatine", 210.0, 1.50, 2.02, 0x754F45),
+    el("Rn", "Radon", 222.0, 1.50, 2.20, 0x428296),
+    el("Fr", "Francium", 223.0, 2.60, 3.48, 0x420066),
+    el("Ra", "Radium", 226.0, 2.21, 2.83, 0x007D00),
+    el("Ac", "Actinium", 227.0, 2.15, 2.80, 0x70ABFA),
+    el("Th", "Thorium", 232.04, 2.06, 2.93, 0x00BAFF),
+    el("Pa", "Protactinium", 231.04, 2.00, 2.88, 0x00A1FF),
+    el("U", "Uranium", 238.03, 1.96, 1.86, 0x008FFF),
+    el("Np", "Neptunium", 237.0, 1.90, 2.82, 0x0080FF),
+    el("Pu", "Plutonium", 244.0, 1.87, 2.81, 0x006BFF),
+    el("Am", "Americium", 243.0, 1.80, 2.83, 0x545CF2),
+    el("Cm", "Curium", 247.0, 1.69, 3.05, 0x785CE3),
+    el("Bk", "Berkelium", 247.0, 1.68, 3.40, 0x8A4FE3),
+    el("Cf", "Californium", 251.0, 1.68, 3.05, 0xA136D4),
+    el("Es", "Einsteinium", 252.0, 1.65, 2.70, 0xB31FD4),
+    el("Fm", "Fermium", 257.0, 1.67, 2.00, 0xB31FBA),
+    el("Md", "Mendelevium", 258.0, 1.73, 2.00, 0xB30DA6),
+    el("No", "Nobelium", 259.0, 1.76, 2.00, 0xBD0D87),
+    el("Lr", "Lawrencium", 266.0, 1.61, 2.00, 0xC70066),
+    el("Rf", "Rutherfordium", 267.0, 1.57, 2.00, 0xCC0059),
+    el("Db", "Dubnium", 268.0, 1.49, 2.00, 0xD1004F),
+    el("Sg", "Seaborgium", 269.0, 1.43, 2.00, 0xD90045),
+    el("Bh", "Bohrium", 270.0, 1.41, 2.00, 0xE00038),
+    el("Hs", "Hassium", 277.0, 1.34, 2.00, 0xE6002E),
+    el("Mt", "Meitnerium", 278.0, 1.29, 2.00, 0xEB0026),
+    el("Ds", "Darmstadtium", 281.0, 1.28, 2.00, 0xFF1493),
+    el("Rg", "Roentgenium", 282.0, 1.21, 2.00, 0xFF1493),
+    el("Cn", "Copernicium", 285.0, 1.22, 2.00, 0xFF1493),
+    el("Nh", "Nihonium", 286.0, 1.36, 2.00, 0xFF1493),
+    el("Fl", "Flerovium", 289.0, 1.43, 2.00, 0xFF1493),
+    el("Mc", "Moscovium", 290.0, 1.62, 2.00, 0xFF1493),
+    el("Lv", "Livermorium", 293.0, 1.75, 2.00, 0xFF1493),
+    el("Ts", "Tennessine", 294.0, 1.65, 2.00, 0xFF1493),
+    el("Og", "Oganesson", 294.0, 1.57, 2.00, 0xFF1493),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbols_and_numbers_round_trip() {
+        for (z, e) in ELEMENTS.iter().enumerate().skip(1) {
+            assert_eq!(lookup(e.symbol), Some(z as u8), "{}", e.symbol);
+            assert_eq!(parse(&z.to_string()), Some(z as u8));
+        }
+        assert_eq!(ELEMENTS[118].symbol, "Og");
+    }
+
+    #[test]
+    fn lookup_ignores_case() {
+        assert_eq!(lookup("Fe"), Some(26));
+        assert_eq!(lookup("FE"), Some(26));
+        assert_eq!(lookup("fe"), Some(26));
+        assert_eq!(parse("cl"), Some(17));
+        assert_eq!(lookup("Xx"), None);
+        assert_eq!(lookup(""), None);
+    }
+
+    #[test]
+    fn atomic_numbers() {
+        assert_eq!(parse("26"), Some(26));
+        // 0 is the dummy atom
+        assert_eq!(parse("0"), Some(0));
+        assert_eq!(parse("119"), None);
+        assert_eq!(parse("300"), None);
+        assert_eq!(parse("-1"), None);
+    }
+}
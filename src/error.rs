@@ -0,0 +1,214 @@
+use std::{
+    fmt::Display,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// an error encountered while loading an input file
+#[derive(Debug)]
+pub enum Error {
+    Io { path: PathBuf, source: io::Error },
+    Parse(ParseError),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io { path, source } => {
+                write!(f, "{}: {source}", path.display())
+            }
+            Error::Parse(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Parse(_) => None,
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ErrorKind {
+    /// the token is neither an element symbol nor an atomic number
+    UnknownElement,
+    /// the token could not be parsed as a number
+    InvalidNumber,
+    /// a required field was absent; the payload describes it
+    Missing(&'static str),
+    /// the token is not valid here; the payload describes what was expected
+    Expected(&'static str),
+    /// a trajectory frame does not contain the same atoms as the first one
+    InconsistentFrame,
+}
+
+/// a syntax error at a particular place in an input file. `line` and
+/// `column` are 1-based, and `token` is empty when the error is about
+/// something missing
+#[derive(Debug)]
+pub struct ParseError {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+    pub kind: ErrorKind,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { path, line, column, token, kind } = self;
+        write!(f, "{}:{line}:{column}: ", path.display())?;
+        match kind {
+            ErrorKind::UnknownElement => write!(f, "unknown element `{token}`"),
+            ErrorKind::InvalidNumber => write!(f, "invalid number `{token}`"),
+            ErrorKind::Missing(what) => write!(f, "missing {what}"),
+            ErrorKind::Expected(what) => {
+                write!(f, "expected {what}, found `{token}`")
+            }
+            ErrorKind::InconsistentFrame => {
+                write!(f, "frame does not match the atoms of the first frame")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// a single line of an input file, for attaching locations to errors
+#[derive(Clone, Copy)]
+pub struct Line<'a> {
+    pub path: &'a Path,
+    /// 1-based line number
+    pub number: usize,
+    pub text: &'a str,
+}
+
+/// a whitespace-delimited token of a [Line]
+#[derive(Clone, Copy)]
+pub struct Field<'a> {
+    pub text: &'a str,
+    /// 1-based column of the start of the field
+    pub column: usize,
+}
+
+/// split `s` into [Line]s tagged with `path`
+pub fn lines<'a>(path: &'a Path, s: &'a str) -> impl Iterator<Item = Line<'a>> {
+    s.lines().enumerate().map(move |(i, text)| Line {
+        path,
+        number: i + 1,
+        text,
+    })
+}
+
+impl<'a> Line<'a> {
+    pub fn fields(&self) -> Vec<Field<'a>> {
+        let mut ret = Vec::new();
+        let mut start = None;
+        for (i, c) in self.text.char_indices().chain([(self.text.len(), ' ')]) {
+            match (start, c.is_whitespace()) {
+                (None, false) => start = Some(i),
+                (Some(s), true) => {
+                    ret.push(Field {
+                        text: &self.text[s..i],
+                        column: self.text[..s].chars().count() + 1,
+                    });
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        ret
+    }
+
+    /// the text in the 1-based, inclusive range of columns `start..=end`,
+    /// trimmed of whitespace, for fixed-column formats. The field is empty if
+    /// the line is too short
+    pub fn columns(&self, start: usize, end: usize) -> Field<'a> {
+        let end = end.min(self.text.len());
+        let raw = self.text.get(start.min(end + 1) - 1..end).unwrap_or("");
+        let trimmed = raw.trim_start();
+        Field {
+            text: trimmed.trim_end(),
+            column: start + raw.len() - trimmed.len(),
+        }
+    }
+
+    pub fn error(&self, field: Field, kind: ErrorKind) -> ParseError {
+        ParseError {
+            path: self.path.to_owned(),
+            line: self.number,
+            column: field.column,
+            token: field.text.to_owned(),
+            kind,
+        }
+    }
+
+    /// an error for a field missing from the end of the line
+    pub fn missing(&self, what: &'static str) -> ParseError {
+        ParseError {
+            path: self.path.to_owned(),
+            line: self.number,
+            column: self.text.chars().count() + 1,
+            token: String::new(),
+            kind: ErrorKind::Missing(what),
+        }
+    }
+
+    /// return the `i`th field of `fields`, or a [ErrorKind::Missing] error
+    /// describing it as `what`
+    pub fn field(
+        &self,
+        fields: &[Field<'a>],
+        i: usize,
+        what: &'static str,
+    ) -> Result<Field<'a>, ParseError> {
+        fields.get(i).copied().ok_or_else(|| self.missing(what))
+    }
+
+    pub fn number<T: std::str::FromStr>(
+        &self,
+        field: Field,
+    ) -> Result<T, ParseError> {
+        field
+            .text
+            .parse()
+            .map_err(|_| self.error(field, ErrorKind::InvalidNumber))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_give_their_location() {
+        let path = Path::new("water.xyz");
+        let line = Line {
+            path,
+            number: 3,
+            text: "O  1.0  abc  0.0",
+        };
+        let fields = line.fields();
+        let e = line.number::<f32>(fields[2]).unwrap_err();
+        assert_eq!(e.to_string(), "water.xyz:3:9: invalid number `abc`");
+
+        let e = line.error(fields[0], ErrorKind::Expected("an atom"));
+        assert_eq!(e.to_string(), "water.xyz:3:1: expected an atom, found `O`");
+
+        let e = line.missing("z coordinate");
+        assert_eq!(e.to_string(), "water.xyz:3:17: missing z coordinate");
+        assert_eq!(
+            Error::from(e).to_string(),
+            "water.xyz:3:17: missing z coordinate"
+        );
+    }
+}
@@ -0,0 +1,353 @@
+//! CFOUR files: the program's output, the `ZMAT` input and the `FCMFINAL`
+//! force constants. The output gives a frame for every `Coordinates (in
+//! bohr)` block, the final electronic energy of each geometry (in Hartree)
+//! and the normal coordinates with their IR intensities. `ZMAT` gives the
+//! geometry as a Z-matrix or in Cartesian coordinates. `FCMFINAL` holds only
+//! the Hessian, so the geometry comes from the `GRD` file alongside it (the
+//! `ZMAT` won't do, being in the input orientation and atom order), and the
+//! normal modes from diagonalizing the mass-weighted Hessian.
+
+use std::path::Path;
+
+use super::{psi4::frequency, zmatrix::read_zmatrix, Lines};
+use crate::{
+    element,
+    error::{Error, ErrorKind, Field, Line, ParseError},
+    molecule::{Atom, Molecule, Trajectory},
+    units::Unit,
+    vibration::{self, NormalMode},
+};
+
+/// the types of normal coordinate CFOUR prints
+const MODE_KINDS: [&str; 3] = ["VIBRATION", "ROTATION", "TRANSLATION"];
+
+fn is_rule(line: &Line) -> bool {
+    line.text.trim_start().starts_with("----")
+}
+
+/// the atoms of a coordinate block, starting just after its title line.
+/// Dummy atoms, with atomic number 0, are left out
+fn read_coordinates(lines: &mut Lines) -> Result<Vec<Atom>, ParseError> {
+    while !is_rule(&lines.require("coordinates")?) {}
+    let mut atoms = Vec::new();
+    loop {
+        let line = lines.require("end of coordinates")?;
+        if is_rule(&line) {
+            return Ok(atoms);
+        }
+        let fields = line.fields();
+        let number = line.field(&fields, 1, "atomic number")?;
+        let w: u8 = line.number(number)?;
+        if w as usize >= element::NELEMENTS {
+            Err(line.error(number, ErrorKind::UnknownElement))?;
+        }
+        if w == 0 {
+            continue;
+        }
+        let coord = |i, what| line.number(line.field(&fields, i, what)?);
+        atoms.push(Atom {
+            x: coord(2, "x coordinate")?,
+            y: coord(3, "y coordinate")?,
+            z: coord(4, "z coordinate")?,
+            w,
+        });
+    }
+}
+
+/// the blocks of the `Normal Coordinates` section, starting just after its
+/// title. Each block has a line of irreps, a line of frequencies and a line
+/// of types, followed by a row per atom. Only vibrations are kept
+fn read_normal_coordinates(
+    lines: &mut Lines,
+    natoms: usize,
+) -> Result<Vec<NormalMode>, ParseError> {
+    let mut modes = Vec::new();
+    loop {
+        while lines.peek().is_some_and(|l| l.text.trim().is_empty()) {
+            lines.next();
+        }
+        let start = lines.pos;
+        let (Some(_), Some(freqs), Some(kinds)) =
+            (lines.next(), lines.next(), lines.next())
+        else {
+            lines.pos = start;
+            return Ok(modes);
+        };
+        let kinds = kinds.fields();
+        let is_kind = |f: &Field| MODE_KINDS.contains(&f.text);
+        if kinds.is_empty() || !kinds.iter().all(is_kind) {
+            lines.pos = start;
+            return Ok(modes);
+        }
+        let mut block: Vec<NormalMode> = freqs
+            .fields()
+            .iter()
+            .map(|&f| {
+                let frequency = frequency(&freqs, f)?;
+                Ok(NormalMode { frequency, ..Default::default() })
+            })
+            .collect::<Result<_, ParseError>>()?;
+        if block.len() != kinds.len() {
+            Err(freqs.missing("a frequency for every mode"))?;
+        }
+        for _ in 0..natoms {
+            let line = lines.require("normal coordinates")?;
+            let fields = line.fields();
+            if fields.len() != 1 + 3 * block.len() {
+                Err(line.missing("displacements"))?;
+            }
+            for (k, mode) in block.iter_mut().enumerate() {
+                let d = |c| line.number(fields[1 + 3 * k + c]);
+                mode.displacements.push([d(0)?, d(1)?, d(2)?]);
+            }
+        }
+        modes.extend(
+            block
+                .into_iter()
+                .zip(&kinds)
+                .filter(|(_, kind)| kind.text == "VIBRATION")
+                .map(|(mode, _)| mode),
+        );
+    }
+}
+
+/// the `(frequency, intensity)` of each vibration in the rows of the
+/// `Normal Coordinate Analysis` table, which end the first line that isn't
+/// one after them
+fn read_intensities(lines: &mut Lines) -> Result<Vec<(f32, f32)>, ParseError> {
+    let mut ret = Vec::new();
+    let mut rows = 0;
+    for line in lines.by_ref() {
+        let fields = line.fields();
+        match fields.as_slice() {
+            [_, freq, ir, kind] if MODE_KINDS.contains(&kind.text) => {
+                if kind.text == "VIBRATION" {
+                    ret.push((frequency(&line, *freq)?, line.number(*ir)?));
+                }
+                rows += 1;
+            }
+            _ if rows > 0 => break,
+            _ => {}
+        }
+    }
+    Ok(ret)
+}
+
+pub fn load_cfour(path: impl AsRef<Path>) -> Result<Trajectory, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let mut lines = Lines::new(path, &s);
+    let mut frames: Vec<Molecule> = Vec::new();
+    let mut intensities = Vec::new();
+    while let Some(line) = lines.next() {
+        let text = line.text.trim();
+        if text.contains("Coordinates (in bohr)") {
+            let atoms = read_coordinates(&mut lines)?;
+            let mut mol = Molecule { atoms, ..Default::default() };
+            mol.convert_from(Unit::Bohr);
+            if frames.first().is_some_and(|f| !f.same_atoms(&mol)) {
+                let field = line.fields()[0];
+                Err(line.error(field, ErrorKind::InconsistentFrame))?;
+            }
+            frames.push(mol);
+            continue;
+        }
+        // everything else describes the most recent geometry
+        let Some(mol) = frames.last_mut() else {
+            continue;
+        };
+        if text.starts_with("The final electronic energy is") {
+            let fields = line.fields();
+            let energy = line.field(&fields, 5, "energy")?;
+            line.number::<f64>(energy)?;
+            mol.info.retain(|(k, _)| k != "energy");
+            mol.info
+                .push((String::from("energy"), energy.text.to_owned()));
+        } else if text == "Normal Coordinate Analysis" {
+            intensities = read_intensities(&mut lines)?;
+        } else if text == "Normal Coordinates" {
+            let natoms = mol.atoms.len();
+            mol.modes = read_normal_coordinates(&mut lines, natoms)?;
+        }
+    }
+    let Some(last) = frames.last_mut() else {
+        Err(lines.eof.missing("a Coordinates (in bohr) block"))?
+    };
+    // the normal coordinates print fewer digits of the frequencies
+    for mode in &mut last.modes {
+        mode.ir_intensity = intensities
+            .iter()
+            .find(|(f, _)| (f - mode.frequency).abs() < 0.01)
+            .map(|&(_, ir)| ir);
+    }
+    Ok(Trajectory { frames })
+}
+
+/// the value of the keyword `key` in the `*CFOUR(...)` namelist of a ZMAT
+fn keyword<'a>(namelist: &'a str, key: &str) -> Option<&'a str> {
+    namelist
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|kv| kv.split_once('='))
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| v)
+}
+
+/// read a ZMAT: a title line, then the geometry as a Z-matrix or in
+/// Cartesian coordinates up to a blank line, then the variables of a
+/// Z-matrix, then the `*CFOUR(...)` namelist, whose `UNITS` gives the units
+/// of the lengths
+pub fn load_zmat(path: impl AsRef<Path>) -> Result<Molecule, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let mut lines = Lines::new(path, &s);
+    let title = lines.require("title line")?;
+    let mut mol = Molecule {
+        comment: title.text.trim().to_owned(),
+        atoms: read_zmatrix(&mut lines)?,
+        ..Default::default()
+    };
+    // the namelist starts a line, unlike the `*` CFOUR puts after the
+    // names of variables to optimize
+    let start = s
+        .match_indices('*')
+        .map(|(i, _)| i)
+        .find(|&i| i == 0 || s[..i].ends_with('\n'))
+        .unwrap_or(s.len());
+    let rest = &s[start..];
+    let namelist = rest.split(')').next().unwrap_or_default();
+    let units = match keyword(namelist, "UNITS") {
+        Some(v) if v.eq_ignore_ascii_case("BOHR") || v == "1" => Unit::Bohr,
+        _ => Unit::Angstrom,
+    };
+    mol.convert_from(units);
+    Ok(mol)
+}
+
+/// read a `GRD` file: the atom count and energy, then the atomic number and
+/// coordinates in bohr of each atom, then the gradient
+fn load_grd(path: &Path) -> Result<Molecule, Error> {
+    let s = super::read(path)?;
+    let mut lines = Lines::new(path, &s);
+    let header = lines.require("atom count")?;
+    let fields = header.fields();
+    let natoms: usize =
+        header.number(header.field(&fields, 0, "atom count")?)?;
+    let mut mol = Molecule::default();
+    for _ in 0..natoms {
+        let line = lines.require("atom line")?;
+        let fields = line.fields();
+        let field = line.field(&fields, 0, "atomic number")?;
+        // written as a float, such as 8.0000000000
+        let z: f32 = line.number(field)?;
+        if !(0.0..element::NELEMENTS as f32).contains(&z) {
+            Err(line.error(field, ErrorKind::UnknownElement))?;
+        }
+        let w = z.round() as u8;
+        let coord = |i, what| line.number(line.field(&fields, i, what)?);
+        mol.atoms.push(Atom {
+            x: coord(1, "x coordinate")?,
+            y: coord(2, "y coordinate")?,
+            z: coord(3, "z coordinate")?,
+            w,
+        });
+    }
+    mol.convert_from(Unit::Bohr);
+    Ok(mol)
+}
+
+/// read `FCMFINAL`, with the geometry from the `GRD` file in the same
+/// directory
+pub fn load_fcmfinal(path: impl AsRef<Path>) -> Result<Molecule, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let mut lines = Lines::new(path, &s);
+    let header = lines.require("atom count")?;
+    let fields = header.fields();
+    let count = header.field(&fields, 0, "atom count")?;
+    let natoms: usize = header.number(count)?;
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let grd = dir.join("GRD");
+    if !grd.exists() {
+        Err(ParseError {
+            token: String::new(),
+            ..header.error(count, ErrorKind::Missing("GRD next to it"))
+        })?;
+    }
+    let mut mol = load_grd(&grd)?;
+    if mol.atoms.len() != natoms {
+        let kind = ErrorKind::Expected("the atom count of the geometry");
+        Err(header.error(count, kind))?;
+    }
+
+    let n = 3 * natoms;
+    let mut hessian = Vec::with_capacity(n * n);
+    for line in lines.by_ref() {
+        for field in line.fields() {
+            hessian.push(line.number::<f64>(field)?);
+        }
+    }
+    if hessian.len() != n * n {
+        Err(lines.eof.missing("force constants"))?;
+    }
+    mol.modes = vibration::from_hessian(&mol, &hessian);
+    Ok(mol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::BOHR_TO_ANGSTROM;
+
+    fn testfile(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testfiles")
+            .join(name)
+    }
+
+    #[test]
+    fn output_with_normal_coordinates() {
+        let traj = load_cfour(testfile("cfour_water.out")).unwrap();
+        assert_eq!(traj.frames.len(), 1);
+        let mol = &traj.frames[0];
+        let elements: Vec<u8> = mol.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [8, 1, 1]);
+        assert_eq!(mol.atoms[1].y, -1.494_280_1 * BOHR_TO_ANGSTROM);
+        assert_eq!(
+            mol.info,
+            [(String::from("energy"), String::from("-76.241305521508"))]
+        );
+
+        // the rotation is left out
+        let frequencies: Vec<f32> =
+            mol.modes.iter().map(|m| m.frequency).collect();
+        assert_eq!(frequencies, [1775.65, 4113.38, 4212.3]);
+        assert_eq!(mol.modes[0].ir_intensity, Some(53.6242));
+        assert_eq!(mol.modes[2].ir_intensity, Some(44.5098));
+        assert_eq!(mol.modes[0].displacements[1], [0.0, 0.4262, -0.5444]);
+    }
+
+    #[test]
+    fn force_constants_with_grd() {
+        let mol = load_fcmfinal(testfile("cfour_h2/FCMFINAL")).unwrap();
+        assert_eq!(mol.atoms.len(), 2);
+        assert_eq!(mol.atoms[1].z, 1.4 * BOHR_TO_ANGSTROM);
+        // the stretch of H2 with a force constant of 0.37 Eh/bohr²
+        assert_eq!(mol.modes.len(), 1);
+        let mass = element::ELEMENTS[1].mass as f64;
+        let expected = (2.0 * 0.37 / mass).sqrt() * 5140.487;
+        assert!((mol.modes[0].frequency as f64 - expected).abs() < 0.1);
+    }
+
+    #[test]
+    fn force_constants_need_grd() {
+        // the ZMAT's geometry isn't in the orientation of the Hessian
+        let err = load_fcmfinal(testfile("cfour_h2_zmat/FCMFINAL"));
+        match err {
+            Err(Error::Parse(e)) => {
+                assert_eq!(e.kind, ErrorKind::Missing("GRD next to it"))
+            }
+            _ => panic!("loaded FCMFINAL without a GRD"),
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Tokenizer and block structure shared by the CIF-based formats: mmCIF (see
+//! [super::mmcif]) and small-molecule crystallographic CIF.
+
+use std::{collections::HashMap, path::Path};
+
+use crate::error::{ErrorKind, ParseError};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Token<'a> {
+    /// the value without any quotes or semicolons around it
+    pub text: &'a str,
+    pub line: usize,
+    pub column: usize,
+    pub quoted: bool,
+}
+
+impl Token<'_> {
+    /// whether this is one of the placeholders `.` (inapplicable) or `?`
+    /// (unknown)
+    pub fn is_null(&self) -> bool {
+        !self.quoted && (self.text == "." || self.text == "?")
+    }
+
+    pub fn error(&self, path: &Path, kind: ErrorKind) -> ParseError {
+        ParseError {
+            path: path.to_owned(),
+            line: self.line,
+            column: self.column,
+            token: self.text.to_owned(),
+            kind,
+        }
+    }
+
+    /// parse the token as a number, ignoring the standard uncertainty that
+    /// crystallographic CIF appends in parentheses, as in `1.234(5)`
+    pub fn number<T: std::str::FromStr>(
+        &self,
+        path: &Path,
+    ) -> Result<T, ParseError> {
+        let text = match self.text.find('(') {
+            Some(i) if self.text.ends_with(')') => &self.text[..i],
+            _ => self.text,
+        };
+        text.parse()
+            .map_err(|_| self.error(path, ErrorKind::InvalidNumber))
+    }
+}
+
+/// split a CIF file into tokens, handling comments, quoted strings and
+/// semicolon-delimited text fields
+pub fn tokenize<'a>(
+    path: &Path,
+    s: &'a str,
+) -> Result<Vec<Token<'a>>, ParseError> {
+    let mut ret = Vec::new();
+    let mut lines = s.split_inclusive('\n').enumerate().peekable();
+    while let Some((i, line)) = lines.next() {
+        let number = i + 1;
+        let col = |j: usize| line[..j].chars().count() + 1;
+        if let Some(rest) = line.strip_prefix(';') {
+            // a text field runs until a line starting with another ;
+            let start = rest.as_ptr() as usize - s.as_ptr() as usize;
+            let mut end = None;
+            for (_, next) in lines.by_ref() {
+                let offset = next.as_ptr() as usize - s.as_ptr() as usize;
+                if next.starts_with(';') {
+                    end = Some(offset);
+                    break;
+                }
+            }
+            let Some(end) = end else {
+                return Err(ParseError {
+                    path: path.to_owned(),
+                    line: number,
+                    column: 1,
+                    token: String::from(";"),
+                    kind: ErrorKind::Expected("closing ; of text field"),
+                });
+            };
+            ret.push(Token {
+                text: s[start..end].trim_end_matches(['\r', '\n']),
+                line: number,
+                column: 2,
+                quoted: true,
+            });
+            continue;
+        }
+
+        let bytes = line.as_bytes();
+        let mut j = 0;
+        while j < bytes.len() {
+            match bytes[j] {
+                b' ' | b'\t' | b'\r' | b'\n' => j += 1,
+                b'#' => break,
+                q @ (b'\'' | b'"') => {
+                    // a quote only closes the string when followed by
+                    // whitespace
+                    let close = (j + 1..bytes.len()).find(|&k| {
+                        bytes[k] == q
+                            && bytes
+                                .get(k + 1)
+                                .is_none_or(|b| b.is_ascii_whitespace())
+                    });
+                    let Some(k) = close else {
+                        return Err(ParseError {
+                            path: path.to_owned(),
+                            line: number,
+                            column: col(j),
+                            token: line[j..].trim_end().to_owned(),
+                            kind: ErrorKind::Expected("closing quote"),
+                        });
+                    };
+                    ret.push(Token {
+                        text: &line[j + 1..k],
+                        line: number,
+                        column: col(j),
+                        quoted: true,
+                    });
+                    j = k + 1;
+                }
+                _ => {
+                    let k = (j..bytes.len())
+                        .find(|&k| bytes[k].is_ascii_whitespace())
+                        .unwrap_or(bytes.len());
+                    ret.push(Token {
+                        text: &line[j..k],
+                        line: number,
+                        column: col(j),
+                        quoted: false,
+                    });
+                    j = k;
+                }
+            }
+        }
+    }
+    Ok(ret)
+}
+
+pub struct Loop<'a> {
+    /// lowercase tag names, including the leading underscore
+    pub tags: Vec<String>,
+    pub values: Vec<Token<'a>>,
+}
+
+impl<'a> Loop<'a> {
+    pub fn column(&self, tag: &str) -> Option<usize> {
+        self.tags.iter().position(|t| t == tag)
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Token<'a>]> {
+        self.values.chunks(self.tags.len())
+    }
+}
+
+/// a `data_` block holding single tag-value items and loops
+pub struct Block<'a> {
+    pub name: &'a str,
+    /// single items by lowercase tag
+    pub items: HashMap<String, Token<'a>>,
+    pub loops: Vec<Loop<'a>>,
+}
+
+impl<'a> Block<'a> {
+    /// the value of `tag`, whether given as a single item or as a loop with
+    /// one row
+    pub fn value(&self, tag: &str) -> Option<Token<'a>> {
+        self.items.get(tag).copied().or_else(|| {
+            self.loops
+                .iter()
+                .filter(|l| l.values.len() == l.tags.len())
+                .find_map(|l| l.column(tag).map(|c| l.values[c]))
+        })
+    }
+
+    /// the loop containing `tag`
+    pub fn find_loop(&self, tag: &str) -> Option<&Loop<'a>> {
+        self.loops.iter().find(|l| l.column(tag).is_some())
+    }
+}
+
+fn is_keyword(tok: &Token, keyword: &str) -> bool {
+    !tok.quoted
+        && tok
+            .text
+            .get(..keyword.len())
+            .is_some_and(|p| p.eq_ignore_ascii_case(keyword))
+}
+
+fn is_tag(tok: &Token) -> bool {
+    !tok.quoted && tok.text.starts_with('_')
+}
+
+/// parse a CIF file into its data blocks
+pub fn parse<'a>(
+    path: &Path,
+    s: &'a str,
+) -> Result<Vec<Block<'a>>, ParseError> {
+    let tokens = tokenize(path, s)?;
+    let mut tokens = tokens.into_iter().peekable();
+    let mut blocks: Vec<Block> = Vec::new();
+    let expected =
+        |tok: &Token, what| tok.error(path, ErrorKind::Expected(what));
+    while let Some(tok) = tokens.next() {
+        if is_keyword(&tok, "data_") {
+            blocks.push(Block {
+                name: &tok.text[5..],
+                items: HashMap::new(),
+                loops: Vec::new(),
+            });
+            continue;
+        }
+        if is_keyword(&tok, "global_") || is_keyword(&tok, "save_") {
+            continue;
+        }
+        let Some(block) = blocks.last_mut() else {
+            return Err(expected(&tok, "data_ block"));
+        };
+        if is_keyword(&tok, "loop_") {
+            let mut tags = Vec::new();
+            while let Some(tag) = tokens.next_if(is_tag) {
+                tags.push(tag.text.to_ascii_lowercase());
+            }
+            if tags.is_empty() {
+                return Err(expected(&tok, "loop tags"));
+            }
+            let mut values = Vec::new();
+            while let Some(v) = tokens.next_if(|t| {
+                !is_tag(t)
+                    && !is_keyword(t, "loop_")
+                    && !is_keyword(t, "data_")
+                    && !is_keyword(t, "save_")
+            }) {
+                values.push(v);
+            }
+            if values.len() % tags.len() != 0 {
+                let last = values.last().unwrap_or(&tok);
+                return Err(expected(last, "a complete row of loop values"));
+            }
+            block.loops.push(Loop { tags, values });
+        } else if is_tag(&tok) {
+            let Some(value) = tokens.next_if(|t| !is_tag(t)) else {
+                return Err(ParseError {
+                    token: String::new(),
+                    ..tok.error(path, ErrorKind::Missing("value for tag"))
+                });
+            };
+            block.items.insert(tok.text.to_ascii_lowercase(), value);
+        } else {
+            return Err(expected(&tok, "tag or loop_"));
+        }
+    }
+    Ok(blocks)
+}
@@ -0,0 +1,429 @@
+//! Small-molecule crystallographic CIF files, as distributed by the CSD and
+//! COD. These list only the asymmetric unit, in fractional coordinates, along
+//! with the cell parameters and the symmetry operators of the space group.
+//! The full unit cell is built by applying every operator to every atom,
+//! wrapping the results into the cell and merging the copies of atoms on
+//! special positions. Files with Cartesian `_atom_site` coordinates instead
+//! are read as mmCIF.
+
+use std::path::Path;
+
+use super::{
+    cif::{self, Block, Token},
+    mmcif,
+};
+use crate::{
+    cell::Cell,
+    element,
+    error::{Error, ErrorKind, ParseError},
+    molecule::{Atom, AtomProperty, Molecule, PropertyValues, Trajectory},
+};
+
+/// images of an atom closer than this, in Å, are the same atom on a special
+/// position
+const MERGE_DISTANCE: f32 = 0.1;
+
+/// tags of the symmetry operators, in the old and new dictionaries
+const SYMOP_TAGS: [&str; 4] = [
+    "_space_group_symop_operation_xyz",
+    "_space_group_symop.operation_xyz",
+    "_symmetry_equiv_pos_as_xyz",
+    "_symmetry_equiv.pos_as_xyz",
+];
+
+const SPACE_GROUP_TAGS: [&str; 4] = [
+    "_space_group_name_h-m_alt",
+    "_space_group.name_h-m_alt",
+    "_symmetry_space_group_name_h-m",
+    "_symmetry.space_group_name_h-m",
+];
+
+/// the spellings of `_atom_site_` item `name` in the old and new
+/// dictionaries
+fn site_tags(name: &str) -> [String; 2] {
+    [format!("_atom_site_{name}"), format!("_atom_site.{name}")]
+}
+
+/// the first of `tags` with a value in `block`
+fn value<'a>(block: &Block<'a>, tags: &[&str]) -> Option<Token<'a>> {
+    tags.iter()
+        .find_map(|tag| block.value(tag))
+        .filter(|t| !t.is_null())
+}
+
+/// a symmetry operator taking fractional coordinates f to `rotation` f +
+/// `translation`
+struct Symop {
+    rotation: [[f32; 3]; 3],
+    translation: [f32; 3],
+}
+
+impl Symop {
+    const IDENTITY: Symop = Symop {
+        rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        translation: [0.0; 3],
+    };
+
+    /// parse an operator written like `-x+1/2, y, z+1/2`
+    fn parse(path: &Path, tok: &Token) -> Result<Self, ParseError> {
+        let err = || tok.error(path, ErrorKind::Expected("symmetry operator"));
+        let parts: Vec<&str> = tok.text.split(',').collect();
+        if parts.len() != 3 {
+            return Err(err());
+        }
+        let mut ret = Symop {
+            rotation: [[0.0; 3]; 3],
+            translation: [0.0; 3],
+        };
+        for (i, part) in parts.into_iter().enumerate() {
+            let (row, t) = parse_component(part).ok_or_else(err)?;
+            ret.rotation[i] = row;
+            ret.translation[i] = t;
+        }
+        Ok(ret)
+    }
+
+    fn apply(&self, f: [f32; 3]) -> [f32; 3] {
+        let r = self.rotation;
+        [0, 1, 2].map(|i| {
+            r[i][0] * f[0]
+                + r[i][1] * f[1]
+                + r[i][2] * f[2]
+                + self.translation[i]
+        })
+    }
+}
+
+/// a number such as `0.5` or a fraction such as `1/2`
+fn fraction(s: &str) -> Option<f32> {
+    match s.split_once('/') {
+        Some((n, d)) => Some(n.parse::<f32>().ok()? / d.parse::<f32>().ok()?),
+        None => s.parse().ok(),
+    }
+}
+
+/// one coordinate of a symmetry operator, such as `x-y` or `1/2+z`, as the
+/// coefficients of x, y and z and a constant
+fn parse_component(s: &str) -> Option<([f32; 3], f32)> {
+    let s: String = s
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    let mut rest = s.as_str();
+    if rest.is_empty() {
+        return None;
+    }
+    let mut row = [0.0; 3];
+    let mut t = 0.0;
+    let mut first = true;
+    while !rest.is_empty() {
+        // every term after the first starts with its sign
+        let sign = if let Some(r) = rest.strip_prefix('+') {
+            rest = r;
+            1.0
+        } else if let Some(r) = rest.strip_prefix('-') {
+            rest = r;
+            -1.0
+        } else if first {
+            1.0
+        } else {
+            return None;
+        };
+        first = false;
+        let end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '/'))
+            .unwrap_or(rest.len());
+        let (number, after) = rest.split_at(end);
+        let after = after.strip_prefix('*').unwrap_or(after);
+        let axis = after.chars().next().and_then(|c| "xyz".find(c));
+        match axis {
+            Some(k) => {
+                let coef = if number.is_empty() {
+                    1.0
+                } else {
+                    fraction(number)?
+                };
+                row[k] += sign * coef;
+                rest = &after[1..];
+            }
+            None if !number.is_empty() => {
+                t += sign * fraction(number)?;
+                rest = after;
+            }
+            None => return None,
+        }
+    }
+    Some((row, t))
+}
+
+/// the element named by an atom type such as `Fe3+` or a label such as
+/// `Cl2` or `H12A`: a capital letter followed by an optional lowercase one
+fn site_element(text: &str) -> Option<u8> {
+    let mut chars = text.chars();
+    let first = chars.next().filter(char::is_ascii_alphabetic)?;
+    match chars.next().filter(char::is_ascii_lowercase) {
+        Some(second) => element::lookup(&format!("{first}{second}"))
+            .or_else(|| element::lookup(&first.to_string())),
+        None => element::lookup(&first.to_string()),
+    }
+}
+
+fn parse_cell(path: &Path, block: &Block) -> Result<Cell, ParseError> {
+    let names = [
+        "length_a",
+        "length_b",
+        "length_c",
+        "angle_alpha",
+        "angle_beta",
+        "angle_gamma",
+    ];
+    let mut params = [0.0; 6];
+    for (p, name) in params.iter_mut().zip(names) {
+        let tags = [format!("_cell_{name}"), format!("_cell.{name}")];
+        let tok = value(block, &tags.each_ref().map(String::as_str));
+        match tok {
+            Some(t) => *p = t.number(path)?,
+            None => {
+                let at = block.loops.first().and_then(|l| l.values.first());
+                Err(ParseError {
+                    path: path.to_owned(),
+                    line: at.map_or(1, |t| t.line),
+                    column: at.map_or(1, |t| t.column),
+                    token: String::new(),
+                    kind: ErrorKind::Missing("cell parameters"),
+                })?
+            }
+        }
+    }
+    Ok(Cell::from_parameters(
+        [params[0], params[1], params[2]],
+        [params[3], params[4], params[5]],
+    ))
+}
+
+/// the symmetry operators of `block`, or just the identity if it lists none
+fn parse_symops(path: &Path, block: &Block) -> Result<Vec<Symop>, ParseError> {
+    for tag in SYMOP_TAGS {
+        if let Some(ops) = block.find_loop(tag) {
+            let c = ops.column(tag).unwrap();
+            return ops.rows().map(|row| Symop::parse(path, &row[c])).collect();
+        }
+        if let Some(tok) = block.value(tag) {
+            return Ok(vec![Symop::parse(path, &tok)?]);
+        }
+    }
+    Ok(vec![Symop::IDENTITY])
+}
+
+/// `f` moved into the cell, with each coordinate in [0, 1)
+fn wrap(f: [f32; 3]) -> [f32; 3] {
+    f.map(|x| {
+        let w = x - x.floor();
+        if w >= 1.0 {
+            0.0
+        } else {
+            w
+        }
+    })
+}
+
+/// the distance in Å between fractional positions `f` and `g` in `cell`,
+/// taking the nearest periodic image of `g`
+fn distance(cell: &Cell, f: [f32; 3], g: [f32; 3]) -> f32 {
+    let d = [0, 1, 2].map(|c| {
+        let d = f[c] - g[c];
+        d - d.round()
+    });
+    let [x, y, z] = cell.cartesian(d);
+    (x * x + y * y + z * z).sqrt()
+}
+
+/// read a small-molecule CIF file, expanding its asymmetric unit to the
+/// full unit cell, or an mmCIF file with Cartesian coordinates
+pub fn load_cif(path: impl AsRef<Path>) -> Result<Trajectory, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let blocks = cif::parse(path, &s)?;
+    let [fx, fy, fz] = ["fract_x", "fract_y", "fract_z"].map(site_tags);
+    let cartesian = site_tags("cartn_x");
+    let found = blocks.iter().find_map(|b| {
+        fx.iter()
+            .find_map(|tag| b.find_loop(tag))
+            .map(|sites| (b, sites))
+    });
+    let Some((block, sites)) = found.filter(|_| {
+        !blocks
+            .iter()
+            .any(|b| cartesian.iter().any(|tag| b.find_loop(tag).is_some()))
+    }) else {
+        return Ok(mmcif::from_blocks(path, &s, &blocks)?);
+    };
+
+    let col = |tags: &[String; 2]| tags.iter().find_map(|t| sites.column(t));
+    // the loop holds the first tag, but the others may be spelled differently
+    let missing = |what| {
+        let at = sites.values.first().copied();
+        ParseError {
+            path: path.to_owned(),
+            line: at.map_or(1, |t| t.line),
+            column: at.map_or(1, |t| t.column),
+            token: String::new(),
+            kind: ErrorKind::Missing(what),
+        }
+    };
+    let x = col(&fx).unwrap();
+    let y = col(&fy).ok_or_else(|| missing("_atom_site_fract_y"))?;
+    let z = col(&fz).ok_or_else(|| missing("_atom_site_fract_z"))?;
+    let label = col(&site_tags("label"));
+    let symbol = col(&site_tags("type_symbol"));
+    let occupancy = col(&site_tags("occupancy"));
+
+    let cell = parse_cell(path, block)?;
+    let symops = parse_symops(path, block)?;
+
+    let mut atoms = Vec::new();
+    let mut labels = Vec::new();
+    let mut occupancies = Vec::new();
+    for row in sites.rows() {
+        let get = |c: Option<usize>| c.map(|c| row[c]).filter(|t| !t.is_null());
+        let name = get(symbol).or(get(label)).unwrap_or(row[x]);
+        let w = site_element(name.text)
+            .ok_or_else(|| name.error(path, ErrorKind::UnknownElement))?;
+        let f = [
+            row[x].number(path)?,
+            row[y].number(path)?,
+            row[z].number(path)?,
+        ];
+        let occ = get(occupancy).map(|t| t.number(path)).transpose()?;
+
+        // the distinct images of this atom
+        let mut images: Vec<[f32; 3]> = Vec::new();
+        for op in &symops {
+            let g = wrap(op.apply(f));
+            if images
+                .iter()
+                .all(|&h| distance(&cell, g, h) > MERGE_DISTANCE)
+            {
+                images.push(g);
+            }
+        }
+        for g in images {
+            let [x, y, z] = cell.cartesian(g);
+            atoms.push(Atom { x, y, z, w });
+            labels.push(get(label).map_or_else(String::new, |t| t.text.into()));
+            occupancies.push(occ.unwrap_or(1.0));
+        }
+    }
+
+    let comment = ["_chemical_name_common", "_chemical_name_systematic"]
+        .into_iter()
+        .find_map(|tag| value(block, &[tag]))
+        .map_or(block.name, |t| t.text)
+        .trim()
+        .to_owned();
+    let mut properties = vec![AtomProperty {
+        name: String::from("label"),
+        ncols: 1,
+        values: PropertyValues::Str(labels),
+    }];
+    if occupancy.is_some() {
+        properties.push(AtomProperty {
+            name: String::from("occupancy"),
+            ncols: 1,
+            values: PropertyValues::Real(occupancies),
+        });
+    }
+    let mut info = Vec::new();
+    if let Some(t) = value(block, &SPACE_GROUP_TAGS) {
+        info.push((String::from("space_group"), t.text.trim().to_owned()));
+    }
+    info.push((String::from("symmetry_operators"), symops.len().to_string()));
+    Ok(Molecule {
+        atoms,
+        comment,
+        cell: Some(cell),
+        properties,
+        info,
+        ..Default::default()
+    }
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testfile(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testfiles")
+            .join(name)
+    }
+
+    fn info<'a>(mol: &'a Molecule, key: &str) -> Option<&'a str> {
+        mol.info
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    #[test]
+    fn symmetry_operators() {
+        assert_eq!(parse_component("x"), Some(([1.0, 0.0, 0.0], 0.0)));
+        assert_eq!(parse_component("1/2-y"), Some(([0.0, -1.0, 0.0], 0.5)));
+        assert_eq!(parse_component("-X+Y"), Some(([-1.0, 1.0, 0.0], 0.0)));
+        assert_eq!(parse_component("z-0.25"), Some(([0.0, 0.0, 1.0], -0.25)));
+        assert_eq!(parse_component("2*x"), Some(([2.0, 0.0, 0.0], 0.0)));
+        assert_eq!(parse_component("x y"), None);
+        assert_eq!(parse_component(""), None);
+        assert_eq!(parse_component("+"), None);
+    }
+
+    #[test]
+    fn rutile_unit_cell() {
+        let traj = load_cif(testfile("rutile.cif")).unwrap();
+        let mol = &traj.frames[0];
+        assert_eq!(mol.comment, "Rutile");
+        let elements: Vec<u8> = mol.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [22, 22, 8, 8, 8, 8]);
+        let cell = mol.cell.as_ref().unwrap();
+        let widths = cell.widths();
+        for (w, expected) in widths.iter().zip([4.5937, 4.5937, 2.9587]) {
+            assert!((w - expected).abs() < 1e-4);
+        }
+        assert_eq!(cell.pbc, [true; 3]);
+        // the second titanium at the body centre
+        let ti = &mol.atoms[1];
+        let f = cell.fractional([ti.x, ti.y, ti.z]);
+        assert!(f.iter().all(|x| (x - 0.5).abs() < 1e-5), "{f:?}");
+        assert_eq!(info(mol, "space_group"), Some("P 42/m n m"));
+        assert_eq!(info(mol, "symmetry_operators"), Some("16"));
+        let labels = mol.property("label").unwrap();
+        let expected = ["Ti1", "Ti1", "O1", "O1", "O1", "O1"];
+        assert_eq!(
+            labels.values,
+            PropertyValues::Str(expected.map(String::from).to_vec())
+        );
+        let occupancy = mol.property("occupancy").unwrap();
+        assert_eq!(occupancy.values, PropertyValues::Real(vec![1.0; 6]));
+    }
+
+    #[test]
+    fn atoms_on_special_positions_are_merged() {
+        let traj = load_cif(testfile("p21c_inversion.cif")).unwrap();
+        let mol = &traj.frames[0];
+        // four of each general position, but the chlorine sits on an
+        // inversion centre
+        let elements: Vec<u8> = mol.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [6, 6, 6, 6, 17, 17, 1, 1, 1, 1]);
+        assert_eq!(info(mol, "space_group"), Some("P 1 21/c 1"));
+        let cell = mol.cell.as_ref().unwrap();
+        let [_, beta, _] = cell.parameters().1;
+        assert!((beta - 100.0).abs() < 1e-3);
+        for a in &mol.atoms {
+            let f = cell.fractional([a.x, a.y, a.z]);
+            assert!(f.iter().all(|x| (-1e-5..1.0).contains(x)), "{f:?}");
+        }
+        assert!(mol.property("occupancy").is_none());
+    }
+}
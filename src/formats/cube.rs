@@ -0,0 +1,188 @@
+//! Gaussian cube files: two comment lines, the atom count and grid origin,
+//! the number of points and step vector along each axis, the atoms, and then
+//! the values with the last axis varying fastest. Lengths are in bohr, or in
+//! Å when the first point count is negative. A negative atom count marks a
+//! cube of several orbitals, whose indices follow the atoms and whose values
+//! are interleaved at each point; each orbital becomes its own
+//! [VolumeGrid].
+
+use std::path::Path;
+
+use super::Lines;
+use crate::{
+    element,
+    error::{Error, ErrorKind, Field, Line, ParseError},
+    molecule::{Atom, Molecule, Trajectory},
+    units::Unit,
+    volume::VolumeGrid,
+};
+
+/// the fields of the next line, requiring at least `n` of them
+fn require_fields<'a>(
+    lines: &mut Lines<'a>,
+    n: usize,
+    what: &'static str,
+) -> Result<(Line<'a>, Vec<Field<'a>>), ParseError> {
+    let line = lines.require(what)?;
+    let fields = line.fields();
+    if fields.len() < n {
+        Err(line.missing(what))?;
+    }
+    Ok((line, fields))
+}
+
+fn vector(line: &Line, fields: &[Field]) -> Result<[f32; 3], ParseError> {
+    Ok([
+        line.number(fields[0])?,
+        line.number(fields[1])?,
+        line.number(fields[2])?,
+    ])
+}
+
+pub fn load_cube(path: impl AsRef<Path>) -> Result<Trajectory, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let mut lines = Lines::new(path, &s);
+    let title = lines.require("comment line")?;
+    let description = lines.require("comment line")?;
+
+    let (line, fields) =
+        require_fields(&mut lines, 4, "atom count and origin")?;
+    let natoms: i64 = line.number(fields[0])?;
+    let origin = vector(&line, &fields[1..])?;
+    // several values per point, which some programs write instead of an
+    // orbital list
+    let nval: usize = match fields.get(4) {
+        Some(&f) => line.number(f)?,
+        None => 1,
+    };
+
+    let mut shape = [0; 3];
+    let mut axes = [[0.0; 3]; 3];
+    let mut units = Unit::Bohr;
+    for k in 0..3 {
+        let (line, fields) = require_fields(&mut lines, 4, "grid axis")?;
+        let n: i64 = line.number(fields[0])?;
+        if k == 0 && n < 0 {
+            units = Unit::Angstrom;
+        }
+        shape[k] = n.unsigned_abs() as usize;
+        axes[k] = vector(&line, &fields[1..])?;
+    }
+
+    let mut mol = Molecule {
+        comment: title.text.trim().to_owned(),
+        ..Default::default()
+    };
+    for _ in 0..natoms.unsigned_abs() {
+        let (line, fields) = require_fields(&mut lines, 5, "atom line")?;
+        let w = element::parse(fields[0].text)
+            .ok_or_else(|| line.error(fields[0], ErrorKind::UnknownElement))?;
+        let [x, y, z] = vector(&line, &fields[2..])?;
+        mol.atoms.push(Atom { x, y, z, w });
+    }
+
+    let names: Vec<String> = if natoms < 0 {
+        // the orbital count and indices, which may wrap onto several lines
+        let line = lines.require("orbital count")?;
+        let fields = line.fields();
+        let count = line.field(&fields, 0, "orbital count")?;
+        let n: usize = line.number(count)?;
+        let mut indices: Vec<String> =
+            fields[1..].iter().map(|f| f.text.to_owned()).collect();
+        while indices.len() < n {
+            let line = lines.require("orbital indices")?;
+            indices.extend(line.fields().iter().map(|f| f.text.to_owned()));
+        }
+        indices.iter().map(|i| format!("MO {i}")).collect()
+    } else if nval > 1 {
+        (1..=nval).map(|k| format!("value {k}")).collect()
+    } else {
+        let name = description.text.trim();
+        vec![if name.is_empty() { "volume" } else { name }.to_owned()]
+    };
+
+    let npoints = shape.iter().product::<usize>();
+    let nvalues = npoints * names.len();
+    let mut values = Vec::with_capacity(nvalues);
+    for line in lines.by_ref() {
+        for field in line.fields() {
+            values.push(line.number::<f32>(field)?);
+        }
+    }
+    if values.len() != nvalues {
+        Err(lines.eof.missing("a value for every grid point"))?;
+    }
+
+    mol.convert_from(units);
+    let f = units.to_angstrom();
+    let scale = |v: [f32; 3]| v.map(|x| x * f);
+    let nvol = names.len();
+    mol.volumes = names
+        .into_iter()
+        .enumerate()
+        .map(|(k, name)| VolumeGrid {
+            name,
+            origin: scale(origin),
+            axes: axes.map(scale),
+            shape,
+            values: values.iter().skip(k).step_by(nvol).copied().collect(),
+        })
+        .collect();
+    Ok(mol.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::BOHR_TO_ANGSTROM;
+
+    fn testfile(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testfiles")
+            .join(name)
+    }
+
+    #[test]
+    fn density() {
+        let traj = load_cube(testfile("h2_density.cube")).unwrap();
+        let mol = &traj.frames[0];
+        assert_eq!(mol.comment, "H2 density");
+        let elements: Vec<u8> = mol.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [1, 1]);
+        assert_eq!(mol.atoms[1].z, 0.7 * BOHR_TO_ANGSTROM);
+
+        assert_eq!(mol.volumes.len(), 1);
+        let grid = &mol.volumes[0];
+        assert_eq!(grid.name, "SCF Total Density");
+        assert_eq!(grid.shape, [4, 4, 4]);
+        assert_eq!(grid.origin, [-1.5 * BOHR_TO_ANGSTROM; 3]);
+        assert_eq!(grid.spacing(), [BOHR_TO_ANGSTROM; 3]);
+        assert_eq!(grid.values.len(), 64);
+        // z varies fastest
+        assert_eq!(
+            grid.values[..4],
+            [1.17088e-3, 8.6517e-3, 8.6517e-3, 1.17088e-3]
+        );
+        assert_eq!(grid.range(), (1.17088e-3, 0.472_367));
+    }
+
+    #[test]
+    fn interleaved_orbitals() {
+        let traj = load_cube(testfile("h2_orbitals.cube")).unwrap();
+        let mol = &traj.frames[0];
+        assert_eq!(mol.atoms.len(), 2);
+        let names: Vec<&str> =
+            mol.volumes.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, ["MO 1", "MO 2"]);
+        for grid in &mol.volumes {
+            assert_eq!(grid.shape, [4, 4, 4]);
+            assert_eq!(grid.values.len(), 64);
+        }
+        // the second orbital is twice the first at every point
+        let [a, b] = [&mol.volumes[0], &mol.volumes[1]];
+        for (x, y) in a.values.iter().zip(&b.values) {
+            assert!((2.0 * x - y).abs() < 1e-5);
+        }
+    }
+}
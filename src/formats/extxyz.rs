@@ -0,0 +1,374 @@
+//! Extended XYZ, as written by ASE and friends. The comment line holds
+//! `key=value` pairs, where `Lattice` gives the unit cell and `Properties`
+//! describes the columns of the atom lines, for example
+//! `Properties=species:S:1:pos:R:3:forces:R:3`.
+
+use crate::{
+    cell::Cell,
+    element,
+    error::{ErrorKind, Field, Line, ParseError},
+    molecule::{Atom, AtomProperty, PropertyValues},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Str,
+    Real,
+    Int,
+    Bool,
+}
+
+pub struct Column {
+    name: String,
+    kind: Kind,
+    ncols: usize,
+}
+
+/// the parsed contents of an extended XYZ comment line
+pub struct Header {
+    /// every pair other than `Lattice`, `Properties` and `pbc`
+    pub info: Vec<(String, String)>,
+    pub cell: Option<Cell>,
+    pub columns: Vec<Column>,
+}
+
+struct Pair<'a> {
+    key: String,
+    value: String,
+    /// location of the value in the line, including any quotes
+    field: Field<'a>,
+    /// whether the value was given explicitly rather than implied by a bare
+    /// key
+    explicit: bool,
+}
+
+/// read a possibly-quoted value starting at byte `start` of `text`, returning
+/// the unquoted value and the index just past it
+fn read_value(text: &str, start: usize) -> Option<(String, usize)> {
+    let rest = &text[start..];
+    match rest.chars().next() {
+        Some('"') => {
+            let mut ret = String::new();
+            let mut chars = rest.char_indices().skip(1);
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '"' => return Some((ret, start + i + 1)),
+                    '\\' => ret.push(chars.next()?.1),
+                    c => ret.push(c),
+                }
+            }
+            None
+        }
+        Some('{') => {
+            let end = rest.find('}')?;
+            Some((rest[1..end].to_owned(), start + end + 1))
+        }
+        _ => {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            Some((rest[..end].to_owned(), start + end))
+        }
+    }
+}
+
+/// split `line` into `key=value` pairs. Values may be quoted with "" or {},
+/// and a bare key is shorthand for `key=T`. Returns `None` if the line
+/// can't be split this way
+fn key_values<'a>(line: &Line<'a>) -> Option<Vec<Pair<'a>>> {
+    let text = line.text;
+    let field = |start: usize, end: usize| Field {
+        text: &text[start..end],
+        column: text[..start].chars().count() + 1,
+    };
+    let skip_ws = |i: usize| {
+        text[i..]
+            .find(|c: char| !c.is_whitespace())
+            .map_or(text.len(), |j| i + j)
+    };
+
+    let mut ret = Vec::new();
+    let mut i = skip_ws(0);
+    while i < text.len() {
+        let key_end = text[i..]
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .map_or(text.len(), |j| i + j);
+        if key_end == i {
+            return None;
+        }
+        let key = text[i..key_end].to_owned();
+        let after = skip_ws(key_end);
+        if text[after..].starts_with('=') {
+            let start = skip_ws(after + 1);
+            let (value, end) = read_value(text, start)?;
+            ret.push(Pair {
+                key,
+                value,
+                field: field(start, end),
+                explicit: true,
+            });
+            i = skip_ws(end);
+        } else {
+            ret.push(Pair {
+                key,
+                value: String::from("T"),
+                field: field(i, key_end),
+                explicit: false,
+            });
+            i = after;
+        }
+    }
+    Some(ret)
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s {
+        "T" | "True" | "true" | "TRUE" => Some(true),
+        "F" | "False" | "false" | "FALSE" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_lattice(line: &Line, pair: &Pair) -> Result<Cell, ParseError> {
+    let v: Vec<f32> = pair
+        .value
+        .split_whitespace()
+        .map(|s| s.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| line.error(pair.field, ErrorKind::InvalidNumber))?;
+    let [ax, ay, az, bx, by, bz, cx, cy, cz] = v[..] else {
+        return Err(line.error(pair.field, ErrorKind::Expected("9 numbers")));
+    };
+    Ok(Cell::new([[ax, ay, az], [bx, by, bz], [cx, cy, cz]]))
+}
+
+fn parse_pbc(line: &Line, pair: &Pair) -> Result<[bool; 3], ParseError> {
+    let v: Option<Vec<bool>> =
+        pair.value.split_whitespace().map(parse_bool).collect();
+    match v.as_deref() {
+        Some(&[a, b, c]) => Ok([a, b, c]),
+        _ => Err(line.error(pair.field, ErrorKind::Expected("3 logicals"))),
+    }
+}
+
+fn parse_properties(
+    line: &Line,
+    pair: &Pair,
+) -> Result<Vec<Column>, ParseError> {
+    let sp: Vec<&str> = pair.value.split(':').collect();
+    if !sp.len().is_multiple_of(3) {
+        return Err(line.error(
+            pair.field,
+            ErrorKind::Expected("name:type:count triples"),
+        ));
+    }
+    let columns = sp
+        .chunks(3)
+        .map(|c| {
+            let kind = match c[1] {
+                "S" => Kind::Str,
+                "R" => Kind::Real,
+                "I" => Kind::Int,
+                "L" => Kind::Bool,
+                _ => {
+                    return Err(line.error(
+                        pair.field,
+                        ErrorKind::Expected("property type S, R, I or L"),
+                    ))
+                }
+            };
+            let ncols = c[2].parse().map_err(|_| {
+                line.error(pair.field, ErrorKind::InvalidNumber)
+            })?;
+            Ok(Column {
+                name: c[0].to_owned(),
+                kind,
+                ncols,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let has = |f: fn(&Column) -> bool| columns.iter().any(f);
+    if !has(|c| is_builtin(c) && c.kind == Kind::Real)
+        || !has(|c| (is_builtin(c) && c.kind == Kind::Str) || is_z(c))
+    {
+        return Err(line.error(
+            pair.field,
+            ErrorKind::Expected("species (or Z) and pos properties"),
+        ));
+    }
+    Ok(columns)
+}
+
+/// parse `line` as an extended XYZ comment line, or return `None` if it
+/// doesn't contain any `key=value` pairs and should be treated as a plain
+/// comment
+pub fn parse_comment(line: &Line) -> Result<Option<Header>, ParseError> {
+    let Some(pairs) = key_values(line) else {
+        return Ok(None);
+    };
+    if !pairs.iter().any(|p| p.explicit) {
+        return Ok(None);
+    }
+    let mut ret = Header {
+        info: Vec::new(),
+        cell: None,
+        columns: Vec::new(),
+    };
+    let mut pbc = None;
+    for pair in &pairs {
+        match pair.key.to_ascii_lowercase().as_str() {
+            "lattice" => ret.cell = Some(parse_lattice(line, pair)?),
+            "properties" => ret.columns = parse_properties(line, pair)?,
+            "pbc" => pbc = Some(parse_pbc(line, pair)?),
+            _ => ret.info.push((pair.key.clone(), pair.value.clone())),
+        }
+    }
+    if let (Some(cell), Some(pbc)) = (&mut ret.cell, pbc) {
+        cell.pbc = pbc;
+    }
+    Ok(Some(ret))
+}
+
+/// empty property arrays for every column other than the species and
+/// positions, to be filled by [parse_atom]
+pub fn properties(columns: &[Column]) -> Vec<AtomProperty> {
+    columns
+        .iter()
+        .filter(|c| !is_builtin(c))
+        .map(|c| AtomProperty {
+            name: c.name.clone(),
+            ncols: c.ncols,
+            values: match c.kind {
+                Kind::Str => PropertyValues::Str(Vec::new()),
+                Kind::Real => PropertyValues::Real(Vec::new()),
+                Kind::Int => PropertyValues::Int(Vec::new()),
+                Kind::Bool => PropertyValues::Bool(Vec::new()),
+            },
+        })
+        .collect()
+}
+
+/// atomic numbers, used for the elements when there is no species column
+fn is_z(column: &Column) -> bool {
+    column.name == "Z" && column.kind == Kind::Int && column.ncols == 1
+}
+
+fn is_builtin(column: &Column) -> bool {
+    matches!(
+        (column.name.as_str(), column.kind, column.ncols),
+        ("species", Kind::Str, 1) | ("pos", Kind::Real, 3)
+    )
+}
+
+/// parse an atom line laid out according to `columns`, appending the values
+/// of any extra columns to `props`
+pub fn parse_atom(
+    line: &Line,
+    columns: &[Column],
+    props: &mut [AtomProperty],
+) -> Result<Atom, ParseError> {
+    let fields = line.fields();
+    let mut fields = fields.iter().copied();
+    let mut next = || fields.next().ok_or_else(|| line.missing("atom column"));
+    let mut w = None;
+    let mut pos = None;
+    let mut props = props.iter_mut();
+    for col in columns {
+        if is_builtin(col) {
+            if col.kind == Kind::Str {
+                let f = next()?;
+                w =
+                    Some(element::parse(f.text).ok_or_else(|| {
+                        line.error(f, ErrorKind::UnknownElement)
+                    })?);
+            } else {
+                pos = Some([
+                    line.number(next()?)?,
+                    line.number(next()?)?,
+                    line.number(next()?)?,
+                ]);
+            }
+            continue;
+        }
+        let prop = props.next().unwrap();
+        for _ in 0..col.ncols {
+            let f = next()?;
+            if is_z(col) && w.is_none() {
+                w =
+                    Some(element::parse(f.text).ok_or_else(|| {
+                        line.error(f, ErrorKind::UnknownElement)
+                    })?);
+            }
+            match &mut prop.values {
+                PropertyValues::Real(v) => v.push(line.number(f)?),
+                PropertyValues::Int(v) => v.push(line.number(f)?),
+                PropertyValues::Bool(v) => {
+                    v.push(parse_bool(f.text).ok_or_else(|| {
+                        line.error(f, ErrorKind::Expected("T or F"))
+                    })?)
+                }
+                PropertyValues::Str(v) => v.push(f.text.to_owned()),
+            }
+        }
+    }
+    // parse_properties ensures that both of these columns are present
+    let [x, y, z] = pos.unwrap();
+    Ok(Atom { x, y, z, w: w.unwrap() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::load_xyz;
+
+    const EXTXYZ: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/testfiles/silicon.extxyz");
+
+    #[test]
+    fn lattice_info_and_properties() {
+        let (traj, extended) = load_xyz(EXTXYZ).unwrap();
+        assert!(extended);
+        let mol = &traj.frames[0];
+        let elements: Vec<u8> = mol.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [14, 14]);
+        let si = &mol.atoms[1];
+        assert_eq!([si.x, si.y, si.z], [1.3575; 3]);
+
+        let cell = mol.cell.as_ref().unwrap();
+        assert_eq!(cell.vectors[1], [2.715, 0.0, 2.715]);
+        assert_eq!(cell.pbc, [true, true, false]);
+
+        let info: Vec<(&str, &str)> = mol
+            .info
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            info,
+            [
+                ("energy", "-10.84"),
+                ("config_type", "bulk diamond"),
+                ("relaxed", "T")
+            ]
+        );
+
+        let forces = mol.property("forces").unwrap();
+        assert_eq!(forces.ncols, 3);
+        assert_eq!(
+            forces.values,
+            PropertyValues::Real(vec![0.01, -0.02, 0.03, -0.01, 0.02, -0.03])
+        );
+        let tag = mol.property("tag").unwrap();
+        assert_eq!(tag.values, PropertyValues::Int(vec![1, 2]));
+        let fixed = mol.property("fixed").unwrap();
+        assert_eq!(fixed.values, PropertyValues::Bool(vec![true, false]));
+    }
+
+    #[test]
+    fn plain_comment_lines() {
+        let line = Line {
+            path: std::path::Path::new("plain.xyz"),
+            number: 2,
+            text: "water, optimized at B3LYP/6-31G*",
+        };
+        assert!(parse_comment(&line).unwrap().is_none());
+    }
+}
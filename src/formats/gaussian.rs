@@ -0,0 +1,244 @@
+//! Gaussian output (`.log`) files. Every orientation block is a trajectory
+//! frame, using the standard orientation unless the job ran with `NoSymm`
+//! and only printed the input orientation. SCF energies (in Hartree) go in
+//! the info of the frame they belong to, Mulliken charges become the
+//! `mulliken_charge` property and the frequency section gives the
+//! [NormalMode]s of the last frame. Only the default three-column frequency
+//! output is read, not the `HPModes` one.
+
+use std::path::Path;
+
+use super::Lines;
+use crate::{
+    element::NELEMENTS,
+    error::{Error, ErrorKind, Field, Line, ParseError},
+    molecule::{Atom, AtomProperty, Molecule, PropertyValues, Trajectory},
+    vibration::NormalMode,
+};
+
+fn is_rule(line: &Line) -> bool {
+    line.text.trim_start().starts_with("----")
+}
+
+/// the atomic number in `field`, or `None` for a dummy atom (-1)
+fn atomic_number(line: &Line, field: Field) -> Result<Option<u8>, ParseError> {
+    match line.number::<i32>(field)? {
+        -1 => Ok(None),
+        z if (0..NELEMENTS as i32).contains(&z) => Ok(Some(z as u8)),
+        _ => Err(line.error(field, ErrorKind::UnknownElement)),
+    }
+}
+
+/// the atoms of an orientation block, starting just after its title line
+fn read_orientation(lines: &mut Lines) -> Result<Vec<Atom>, ParseError> {
+    // a rule, two lines of column headings and another rule
+    for _ in 0..4 {
+        lines.require("orientation header")?;
+    }
+    let mut atoms = Vec::new();
+    loop {
+        let line = lines.require("end of orientation")?;
+        if is_rule(&line) {
+            return Ok(atoms);
+        }
+        // older versions have no atomic type column, so take the
+        // coordinates from the end
+        let fields = line.fields();
+        if fields.len() < 5 {
+            Err(line.missing("coordinates"))?;
+        }
+        let n = fields.len();
+        let Some(w) = atomic_number(&line, fields[1])? else {
+            continue;
+        };
+        atoms.push(Atom {
+            x: line.number(fields[n - 3])?,
+            y: line.number(fields[n - 2])?,
+            z: line.number(fields[n - 1])?,
+            w,
+        });
+    }
+}
+
+/// the Mulliken charges, starting just after the title line
+fn read_mulliken(lines: &mut Lines) -> Result<Vec<f32>, ParseError> {
+    // column heading
+    lines.require("Mulliken charges")?;
+    let mut charges = Vec::new();
+    loop {
+        let line = lines.require("sum of Mulliken charges")?;
+        if line.text.trim_start().starts_with("Sum of Mulliken") {
+            return Ok(charges);
+        }
+        let fields = line.fields();
+        charges.push(line.number(line.field(&fields, 2, "charge")?)?);
+    }
+}
+
+/// the `Frequencies --` line that starts a block of modes, but not the
+/// `Frequencies ---` of the `HPModes` block
+fn is_frequencies(line: &Line) -> bool {
+    let fields = line.fields();
+    fields.len() > 1
+        && fields[0].text == "Frequencies"
+        && fields[1].text == "--"
+}
+
+/// the values after `--` on lines such as `Frequencies --`
+fn values(line: &Line) -> Result<Vec<f32>, ParseError> {
+    let fields = line.fields();
+    let start = fields.iter().position(|f| f.text == "--").unwrap_or(0) + 1;
+    fields[start..].iter().map(|&f| line.number(f)).collect()
+}
+
+/// one block of up to three modes, starting at its `Frequencies --` line
+fn read_modes(
+    lines: &mut Lines,
+    first: Line,
+    natoms: usize,
+) -> Result<Vec<NormalMode>, ParseError> {
+    let mut modes: Vec<NormalMode> = values(&first)?
+        .into_iter()
+        .map(|frequency| NormalMode { frequency, ..Default::default() })
+        .collect();
+    loop {
+        let line = lines.require("normal mode displacements")?;
+        let text = line.text.trim_start();
+        if text.starts_with("Red. masses") {
+            for (mode, mass) in modes.iter_mut().zip(values(&line)?) {
+                mode.reduced_mass = Some(mass);
+            }
+        } else if text.starts_with("IR Inten") {
+            for (mode, ir) in modes.iter_mut().zip(values(&line)?) {
+                mode.ir_intensity = Some(ir);
+            }
+        } else if text.starts_with("Atom") {
+            break;
+        }
+    }
+    for _ in 0..natoms {
+        let line = lines.require("normal mode displacements")?;
+        let fields = line.fields();
+        if fields.len() != 2 + 3 * modes.len() {
+            Err(line.missing("displacements"))?;
+        }
+        for (k, mode) in modes.iter_mut().enumerate() {
+            let d = |c| line.number(fields[2 + 3 * k + c]);
+            mode.displacements.push([d(0)?, d(1)?, d(2)?]);
+        }
+    }
+    Ok(modes)
+}
+
+pub fn load_gaussian(path: impl AsRef<Path>) -> Result<Trajectory, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let orientation = if s.contains("Standard orientation:") {
+        "Standard orientation:"
+    } else {
+        "Input orientation:"
+    };
+    let mut lines = Lines::new(path, &s);
+    let mut frames: Vec<Molecule> = Vec::new();
+    while let Some(line) = lines.next() {
+        let text = line.text.trim();
+        if text == orientation {
+            let atoms = read_orientation(&mut lines)?;
+            let mol = Molecule { atoms, ..Default::default() };
+            if frames.first().is_some_and(|f| !f.same_atoms(&mol)) {
+                let field = line.fields()[0];
+                Err(line.error(field, ErrorKind::InconsistentFrame))?;
+            }
+            frames.push(mol);
+            continue;
+        }
+        // everything else describes the most recent geometry
+        let Some(mol) = frames.last_mut() else {
+            continue;
+        };
+        if text.starts_with("SCF Done:") {
+            let fields = line.fields();
+            let Some(eq) = fields.iter().position(|f| f.text == "=") else {
+                continue;
+            };
+            let energy = line.field(&fields, eq + 1, "SCF energy")?;
+            line.number::<f64>(energy)?;
+            let info = (String::from("scf_energy"), energy.text.to_owned());
+            mol.info.retain(|(k, _)| k != "scf_energy");
+            mol.info.push(info);
+        } else if text == "Mulliken charges:"
+            || text == "Mulliken atomic charges:"
+        {
+            // not the block with hydrogens summed into heavy atoms, which
+            // only lists the heavy atoms
+            let charges = read_mulliken(&mut lines)?;
+            if charges.len() != mol.atoms.len() {
+                Err(line.missing("a Mulliken charge for every atom"))?;
+            }
+            mol.properties.retain(|p| p.name != "mulliken_charge");
+            mol.properties.push(AtomProperty {
+                name: String::from("mulliken_charge"),
+                ncols: 1,
+                values: PropertyValues::Real(charges),
+            });
+        } else if text == "Optimization completed." {
+            mol.info
+                .push((String::from("optimization"), "converged".into()));
+        } else if text == "Optimization stopped." {
+            let status = String::from("not converged");
+            mol.info.push((String::from("optimization"), status));
+        } else if text.starts_with("Harmonic frequencies") {
+            mol.modes.clear();
+        } else if is_frequencies(&line) {
+            let natoms = mol.atoms.len();
+            mol.modes.extend(read_modes(&mut lines, line, natoms)?);
+        }
+    }
+    if frames.is_empty() {
+        Err(lines.eof.missing("an orientation block"))?;
+    }
+    Ok(Trajectory { frames })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/testfiles/water_opt_freq.log");
+
+    #[test]
+    fn optimization_with_frequencies() {
+        let traj = load_gaussian(LOG).unwrap();
+        assert_eq!(traj.frames.len(), 2);
+        let mol = &traj.frames[1];
+        let elements: Vec<u8> = mol.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [8, 1, 1]);
+        assert_eq!(mol.atoms[1].y, 0.758561);
+        assert_eq!(mol.atoms[2].z, -0.509469);
+
+        let info = |key: &str| {
+            mol.info
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(info("scf_energy"), Some("-74.9659011707"));
+        assert_eq!(info("optimization"), Some("converged"));
+
+        // the charges of the last geometry, not the hydrogens-summed block
+        let charges = mol.property("mulliken_charge").unwrap();
+        assert_eq!(
+            charges.values,
+            PropertyValues::Real(vec![-0.366519, 0.18326, 0.18326])
+        );
+
+        assert_eq!(mol.modes.len(), 3);
+        let mode = &mol.modes[2];
+        assert_eq!(mode.frequency, 4391.605);
+        assert_eq!(mode.reduced_mass, Some(1.0818));
+        assert_eq!(mode.ir_intensity, Some(1.2523));
+        assert_eq!(mode.displacements[2], [0.0, -0.56, -0.43]);
+        assert!(traj.frames[0].modes.is_empty());
+    }
+}
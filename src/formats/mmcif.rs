@@ -0,0 +1,354 @@
+//! Macromolecular CIF (PDBx/mmCIF) files, the archive format of the Protein
+//! Data Bank. Atoms come from the `_atom_site` loop, which gives both the
+//! author's chain and residue numbering (as in PDB files) and the archive's
+//! own `label_*` numbering; the hierarchy keeps the author's where present and
+//! records the label numbering alongside it. Each `pdbx_PDB_model_num` is a
+//! trajectory frame, and only the first alternate location of each atom is
+//! kept.
+
+use std::{collections::HashMap, path::Path};
+
+use super::cif::{self, Block, Loop, Token};
+use crate::{
+    cell::Cell,
+    element,
+    error::{Error, ErrorKind, ParseError},
+    hierarchy::{AtomSite, Chain, Hierarchy, Residue},
+    molecule::{Atom, Molecule, Trajectory},
+};
+
+/// the columns of `_atom_site` used here, as indices into each row
+struct Columns {
+    x: usize,
+    y: usize,
+    z: usize,
+    symbol: Option<usize>,
+    group: Option<usize>,
+    serial: Option<usize>,
+    altloc: Option<usize>,
+    occupancy: Option<usize>,
+    b_factor: Option<usize>,
+    model: Option<usize>,
+    /// author columns first, then label columns
+    atom_name: [Option<usize>; 2],
+    res_name: [Option<usize>; 2],
+    chain: [Option<usize>; 2],
+    number: [Option<usize>; 2],
+    insertion: Option<usize>,
+}
+
+impl Columns {
+    fn new(
+        path: &Path,
+        sites: &Loop,
+        first: &Token,
+    ) -> Result<Self, ParseError> {
+        let col = |name: &str| sites.column(&format!("_atom_site.{name}"));
+        let required = |name, what| {
+            col(name).ok_or_else(|| ParseError {
+                token: String::new(),
+                ..first.error(path, ErrorKind::Missing(what))
+            })
+        };
+        let both = |name: &str| {
+            [col(&format!("auth_{name}")), col(&format!("label_{name}"))]
+        };
+        Ok(Columns {
+            x: required("cartn_x", "_atom_site.Cartn_x column")?,
+            y: required("cartn_y", "_atom_site.Cartn_y column")?,
+            z: required("cartn_z", "_atom_site.Cartn_z column")?,
+            symbol: col("type_symbol"),
+            group: col("group_pdb"),
+            serial: col("id"),
+            altloc: col("label_alt_id"),
+            occupancy: col("occupancy"),
+            b_factor: col("b_iso_or_equiv"),
+            model: col("pdbx_pdb_model_num"),
+            atom_name: both("atom_id"),
+            res_name: both("comp_id"),
+            chain: both("asym_id"),
+            number: both("seq_id"),
+            insertion: col("pdbx_pdb_ins_code"),
+        })
+    }
+}
+
+/// the value in column `c` of `row`, or `None` if the column is absent or
+/// the value is `.` or `?`
+fn get<'a>(row: &[Token<'a>], c: Option<usize>) -> Option<Token<'a>> {
+    c.map(|c| row[c]).filter(|t| !t.is_null())
+}
+
+/// the author's value of a pair of columns, falling back on the label value
+fn preferred<'a>(
+    row: &[Token<'a>],
+    c: [Option<usize>; 2],
+) -> Option<Token<'a>> {
+    get(row, c[0]).or_else(|| get(row, c[1]))
+}
+
+fn text<'a>(tok: Option<Token<'a>>) -> &'a str {
+    tok.map_or("", |t| t.text)
+}
+
+fn optional_number<T: std::str::FromStr>(
+    path: &Path,
+    tok: Option<Token>,
+) -> Result<Option<T>, ParseError> {
+    tok.map(|t| t.number(path)).transpose()
+}
+
+/// the element of an atom without a type_symbol, from its name. A
+/// single-atom component such as `ZN` or `CL` is named after its element, so
+/// takes two letters, and other names take one, or two if one letter isn't
+/// an element, as for `MG` in a chlorophyll
+fn element_from_name(name: &str, res_name: &str) -> Option<u8> {
+    let one = || name.get(..1).and_then(element::lookup);
+    let two = || name.get(..2).and_then(element::lookup);
+    if name == res_name {
+        two().or_else(one)
+    } else {
+        one().or_else(two)
+    }
+}
+
+#[derive(Default)]
+struct Model {
+    atoms: Vec<Atom>,
+    hierarchy: Hierarchy,
+    /// altloc already chosen for each (chain, residue number, insertion,
+    /// atom name)
+    altlocs: HashMap<(String, i32, Option<char>, String), Option<char>>,
+}
+
+impl Model {
+    fn push_atom(
+        &mut self,
+        path: &Path,
+        cols: &Columns,
+        row: &[Token],
+    ) -> Result<(), ParseError> {
+        let name = text(preferred(row, cols.atom_name)).to_owned();
+        let chain_id = text(preferred(row, cols.chain)).to_owned();
+        let number_tok = preferred(row, cols.number);
+        let number = optional_number(path, number_tok)?.unwrap_or(0);
+        let insertion =
+            get(row, cols.insertion).and_then(|t| t.text.chars().next());
+        let altloc = get(row, cols.altloc).and_then(|t| t.text.chars().next());
+
+        let key = (chain_id.clone(), number, insertion, name.clone());
+        match self.altlocs.get(&key) {
+            Some(&first) if first != altloc => return Ok(()),
+            _ => {
+                self.altlocs.insert(key, altloc);
+            }
+        }
+
+        let w = match get(row, cols.symbol) {
+            Some(t) => element::lookup(t.text)
+                .ok_or_else(|| t.error(path, ErrorKind::UnknownElement))?,
+            None => {
+                let t = preferred(row, cols.atom_name).unwrap_or(row[cols.x]);
+                let res_name = text(preferred(row, cols.res_name));
+                element_from_name(t.text, res_name)
+                    .ok_or_else(|| t.error(path, ErrorKind::UnknownElement))?
+            }
+        };
+        self.atoms.push(Atom {
+            x: row[cols.x].number(path)?,
+            y: row[cols.y].number(path)?,
+            z: row[cols.z].number(path)?,
+            w,
+        });
+
+        let site = AtomSite {
+            serial: optional_number(path, get(row, cols.serial))?
+                .unwrap_or(self.atoms.len() as i64),
+            name,
+            altloc,
+            occupancy: optional_number(path, get(row, cols.occupancy))?
+                .unwrap_or(1.0),
+            b_factor: optional_number(path, get(row, cols.b_factor))?
+                .unwrap_or(0.0),
+            hetero: get(row, cols.group).is_some_and(|t| t.text == "HETATM"),
+            residue: 0,
+        };
+        let chain = Chain {
+            id: chain_id,
+            label_id: get(row, cols.chain[1]).map(|t| t.text.to_owned()),
+        };
+        let label_number = optional_number(path, get(row, cols.number[1]))?
+            .filter(|&n| n != number);
+        let residue = Residue {
+            name: text(preferred(row, cols.res_name)).to_owned(),
+            number,
+            insertion,
+            label_number,
+            chain: 0,
+        };
+        self.hierarchy.push(chain, residue, site);
+        Ok(())
+    }
+}
+
+fn parse_cell(path: &Path, block: &Block) -> Result<Option<Cell>, ParseError> {
+    let tags = [
+        "_cell.length_a",
+        "_cell.length_b",
+        "_cell.length_c",
+        "_cell.angle_alpha",
+        "_cell.angle_beta",
+        "_cell.angle_gamma",
+    ];
+    let mut params = [0.0; 6];
+    for (p, tag) in params.iter_mut().zip(tags) {
+        match block.value(tag).filter(|t| !t.is_null()) {
+            Some(t) => *p = t.number(path)?,
+            None => return Ok(None),
+        }
+    }
+    // structures without a crystal give a 1 Å cubic placeholder
+    if params[..3].iter().all(|&l| l == 1.0) {
+        return Ok(None);
+    }
+    // as for PDB files, the atoms are only the asymmetric unit
+    Ok(Some(Cell {
+        pbc: [false; 3],
+        ..Cell::from_parameters(
+            [params[0], params[1], params[2]],
+            [params[3], params[4], params[5]],
+        )
+    }))
+}
+
+/// read the atoms of the first data block with an `_atom_site` loop
+pub fn load_mmcif(path: impl AsRef<Path>) -> Result<Trajectory, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let blocks = cif::parse(path, &s)?;
+    Ok(from_blocks(path, &s, &blocks)?)
+}
+
+/// the trajectory in `blocks`, parsed from the file `s` at `path`
+pub(super) fn from_blocks(
+    path: &Path,
+    s: &str,
+    blocks: &[Block],
+) -> Result<Trajectory, ParseError> {
+    let found = blocks.iter().find_map(|b| {
+        b.find_loop("_atom_site.cartn_x").map(|sites| (b, sites))
+    });
+    let Some((block, sites)) = found else {
+        Err(ParseError {
+            path: path.to_owned(),
+            line: s.lines().count() + 1,
+            column: 1,
+            token: String::new(),
+            kind: ErrorKind::Missing("_atom_site loop"),
+        })?
+    };
+    let Some(first) = sites.values.first() else {
+        Err(ParseError {
+            path: path.to_owned(),
+            line: s.lines().count() + 1,
+            column: 1,
+            token: String::new(),
+            kind: ErrorKind::Missing("_atom_site rows"),
+        })?
+    };
+    let cols = Columns::new(path, sites, first)?;
+
+    // models in order of first appearance, with the first row of each for
+    // reporting mismatched frames
+    let mut models: Vec<(Option<&str>, Token, Model)> = Vec::new();
+    for row in sites.rows() {
+        let id = get(row, cols.model).map(|t| t.text);
+        let k = match models.iter().position(|m| m.0 == id) {
+            Some(k) => k,
+            None => {
+                models.push((id, row[0], Model::default()));
+                models.len() - 1
+            }
+        };
+        models[k].2.push_atom(path, &cols, row)?;
+    }
+
+    let title = block
+        .value("_struct.title")
+        .filter(|t| !t.is_null())
+        .map_or(block.name, |t| t.text)
+        .trim()
+        .to_owned();
+    let cell = parse_cell(path, block)?;
+    let mut frames = Vec::with_capacity(models.len());
+    for (k, (_, start, model)) in models.into_iter().enumerate() {
+        let mol = Molecule {
+            atoms: model.atoms,
+            comment: title.clone(),
+            cell: cell.clone(),
+            properties: model.hierarchy.properties(),
+            hierarchy: Some(model.hierarchy),
+            ..Default::default()
+        };
+        if k > 0 && !mol.same_atoms(&frames[0]) {
+            Err(start.error(path, ErrorKind::InconsistentFrame))?;
+        }
+        frames.push(mol);
+    }
+    Ok(Trajectory { frames })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CIF: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/testfiles/gly_altloc_models.cif"
+    );
+    const ZINC: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/testfiles/zinc_site.cif");
+
+    #[test]
+    fn models_residues_and_altlocs() {
+        let traj = load_mmcif(CIF).unwrap();
+        assert_eq!(traj.frames.len(), 2);
+        let mol = &traj.frames[0];
+        // the unquoted title isn't ASCII
+        assert_eq!(mol.comment, "αβγ-glycine");
+        assert!(mol.cell.is_none());
+        // only the first of the alternate locations is kept
+        let elements: Vec<u8> = mol.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [7, 6, 8]);
+        let ca = &traj.frames[1].atoms[1];
+        assert_eq!([ca.x, ca.y, ca.z], [1.55, 0.0, 0.0]);
+
+        let h = mol.hierarchy.as_ref().unwrap();
+        let chain = &h.chains[0];
+        assert_eq!(
+            (chain.id.as_str(), chain.label_id.as_deref()),
+            ("B", Some("A"))
+        );
+        let gly = &h.residues[0];
+        assert_eq!((gly.name.as_str(), gly.number), ("GLY", 5));
+        assert_eq!(gly.label_number, Some(1));
+        assert_eq!(h.residues[1].name, "HOH");
+        assert_eq!(h.sites[1].altloc, Some('A'));
+        assert_eq!(h.sites[1].occupancy, 0.5);
+        assert!(h.sites[2].hetero);
+    }
+
+    #[test]
+    fn elements_from_atom_names() {
+        let traj = load_mmcif(ZINC).unwrap();
+        let elements: Vec<u8> =
+            traj.frames[0].atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [7, 6, 16, 30, 17, 12]);
+        // the same as the author's numbering
+        let h = traj.frames[0].hierarchy.as_ref().unwrap();
+        assert_eq!(
+            (h.residues[0].number, h.residues[0].label_number),
+            (1, None)
+        );
+    }
+}
@@ -0,0 +1,417 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    fs::{read_to_string, File},
+    io::Read,
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{
+    bonds::{infer_orders, perceive_bonds, BondOptions},
+    error::{self, Error, Line, ParseError},
+    molecule::Trajectory,
+    orbital::Selection,
+    units::{self, Unit},
+};
+
+mod cfour;
+mod cif;
+mod crystal;
+mod cube;
+mod extxyz;
+mod gaussian;
+mod mmcif;
+mod mol2;
+mod molden;
+mod orca;
+mod pdb;
+mod psi4;
+mod sdf;
+mod vasp;
+mod xyz;
+mod zmatrix;
+
+pub use cfour::{load_cfour, load_fcmfinal, load_zmat};
+pub use crystal::load_cif;
+pub use cube::load_cube;
+pub use gaussian::load_gaussian;
+pub use mmcif::load_mmcif;
+pub use mol2::load_mol2;
+pub use molden::load_molden;
+pub use orca::load_orca;
+pub use pdb::load_pdb;
+pub use psi4::load_psi4;
+pub use sdf::load_sdf;
+pub use vasp::{load_outcar, load_poscar, load_xdatcar};
+pub use xyz::load_xyz;
+pub use zmatrix::{load_zmatrix, write_zmatrix};
+
+/// how much of a file [Format::from_contents] looks at
+const BANNER_BYTES: u64 = 1 << 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Xyz,
+    Pdb,
+    Cif,
+    Mmcif,
+    Sdf,
+    Mol2,
+    Gaussian,
+    Orca,
+    Psi4,
+    Cfour,
+    CfourZmat,
+    CfourFcm,
+    Cube,
+    Molden,
+    Poscar,
+    Outcar,
+    Xdatcar,
+    Zmatrix,
+}
+
+impl Format {
+    pub const ALL: [Format; 18] = [
+        Format::Xyz,
+        Format::Pdb,
+        Format::Cif,
+        Format::Mmcif,
+        Format::Sdf,
+        Format::Mol2,
+        Format::Gaussian,
+        Format::Orca,
+        Format::Psi4,
+        Format::Cfour,
+        Format::CfourZmat,
+        Format::CfourFcm,
+        Format::Cube,
+        Format::Molden,
+        Format::Poscar,
+        Format::Outcar,
+        Format::Xdatcar,
+        Format::Zmatrix,
+    ];
+
+    /// guess the format of `path` from its extension, or its name for files
+    /// with fixed names. VASP files are often renamed with a suffix, as in
+    /// `POSCAR.relaxed`, so only the start of their names matters
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let path = path.as_ref();
+        let name = path.file_name()?.to_str()?;
+        match name {
+            "ZMAT" => return Some(Format::CfourZmat),
+            "FCMFINAL" => return Some(Format::CfourFcm),
+            _ => {}
+        }
+        let vasp = [
+            ("POSCAR", Format::Poscar),
+            ("CONTCAR", Format::Poscar),
+            ("OUTCAR", Format::Outcar),
+            ("XDATCAR", Format::Xdatcar),
+        ];
+        if let Some((_, format)) = vasp
+            .into_iter()
+            .find(|(prefix, _)| name.starts_with(prefix))
+        {
+            return Some(format);
+        }
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "xyz" | "extxyz" => Some(Format::Xyz),
+            "pdb" | "ent" => Some(Format::Pdb),
+            "cif" => Some(Format::Cif),
+            "mmcif" => Some(Format::Mmcif),
+            "mol" | "sdf" | "sd" => Some(Format::Sdf),
+            "mol2" => Some(Format::Mol2),
+            "log" => Some(Format::Gaussian),
+            "cube" | "cub" => Some(Format::Cube),
+            "molden" | "molf" => Some(Format::Molden),
+            "vasp" | "poscar" => Some(Format::Poscar),
+            "zmat" | "gzmat" | "gjf" | "com" => Some(Format::Zmatrix),
+            _ => None,
+        }
+    }
+
+    /// recognize the output of a quantum chemistry program from the banner
+    /// near the start of the file at `path`
+    pub fn from_contents(path: &Path) -> Result<Option<Self>, Error> {
+        let io = |source| Error::Io { path: path.to_owned(), source };
+        let mut start = Vec::new();
+        File::open(path)
+            .and_then(|f| f.take(BANNER_BYTES).read_to_end(&mut start))
+            .map_err(io)?;
+        let start = String::from_utf8_lossy(&start);
+        let banners = [
+            ("[Molden Format]", Format::Molden),
+            ("Entering Gaussian System", Format::Gaussian),
+            ("O   R   C   A", Format::Orca),
+            ("Psi4: An Open-Source", Format::Psi4),
+            ("CFOUR", Format::Cfour),
+        ];
+        Ok(banners
+            .into_iter()
+            .find(|(banner, _)| start.contains(banner))
+            .map(|(_, format)| format))
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Format::Xyz => "xyz",
+            Format::Pdb => "pdb",
+            Format::Cif => "cif",
+            Format::Mmcif => "mmcif",
+            Format::Sdf => "sdf",
+            Format::Mol2 => "mol2",
+            Format::Gaussian => "gaussian",
+            Format::Orca => "orca",
+            Format::Psi4 => "psi4",
+            Format::Cfour => "cfour",
+            Format::CfourZmat => "cfour-zmat",
+            Format::CfourFcm => "cfour-fcm",
+            Format::Cube => "cube",
+            Format::Molden => "molden",
+            Format::Poscar => "poscar",
+            Format::Outcar => "outcar",
+            Format::Xdatcar => "xdatcar",
+            Format::Zmatrix => "zmatrix",
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown format `{s}`"))
+    }
+}
+
+/// read the whole of `path` into a string
+pub fn read(path: &Path) -> Result<String, Error> {
+    read_to_string(path)
+        .map_err(|source| Error::Io { path: path.to_owned(), source })
+}
+
+/// a cursor over the [Line]s of a file, for formats read a section at a time
+struct Lines<'a> {
+    lines: Vec<Line<'a>>,
+    pos: usize,
+    /// just past the last line, for reporting things missing at the end
+    eof: Line<'a>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(path: &'a Path, s: &'a str) -> Self {
+        let lines: Vec<Line> = error::lines(path, s).collect();
+        let eof = Line {
+            path,
+            number: lines.len() + 1,
+            text: "",
+        };
+        Self { lines, pos: 0, eof }
+    }
+
+    /// the next line, or an error describing it as `what` if the file ended
+    fn require(&mut self, what: &'static str) -> Result<Line<'a>, ParseError> {
+        self.next().ok_or_else(|| self.eof.missing(what))
+    }
+
+    fn peek(&self) -> Option<Line<'a>> {
+        self.lines.get(self.pos).copied()
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = Line<'a>;
+
+    fn next(&mut self) -> Option<Line<'a>> {
+        let line = self.peek()?;
+        self.pos += 1;
+        Some(line)
+    }
+}
+
+/// options controlling how files are read
+#[derive(Clone, Debug, Default)]
+pub struct LoadOptions {
+    /// read every file as this format instead of guessing from the extension
+    pub format: Option<Format>,
+    /// length units for formats that don't specify their own, or `None` to
+    /// guess
+    pub units: Option<Unit>,
+    pub bonds: BondOptions,
+    /// orbitals to evaluate on a grid for formats with a basis set and
+    /// orbitals but no grids
+    pub orbitals: Vec<Selection>,
+    /// move the atoms of periodic structures into their cell
+    pub wrap: bool,
+    /// repeat periodic structures this many times along each lattice vector
+    pub replicate: Option<[usize; 3]>,
+}
+
+/// how to find the bonds of a freshly loaded file
+#[derive(PartialEq)]
+enum Bonding {
+    /// from the geometry alone
+    Perceive,
+    /// from the geometry, adding any bonds given in the file
+    Merge,
+    /// exactly as given in the file, orders included
+    Explicit,
+}
+
+/// load the molecules or trajectories at `path`, using `opts.format` if
+/// given and otherwise guessing from the file name. Files with unrecognized
+/// names are identified by [Format::from_contents] if possible and read as
+/// XYZ otherwise. Most files hold a single trajectory, but each record of an
+/// SD or MOL2 file is returned separately. For formats that don't specify
+/// their units, coordinates are taken to be in `opts.units`, or guessed with
+/// [units::detect] if that is `None`, except that extended XYZ is always in
+/// Å. Periodic structures are then wrapped and replicated as `opts` asks
+/// before their bonds are found
+pub fn load(
+    path: impl AsRef<Path>,
+    opts: &LoadOptions,
+) -> Result<Vec<Trajectory>, Error> {
+    let format = match opts.format.or_else(|| Format::from_path(&path)) {
+        Some(format) => format,
+        None => Format::from_contents(path.as_ref())?.unwrap_or(Format::Xyz),
+    };
+    let (mut trajs, bonding) = match format {
+        Format::Xyz => {
+            let (mut traj, extended) = load_xyz(path)?;
+            let units = opts.units.unwrap_or_else(|| {
+                if extended {
+                    Unit::Angstrom
+                } else {
+                    units::detect(&traj.frames[0])
+                }
+            });
+            for frame in &mut traj.frames {
+                frame.convert_from(units);
+            }
+            (vec![traj], Bonding::Perceive)
+        }
+        Format::Pdb => (vec![load_pdb(path)?], Bonding::Merge),
+        Format::Cif => (vec![load_cif(path)?], Bonding::Perceive),
+        Format::Mmcif => (vec![load_mmcif(path)?], Bonding::Perceive),
+        Format::Gaussian => (vec![load_gaussian(path)?], Bonding::Perceive),
+        Format::Orca => (vec![load_orca(path)?], Bonding::Perceive),
+        Format::Psi4 => (vec![load_psi4(path)?], Bonding::Perceive),
+        Format::Cfour => (vec![load_cfour(path)?], Bonding::Perceive),
+        Format::CfourZmat => (vec![load_zmat(path)?.into()], Bonding::Perceive),
+        Format::CfourFcm => {
+            (vec![load_fcmfinal(path)?.into()], Bonding::Perceive)
+        }
+        Format::Cube => (vec![load_cube(path)?], Bonding::Perceive),
+        Format::Poscar => (vec![load_poscar(path)?.into()], Bonding::Perceive),
+        Format::Outcar => (vec![load_outcar(path)?], Bonding::Perceive),
+        Format::Xdatcar => (vec![load_xdatcar(path)?], Bonding::Perceive),
+        Format::Zmatrix => {
+            let (mut mol, stated) = load_zmatrix(path)?;
+            let units =
+                stated.or(opts.units).unwrap_or_else(|| units::detect(&mol));
+            mol.convert_from(units);
+            (vec![mol.into()], Bonding::Perceive)
+        }
+        Format::Molden => {
+            (vec![load_molden(path, &opts.orbitals)?], Bonding::Perceive)
+        }
+        Format::Sdf | Format::Mol2 => {
+            let mols = if format == Format::Sdf {
+                load_sdf(path)?
+            } else {
+                load_mol2(path)?
+            };
+            let trajs = mols.into_iter().map(Trajectory::from).collect();
+            (trajs, Bonding::Explicit)
+        }
+    };
+    for frame in trajs.iter_mut().flat_map(|t| &mut t.frames) {
+        if opts.wrap {
+            frame.wrap();
+        }
+        if let Some(n) = opts.replicate {
+            frame.replicate(n);
+        }
+    }
+    if bonding == Bonding::Explicit {
+        return Ok(trajs);
+    }
+    for frame in trajs.iter_mut().flat_map(|t| &mut t.frames) {
+        let mut bonds = perceive_bonds(frame, &opts.bonds);
+        if bonding == Bonding::Merge {
+            let found: HashSet<_> = bonds.iter().map(|b| (b.i, b.j)).collect();
+            bonds.extend(
+                frame.bonds.iter().filter(|b| !found.contains(&(b.i, b.j))),
+            );
+        }
+        infer_orders(frame, &mut bonds);
+        frame.bonds = bonds;
+    }
+    Ok(trajs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testfile(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testfiles")
+            .join(name)
+    }
+
+    #[test]
+    fn units_of_xyz_are_detected() {
+        let opts = LoadOptions::default();
+        let trajs = load(testfile("acetaldehyde.xyz"), &opts).unwrap();
+        let mol = &trajs[0].frames[0];
+        assert_eq!(mol.units, Unit::Bohr);
+        assert_eq!(mol.atoms[1].z, 2.845_112 * units::BOHR_TO_ANGSTROM);
+
+        let opts = LoadOptions {
+            units: Some(Unit::Angstrom),
+            ..Default::default()
+        };
+        let trajs = load(testfile("acetaldehyde.xyz"), &opts).unwrap();
+        assert_eq!(trajs[0].frames[0].atoms[1].z, 2.845_112);
+    }
+
+    #[test]
+    fn extended_xyz_is_in_angstrom() {
+        // the same bohr coordinates, but extended XYZ says they are in Å
+        let opts = LoadOptions::default();
+        let trajs = load(testfile("acetaldehyde.extxyz"), &opts).unwrap();
+        let mol = &trajs[0].frames[0];
+        assert_eq!(mol.units, Unit::Angstrom);
+        assert_eq!(mol.atoms[1].z, 2.845_112);
+    }
+
+    #[test]
+    fn units_of_bare_zmatrices_follow_the_options() {
+        let opts = LoadOptions {
+            units: Some(Unit::Bohr),
+            ..Default::default()
+        };
+        let trajs = load(testfile("h2.zmat"), &opts).unwrap();
+        let mol = &trajs[0].frames[0];
+        assert_eq!(mol.units, Unit::Bohr);
+        assert_eq!(mol.atoms[1].z, 1.4 * units::BOHR_TO_ANGSTROM);
+
+        // but a Gaussian route section says what they are
+        let trajs = load(testfile("h2o2.gjf"), &opts).unwrap();
+        assert_eq!(trajs[0].frames[0].atoms[1].z, 1.4);
+    }
+}
@@ -0,0 +1,243 @@
+//! Tripos MOL2 files. Each `@<TRIPOS>MOLECULE` block is a separate molecule
+//! with explicit bonds, SYBYL atom types, optional partial charges and the
+//! substructures (usually residues) the atoms belong to. Amide (`am`),
+//! dummy (`du`) and unknown (`un`) bonds are read as single bonds, and
+//! unconnected (`nc`) ones are dropped. Coordinates are in Å.
+
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    element,
+    error::{self, Error, ErrorKind, Line, ParseError},
+    molecule::{Atom, AtomProperty, Bond, BondOrder, Molecule, PropertyValues},
+};
+
+/// SYBYL atom types that aren't elements, read as dummy atoms
+const DUMMY_TYPES: [&str; 6] = ["Du", "LP", "Any", "Hal", "Het", "Hev"];
+
+#[derive(Default)]
+struct Record {
+    mol: Molecule,
+    /// lines of the MOLECULE section read so far
+    header: usize,
+    no_charges: bool,
+    /// atom index by atom id
+    ids: HashMap<i64, usize>,
+    names: Vec<String>,
+    types: Vec<String>,
+    charges: Vec<Option<f32>>,
+    subst_ids: Vec<Option<i64>>,
+    subst_names: Vec<Option<String>>,
+    /// substructure names by id from the SUBSTRUCTURE section
+    substructures: HashMap<i64, String>,
+}
+
+impl Record {
+    fn read_header(&mut self, line: &Line) {
+        match self.header {
+            0 => self.mol.comment = line.text.trim().to_owned(),
+            2 => {
+                let kind = line.text.trim().to_owned();
+                self.mol.info.push((String::from("mol_type"), kind));
+            }
+            3 => {
+                let charges = line.text.trim();
+                self.no_charges = charges == "NO_CHARGES";
+                let kind = charges.to_owned();
+                self.mol.info.push((String::from("charge_type"), kind));
+            }
+            _ => {}
+        }
+        self.header += 1;
+    }
+
+    fn read_atom(&mut self, line: &Line) -> Result<(), ParseError> {
+        let fields = line.fields();
+        let id = line.number(line.field(&fields, 0, "atom id")?)?;
+        let name = line.field(&fields, 1, "atom name")?;
+        let coord = |i, what| line.number(line.field(&fields, i, what)?);
+        let kind = line.field(&fields, 5, "atom type")?;
+        let symbol = kind.text.split('.').next().unwrap_or_default();
+        let w = if DUMMY_TYPES.contains(&symbol) {
+            0
+        } else {
+            element::lookup(symbol)
+                .ok_or_else(|| line.error(kind, ErrorKind::UnknownElement))?
+        };
+        self.ids.insert(id, self.mol.atoms.len());
+        self.mol.atoms.push(Atom {
+            x: coord(2, "x coordinate")?,
+            y: coord(3, "y coordinate")?,
+            z: coord(4, "z coordinate")?,
+            w,
+        });
+        self.names.push(name.text.to_owned());
+        self.types.push(kind.text.to_owned());
+        let subst_id = fields.get(6).map(|&f| line.number(f)).transpose()?;
+        self.subst_ids.push(subst_id);
+        self.subst_names
+            .push(fields.get(7).map(|f| f.text.to_owned()));
+        let charge = fields.get(8).map(|&f| line.number(f)).transpose()?;
+        self.charges.push(charge);
+        Ok(())
+    }
+
+    fn read_bond(&mut self, line: &Line) -> Result<(), ParseError> {
+        let fields = line.fields();
+        let atom = |i, what| {
+            let f = line.field(&fields, i, what)?;
+            self.ids
+                .get(&line.number(f)?)
+                .copied()
+                .ok_or_else(|| line.error(f, ErrorKind::Expected("atom id")))
+        };
+        let (i, j) = (atom(1, "origin atom")?, atom(2, "target atom")?);
+        let kind = line.field(&fields, 3, "bond type")?;
+        let order = match kind.text {
+            "1" | "am" | "du" | "un" => BondOrder::Single,
+            "2" => BondOrder::Double,
+            "3" => BondOrder::Triple,
+            "ar" => BondOrder::Aromatic,
+            "nc" => return Ok(()),
+            _ => Err(line.error(kind, ErrorKind::Expected("bond type")))?,
+        };
+        self.mol.bonds.push(Bond::new(i.min(j), i.max(j), order));
+        Ok(())
+    }
+
+    fn read_substructure(&mut self, line: &Line) -> Result<(), ParseError> {
+        let fields = line.fields();
+        let id = line.number(line.field(&fields, 0, "substructure id")?)?;
+        let name = line.field(&fields, 1, "substructure name")?;
+        self.substructures.insert(id, name.text.to_owned());
+        Ok(())
+    }
+
+    fn finish(self, line: Line) -> Result<Molecule, ParseError> {
+        let mut mol = self.mol;
+        if mol.atoms.is_empty() {
+            Err(line.missing("@<TRIPOS>ATOM section"))?;
+        }
+        let prop = |name: &str, values| AtomProperty {
+            name: name.to_owned(),
+            ncols: 1,
+            values,
+        };
+        mol.properties
+            .push(prop("atom_name", PropertyValues::Str(self.names)));
+        mol.properties
+            .push(prop("atom_type", PropertyValues::Str(self.types)));
+        let charges: Option<Vec<f32>> = self.charges.into_iter().collect();
+        if let Some(charges) = charges.filter(|_| !self.no_charges) {
+            let values = PropertyValues::Real(charges);
+            mol.properties.push(prop("partial_charge", values));
+        }
+        // prefer the name on the atom line, which is the one docking
+        // programs keep up to date
+        let substructures: Option<Vec<String>> = self
+            .subst_names
+            .into_iter()
+            .zip(self.subst_ids)
+            .map(|(name, id)| {
+                name.or_else(|| self.substructures.get(&id?).cloned())
+            })
+            .collect();
+        if let Some(names) = substructures {
+            let values = PropertyValues::Str(names);
+            mol.properties.push(prop("substructure", values));
+        }
+        Ok(mol)
+    }
+}
+
+/// read every molecule of a MOL2 file
+pub fn load_mol2(path: impl AsRef<Path>) -> Result<Vec<Molecule>, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let mut ret = Vec::new();
+    let mut cur: Option<(Line, Record)> = None;
+    let mut section = "";
+    for line in error::lines(path, &s) {
+        let text = line.text.trim();
+        if text.starts_with('#') || (text.is_empty() && section != "MOLECULE") {
+            continue;
+        }
+        if let Some(name) = text.strip_prefix("@<TRIPOS>") {
+            section = name;
+            if name == "MOLECULE" {
+                if let Some((start, rec)) = cur.take() {
+                    ret.push(rec.finish(start)?);
+                }
+                cur = Some((line, Record::default()));
+            }
+            continue;
+        }
+        let Some((_, rec)) = &mut cur else {
+            let field = line.fields()[0];
+            Err(line.error(field, ErrorKind::Expected("@<TRIPOS>MOLECULE")))?
+        };
+        match section {
+            "MOLECULE" => rec.read_header(&line),
+            "ATOM" => rec.read_atom(&line)?,
+            "BOND" => rec.read_bond(&line)?,
+            "SUBSTRUCTURE" => rec.read_substructure(&line)?,
+            _ => {}
+        }
+    }
+    match cur {
+        Some((start, rec)) => ret.push(rec.finish(start)?),
+        None => {
+            let eof = Line {
+                path,
+                number: s.lines().count() + 1,
+                text: "",
+            };
+            Err(eof.missing("@<TRIPOS>MOLECULE section"))?
+        }
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOL2: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/testfiles/docked_poses.mol2");
+
+    #[test]
+    fn molecules_bond_types_and_charges() {
+        let mols = load_mol2(MOL2).unwrap();
+        assert_eq!(mols.len(), 2);
+
+        let pose = &mols[0];
+        assert_eq!(pose.comment, "pose1");
+        let elements: Vec<u8> = pose.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [6, 7, 1]);
+        let h = &pose.atoms[2];
+        assert_eq!([h.x, h.y, h.z], [1.8, 0.9, 0.0]);
+        let bonds: Vec<_> =
+            pose.bonds.iter().map(|b| (b.i, b.j, b.order)).collect();
+        // amide bonds are single
+        assert_eq!(
+            bonds,
+            [(0, 1, BondOrder::Single), (1, 2, BondOrder::Single)]
+        );
+        let charges = pose.property("partial_charge").unwrap();
+        assert_eq!(
+            charges.values,
+            PropertyValues::Real(vec![-0.05, -0.3, 0.2])
+        );
+        let types = pose.property("atom_type").unwrap();
+        let expected = ["C.ar", "N.am", "H"].map(String::from).to_vec();
+        assert_eq!(types.values, PropertyValues::Str(expected));
+        let substructure = pose.property("substructure").unwrap();
+        let expected = vec![String::from("LIG1"); 3];
+        assert_eq!(substructure.values, PropertyValues::Str(expected));
+
+        // NO_CHARGES, and an aromatic bond
+        let pose = &mols[1];
+        assert!(pose.property("partial_charge").is_none());
+        assert_eq!(pose.bonds[0].order, BondOrder::Aromatic);
+    }
+}
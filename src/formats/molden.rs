@@ -0,0 +1,461 @@
+//! Molden files: bracketed sections giving the atoms (`[Atoms]`, in Å or in
+//! bohr with `AU`), a Gaussian basis set (`[GTO]`), the molecular orbitals
+//! (`[MO]`) and the normal modes (`[FREQ]`, `[FR-COORD]`, `[FR-NORM-COORD]`
+//! and `[INT]`). Basis functions are Cartesian unless `[5D]`, `[5D7F]`,
+//! `[5D10F]`, `[7F]` or `[9G]` say otherwise. There are no grids in the file,
+//! so the requested orbitals are evaluated on one around the molecule to
+//! give its [VolumeGrid]s.
+//!
+//! [VolumeGrid]: crate::volume::VolumeGrid
+
+use std::path::Path;
+
+use super::Lines;
+use crate::{
+    element,
+    error::{Error, ErrorKind, Field, Line, ParseError},
+    molecule::{Atom, Molecule, Trajectory},
+    orbital::{Orbital, Selection, Shell, Spin, Wavefunction, MAX_L},
+    units::{Unit, BOHR_TO_ANGSTROM},
+    vibration::NormalMode,
+};
+
+/// the orbitals evaluated when none are requested
+const DEFAULT_ORBITALS: [Selection; 2] =
+    [Selection::Homo(0), Selection::Lumo(0)];
+
+/// the lowercased name of the section started by `line`, such as `atoms`
+/// for `[Atoms] AU`, and the rest of the line
+fn header<'a>(line: &Line<'a>) -> Option<(String, &'a str)> {
+    let rest = line.text.trim().strip_prefix('[')?;
+    let (name, rest) = rest.split_once(']')?;
+    Some((name.trim().to_ascii_lowercase(), rest.trim()))
+}
+
+/// the next non-blank line of the current section, if any
+fn section_line<'a>(lines: &mut Lines<'a>) -> Option<Line<'a>> {
+    while let Some(line) = lines.peek() {
+        if header(&line).is_some() {
+            return None;
+        }
+        lines.next();
+        if !line.text.trim().is_empty() {
+            return Some(line);
+        }
+    }
+    None
+}
+
+/// a number that may be written with a Fortran `D` exponent
+fn real(line: &Line, field: Field) -> Result<f64, ParseError> {
+    field
+        .text
+        .replace(['D', 'd'], "E")
+        .parse()
+        .map_err(|_| line.error(field, ErrorKind::InvalidNumber))
+}
+
+/// the atoms of an `[Atoms]` or `[FR-COORD]` section, whose lines hold a
+/// label, optionally an index and atomic number, and the coordinates
+fn read_atoms(
+    lines: &mut Lines,
+    with_number: bool,
+) -> Result<Vec<Atom>, ParseError> {
+    let mut atoms = Vec::new();
+    while let Some(line) = section_line(lines) {
+        let fields = line.fields();
+        let (w, first) = if with_number {
+            let z = line.field(&fields, 2, "atomic number")?;
+            let w = element::parse(z.text)
+                .ok_or_else(|| line.error(z, ErrorKind::UnknownElement))?;
+            (w, 3)
+        } else {
+            let label = fields[0];
+            let symbol =
+                label.text.trim_end_matches(|c: char| !c.is_alphabetic());
+            let w = element::lookup(symbol)
+                .ok_or_else(|| line.error(label, ErrorKind::UnknownElement))?;
+            (w, 1)
+        };
+        let coord = |i: usize, what| {
+            line.number(line.field(&fields, first + i, what)?)
+        };
+        atoms.push(Atom {
+            x: coord(0, "x coordinate")?,
+            y: coord(1, "y coordinate")?,
+            z: coord(2, "z coordinate")?,
+            w,
+        });
+    }
+    Ok(atoms)
+}
+
+/// a shell of the `[GTO]` section before the basis is known to be Cartesian
+/// or spherical
+struct RawShell {
+    atom: usize,
+    l: usize,
+    exponents: Vec<f64>,
+    coefficients: Vec<f64>,
+}
+
+/// the shells of a `[GTO]` section, where each atom's shells follow a line
+/// giving its index. Each shell is a line with its type and number of
+/// primitives, then an exponent and coefficient per primitive; `sp` shells
+/// have an s and a p coefficient
+fn read_gto(
+    lines: &mut Lines,
+    natoms: usize,
+) -> Result<Vec<RawShell>, ParseError> {
+    let mut shells = Vec::new();
+    let mut atom = None;
+    while let Some(line) = section_line(lines) {
+        let fields = line.fields();
+        let first = fields[0];
+        if let Ok(index) = first.text.parse::<usize>() {
+            if !(1..=natoms).contains(&index) {
+                Err(line.error(first, ErrorKind::Expected("atom number")))?;
+            }
+            atom = Some(index - 1);
+            continue;
+        }
+        let Some(atom) = atom else {
+            Err(line.error(first, ErrorKind::Expected("atom number")))?
+        };
+        let label = first.text.to_ascii_lowercase();
+        let ls: &[usize] = match label.as_str() {
+            "sp" => &[0, 1],
+            _ => match "spdfg".find(label.as_str()) {
+                Some(l) if label.len() == 1 && l <= MAX_L => &[l],
+                _ => Err(line.error(
+                    first,
+                    ErrorKind::Expected("shell type s, p, d, f, g or sp"),
+                ))?,
+            },
+        };
+        let nprim: usize =
+            line.number(line.field(&fields, 1, "number of primitives")?)?;
+        // exponents are scaled by the square of this
+        let scale = match fields.get(2) {
+            Some(&f) => real(&line, f)?,
+            None => 1.0,
+        };
+        let mut exponents = Vec::with_capacity(nprim);
+        let mut coefficients = vec![Vec::with_capacity(nprim); ls.len()];
+        for _ in 0..nprim {
+            let prim =
+                section_line(lines).ok_or_else(|| line.missing("primitive"))?;
+            let fields = prim.fields();
+            exponents.push(real(&prim, fields[0])? * scale * scale);
+            for (k, c) in coefficients.iter_mut().enumerate() {
+                let f =
+                    prim.field(&fields, k + 1, "contraction coefficient")?;
+                c.push(real(&prim, f)?);
+            }
+        }
+        for (&l, coefficients) in ls.iter().zip(coefficients) {
+            shells.push(RawShell {
+                atom,
+                l,
+                exponents: exponents.clone(),
+                coefficients,
+            });
+        }
+    }
+    Ok(shells)
+}
+
+/// the `KEY= VALUE` pair of an orbital header line, with the key lowercased
+fn key_value<'a>(line: &Line<'a>) -> Option<(String, Option<Field<'a>>)> {
+    let fields = line.fields();
+    let k = fields.iter().position(|f| f.text.contains('='))?;
+    let (key, value) = fields[k].text.split_once('=')?;
+    let value = if value.is_empty() {
+        fields.get(k + 1).copied()
+    } else {
+        Some(Field {
+            text: value,
+            column: fields[k].column + key.chars().count() + 1,
+        })
+    };
+    let key = fields[..k]
+        .iter()
+        .map(|f| f.text)
+        .chain([key])
+        .collect::<String>()
+        .to_ascii_lowercase();
+    Some((key, value))
+}
+
+/// the orbitals of an `[MO]` section, each a few `KEY= VALUE` lines followed
+/// by `INDEX COEFFICIENT` lines. Coefficients that are left out are zero
+fn read_mo(lines: &mut Lines) -> Result<Vec<Orbital>, ParseError> {
+    let mut orbitals: Vec<Orbital> = Vec::new();
+    let mut in_header = false;
+    while let Some(line) = section_line(lines) {
+        if let Some((key, value)) = key_value(&line) {
+            if !in_header {
+                orbitals.push(Orbital::default());
+                in_header = true;
+            }
+            let orbital = orbitals.last_mut().unwrap();
+            let value = || value.ok_or_else(|| line.missing("value"));
+            match key.as_str() {
+                "ene" => orbital.energy = real(&line, value()?)?,
+                "occup" => orbital.occupation = real(&line, value()?)?,
+                "spin" if value()?.text.eq_ignore_ascii_case("beta") => {
+                    orbital.spin = Spin::Beta
+                }
+                _ => {}
+            }
+            continue;
+        }
+        in_header = false;
+        let fields = line.fields();
+        let index = fields[0];
+        let Some(orbital) = orbitals.last_mut() else {
+            Err(line.error(index, ErrorKind::Expected("orbital header")))?
+        };
+        let k: usize = line.number(index)?;
+        if k == 0 {
+            Err(
+                line.error(index, ErrorKind::Expected("basis function number"))
+            )?;
+        }
+        let c = real(&line, line.field(&fields, 1, "coefficient")?)?;
+        if orbital.coefficients.len() < k {
+            orbital.coefficients.resize(k, 0.0);
+        }
+        orbital.coefficients[k - 1] = c;
+    }
+    Ok(orbitals)
+}
+
+/// the first field of every line of a section, such as `[FREQ]` or `[INT]`
+fn read_column(lines: &mut Lines) -> Result<Vec<f32>, ParseError> {
+    let mut ret = Vec::new();
+    while let Some(line) = section_line(lines) {
+        ret.push(real(&line, line.fields()[0])? as f32);
+    }
+    Ok(ret)
+}
+
+/// the displacements of each mode of an `[FR-NORM-COORD]` section, each
+/// headed by a `vibration N` line
+fn read_vectors(lines: &mut Lines) -> Result<Vec<Vec<[f32; 3]>>, ParseError> {
+    let mut ret: Vec<Vec<[f32; 3]>> = Vec::new();
+    while let Some(line) = section_line(lines) {
+        let fields = line.fields();
+        if fields[0].text.eq_ignore_ascii_case("vibration") {
+            ret.push(Vec::new());
+            continue;
+        }
+        let Some(mode) = ret.last_mut() else {
+            Err(line.error(fields[0], ErrorKind::Expected("vibration")))?
+        };
+        let d = |i, what| {
+            Ok::<_, ParseError>(
+                real(&line, line.field(&fields, i, what)?)? as f32
+            )
+        };
+        mode.push([
+            d(0, "x displacement")?,
+            d(1, "y displacement")?,
+            d(2, "z displacement")?,
+        ]);
+    }
+    Ok(ret)
+}
+
+/// read a Molden file, evaluating each of `orbitals` on a grid if it has
+/// the basis set and orbitals for that, or the HOMO and LUMO if `orbitals`
+/// is empty
+pub fn load_molden(
+    path: impl AsRef<Path>,
+    orbitals: &[Selection],
+) -> Result<Trajectory, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let mut lines = Lines::new(path, &s);
+    let mut mol = Molecule::default();
+    let mut units = Unit::Angstrom;
+    let mut fr_coord = Vec::new();
+    let mut raw_shells = Vec::new();
+    let mut wfn = Wavefunction::default();
+    let mut mo_line = None;
+    let mut freqs = Vec::new();
+    let mut vectors = Vec::new();
+    let mut intensities = Vec::new();
+    // spherical d, f and g functions
+    let mut spherical = [false; MAX_L + 1];
+    while let Some(line) = lines.next() {
+        let Some((name, rest)) = header(&line) else {
+            continue;
+        };
+        match name.as_str() {
+            "title" => {
+                if let Some(title) = section_line(&mut lines) {
+                    mol.comment = title.text.trim().to_owned();
+                }
+            }
+            "atoms" => {
+                if rest.to_ascii_lowercase().contains("au") {
+                    units = Unit::Bohr;
+                }
+                mol.atoms = read_atoms(&mut lines, true)?;
+            }
+            "gto" => {
+                let natoms = mol.atoms.len();
+                raw_shells = read_gto(&mut lines, natoms)?;
+            }
+            "mo" => {
+                wfn.orbitals = read_mo(&mut lines)?;
+                mo_line = Some(line);
+            }
+            "5d" | "5d7f" => spherical[2..=3].fill(true),
+            "5d10f" => spherical[2] = true,
+            "7f" => spherical[3] = true,
+            "9g" => spherical[4] = true,
+            "freq" => freqs = read_column(&mut lines)?,
+            "int" => intensities = read_column(&mut lines)?,
+            "fr-coord" => fr_coord = read_atoms(&mut lines, false)?,
+            "fr-norm-coord" => vectors = read_vectors(&mut lines)?,
+            _ => {}
+        }
+    }
+    if mol.atoms.is_empty() {
+        if fr_coord.is_empty() {
+            Err(lines.eof.missing("[Atoms] section"))?;
+        }
+        mol.atoms = fr_coord;
+        units = Unit::Bohr;
+    }
+    mol.convert_from(units);
+
+    let natoms = mol.atoms.len();
+    if vectors.len() == freqs.len() && vectors.iter().all(|v| v.len() == natoms)
+    {
+        mol.modes = freqs
+            .iter()
+            .zip(vectors)
+            .enumerate()
+            .filter(|(_, (&f, _))| f != 0.0)
+            .map(|(k, (&frequency, displacements))| NormalMode {
+                frequency,
+                reduced_mass: None,
+                ir_intensity: intensities.get(k).copied(),
+                displacements,
+            })
+            .collect();
+    }
+
+    let positions = mol.positions();
+    wfn.shells = raw_shells
+        .into_iter()
+        .map(|s| {
+            let center =
+                positions[s.atom].map(|x| (x / BOHR_TO_ANGSTROM) as f64);
+            Shell::new(
+                center,
+                s.l,
+                spherical[s.l],
+                s.exponents,
+                &s.coefficients,
+            )
+        })
+        .collect();
+    let nbasis = wfn.nbasis();
+    if let Some(line) = mo_line.filter(|_| !wfn.orbitals.is_empty()) {
+        for orbital in &mut wfn.orbitals {
+            if orbital.coefficients.len() > nbasis {
+                let field = line.fields()[0];
+                Err(line.error(
+                    field,
+                    ErrorKind::Expected(
+                        "as many basis functions as MO coefficients",
+                    ),
+                ))?;
+            }
+            orbital.coefficients.resize(nbasis, 0.0);
+        }
+        mol.info
+            .push((String::from("basis_functions"), nbasis.to_string()));
+        mol.info
+            .push((String::from("orbitals"), wfn.orbitals.len().to_string()));
+        let orbitals = if orbitals.is_empty() {
+            &DEFAULT_ORBITALS[..]
+        } else {
+            orbitals
+        };
+        if nbasis > 0 {
+            mol.volumes = wfn.grids(&mol, orbitals);
+        }
+    }
+    Ok(mol.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOLDEN: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/testfiles/h2_sto3g.molden");
+
+    #[test]
+    fn orbitals_on_a_grid() {
+        let traj = load_molden(MOLDEN, &[]).unwrap();
+        let mol = &traj.frames[0];
+        assert_eq!(mol.comment, "H2 STO-3G");
+        let elements: Vec<u8> = mol.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [1, 1]);
+        assert_eq!(mol.atoms[0].z, -0.7 * BOHR_TO_ANGSTROM);
+        let info: Vec<(&str, &str)> = mol
+            .info
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(info, [("basis_functions", "2"), ("orbitals", "2")]);
+
+        assert_eq!(mol.modes.len(), 1);
+        assert_eq!(mol.modes[0].frequency, 4400.0);
+        assert_eq!(mol.modes[0].ir_intensity, Some(0.0));
+        let [x, y, z] = mol.modes[0].displacements[1];
+        assert_eq!([x, y], [0.0; 2]);
+        assert!((z - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-4);
+
+        // the HOMO and LUMO by default
+        let names: Vec<&str> =
+            mol.volumes.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["MO 1 (HOMO, -0.5782 Eh)", "MO 2 (LUMO, 0.6703 Eh)"]
+        );
+        let [homo, lumo] = [&mol.volumes[0], &mol.volumes[1]];
+        // 4 Å around the atoms, every 0.2 Å
+        assert_eq!(homo.shape, [41, 41, 45]);
+        assert_eq!(homo.spacing(), [0.2; 3]);
+        assert_eq!(homo.values.len(), 41 * 41 * 45);
+        // bonding and antibonding: even and odd under inversion
+        let n = homo.values.len();
+        for k in [0, 1000, 37_000] {
+            let (a, b) = (homo.values[k], homo.values[n - 1 - k]);
+            assert!((a - b).abs() < 1e-6);
+            let (a, b) = (lumo.values[k], lumo.values[n - 1 - k]);
+            assert!((a + b).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn density_holds_the_electrons() {
+        let traj = load_molden(MOLDEN, &[Selection::Density]).unwrap();
+        let grid = &traj.frames[0].volumes[0];
+        assert_eq!(grid.name, "density");
+        let volume: f32 = grid
+            .spacing()
+            .iter()
+            .map(|h| h / BOHR_TO_ANGSTROM)
+            .product();
+        let electrons: f32 = grid.values.iter().sum::<f32>() * volume;
+        assert!((electrons - 2.0).abs() < 0.02, "{electrons}");
+    }
+}
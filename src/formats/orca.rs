@@ -0,0 +1,311 @@
+//! ORCA output files. Every `CARTESIAN COORDINATES (ANGSTROEM)` block, one per
+//! optimization cycle, is a trajectory frame. The final single-point energy
+//! of each geometry (in Hartree) goes in its info, Mulliken and Löwdin
+//! charges become the `mulliken_charge` and `loewdin_charge` properties, and
+//! the frequency, normal mode and IR sections give the [NormalMode]s of the
+//! last frame, leaving out the zero-frequency translations and rotations.
+
+use std::{collections::HashMap, path::Path};
+
+use super::Lines;
+use crate::{
+    element,
+    error::{Error, ErrorKind, Field, Line, ParseError},
+    molecule::{Atom, AtomProperty, Molecule, PropertyValues, Trajectory},
+    vibration::NormalMode,
+};
+
+fn is_rule(line: &Line) -> bool {
+    line.text.trim_start().starts_with("----")
+}
+
+/// the atoms of a coordinate block, starting just after its title line
+fn read_coordinates(lines: &mut Lines) -> Result<Vec<Atom>, ParseError> {
+    lines.require("coordinates")?;
+    let mut atoms = Vec::new();
+    loop {
+        let line = lines.require("end of coordinates")?;
+        let fields = line.fields();
+        if fields.is_empty() {
+            return Ok(atoms);
+        }
+        // ghost atoms and atoms with ECPs are marked with a suffix, and
+        // dummy atoms are DA
+        let symbol = fields[0];
+        let w = match symbol.text.trim_end_matches([':', '>']) {
+            "DA" => 0,
+            s => element::lookup(s)
+                .ok_or_else(|| line.error(symbol, ErrorKind::UnknownElement))?,
+        };
+        let coord = |i, what| line.number(line.field(&fields, i, what)?);
+        atoms.push(Atom {
+            x: coord(1, "x coordinate")?,
+            y: coord(2, "y coordinate")?,
+            z: coord(3, "z coordinate")?,
+            w,
+        });
+    }
+}
+
+/// the charges of a population analysis, starting just after its title
+/// line. Each line is `INDEX SYMBOL : CHARGE`, possibly followed by a spin
+/// population
+fn read_charges(lines: &mut Lines) -> Result<Vec<f32>, ParseError> {
+    lines.require("atomic charges")?;
+    let mut charges = Vec::new();
+    loop {
+        let line = lines.require("end of atomic charges")?;
+        let text = line.text.trim_start();
+        if text.is_empty() || text.starts_with("Sum of atomic charges") {
+            return Ok(charges);
+        }
+        let fields = line.fields();
+        let colon = fields.iter().position(|f| f.text.ends_with(':'));
+        let Some(colon) = colon else {
+            Err(line.missing("`:` before the charge"))?
+        };
+        charges.push(line.number(line.field(
+            &fields,
+            colon + 1,
+            "charge",
+        )?)?);
+    }
+}
+
+/// the `N: FREQ cm**-1` lines of the frequency section, with zero for the
+/// translations and rotations
+fn read_frequencies(lines: &mut Lines) -> Result<Vec<f32>, ParseError> {
+    lines.require("vibrational frequencies")?;
+    let mut freqs = Vec::new();
+    loop {
+        let line = lines.require("end of vibrational frequencies")?;
+        let fields = line.fields();
+        match fields.as_slice() {
+            [index, freq, unit, ..]
+                if index.text.ends_with(':') && unit.text == "cm**-1" =>
+            {
+                freqs.push(line.number(*freq)?);
+            }
+            _ if is_rule(&line) && !freqs.is_empty() => return Ok(freqs),
+            _ => {}
+        }
+    }
+}
+
+/// the `n` by `n` matrix of normal modes, one per column, which ORCA prints
+/// in blocks of columns each headed by their indices
+fn read_normal_modes(
+    lines: &mut Lines,
+    n: usize,
+) -> Result<Vec<Vec<f32>>, ParseError> {
+    let mut modes = vec![vec![0.0; n]; n];
+    let mut columns: Vec<usize> = Vec::new();
+    loop {
+        let line = lines.require("normal modes")?;
+        let fields = line.fields();
+        let Some(first) = fields.first() else {
+            continue;
+        };
+        let indices: Option<Vec<usize>> =
+            fields.iter().map(|f| f.text.parse().ok()).collect();
+        if let Some(indices) = indices {
+            if indices.iter().any(|&k| k >= n) {
+                Err(line.error(*first, ErrorKind::Expected("mode number")))?;
+            }
+            columns = indices;
+            continue;
+        }
+        let Ok(row) = first.text.parse::<usize>() else {
+            continue;
+        };
+        if columns.is_empty() || fields.len() != columns.len() + 1 {
+            continue;
+        }
+        if row >= n {
+            Err(line.error(*first, ErrorKind::Expected("coordinate number")))?;
+        }
+        for (&k, &f) in columns.iter().zip(&fields[1..]) {
+            modes[k][row] = line.number(f)?;
+        }
+        if row == n - 1 && columns.last() == Some(&(n - 1)) {
+            return Ok(modes);
+        }
+    }
+}
+
+/// the IR intensities in km/mol by mode number. Versions before 5 print no
+/// such column and give nothing
+fn read_ir(lines: &mut Lines) -> Result<HashMap<usize, f32>, ParseError> {
+    // the title's underline, then column headings down to another rule
+    lines.require("IR spectrum")?;
+    let mut has_intensity = false;
+    loop {
+        let line = lines.require("IR spectrum")?;
+        if is_rule(&line) {
+            break;
+        }
+        has_intensity |= line.text.contains("Int");
+    }
+    let mut ret = HashMap::new();
+    for line in lines.by_ref() {
+        let fields = line.fields();
+        let Some(index) = fields.first().and_then(|f| f.text.strip_suffix(':'))
+        else {
+            break;
+        };
+        if has_intensity {
+            let mode = line.number(Field { text: index, ..fields[0] })?;
+            ret.insert(mode, line.number(line.field(&fields, 3, "Int")?)?);
+        }
+    }
+    Ok(ret)
+}
+
+/// set the per-atom property `name` from the population analysis titled
+/// by `line`
+fn set_charges(
+    mol: &mut Molecule,
+    line: &Line,
+    name: &str,
+    charges: Vec<f32>,
+) -> Result<(), ParseError> {
+    if charges.len() != mol.atoms.len() {
+        Err(line.missing("a charge for every atom"))?;
+    }
+    mol.properties.retain(|p| p.name != name);
+    mol.properties.push(AtomProperty {
+        name: name.to_owned(),
+        ncols: 1,
+        values: PropertyValues::Real(charges),
+    });
+    Ok(())
+}
+
+pub fn load_orca(path: impl AsRef<Path>) -> Result<Trajectory, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let mut lines = Lines::new(path, &s);
+    let mut frames: Vec<Molecule> = Vec::new();
+    let mut freqs = Vec::new();
+    let mut vectors = Vec::new();
+    let mut ir = HashMap::new();
+    while let Some(line) = lines.next() {
+        let text = line.text.trim();
+        if text == "CARTESIAN COORDINATES (ANGSTROEM)" {
+            let atoms = read_coordinates(&mut lines)?;
+            let mol = Molecule { atoms, ..Default::default() };
+            if frames.first().is_some_and(|f| !f.same_atoms(&mol)) {
+                let field = line.fields()[0];
+                Err(line.error(field, ErrorKind::InconsistentFrame))?;
+            }
+            frames.push(mol);
+            continue;
+        }
+        // everything else describes the most recent geometry
+        let Some(mol) = frames.last_mut() else {
+            continue;
+        };
+        if text.starts_with("FINAL SINGLE POINT ENERGY") {
+            let fields = line.fields();
+            let energy = line.field(&fields, 4, "energy")?;
+            line.number::<f64>(energy)?;
+            mol.info.retain(|(k, _)| k != "energy");
+            mol.info
+                .push((String::from("energy"), energy.text.to_owned()));
+        } else if text.starts_with("MULLIKEN ATOMIC CHARGES") {
+            let charges = read_charges(&mut lines)?;
+            set_charges(mol, &line, "mulliken_charge", charges)?;
+        } else if text.starts_with("LOEWDIN ATOMIC CHARGES") {
+            let charges = read_charges(&mut lines)?;
+            set_charges(mol, &line, "loewdin_charge", charges)?;
+        } else if text.contains("THE OPTIMIZATION HAS CONVERGED") {
+            mol.info
+                .push((String::from("optimization"), "converged".into()));
+        } else if text == "VIBRATIONAL FREQUENCIES" {
+            freqs = read_frequencies(&mut lines)?;
+        } else if text == "NORMAL MODES" {
+            vectors = read_normal_modes(&mut lines, 3 * mol.atoms.len())?;
+        } else if text == "IR SPECTRUM" {
+            ir = read_ir(&mut lines)?;
+        }
+    }
+    let Some(last) = frames.last_mut() else {
+        Err(lines
+            .eof
+            .missing("a CARTESIAN COORDINATES (ANGSTROEM) block"))?
+    };
+    if vectors.len() == freqs.len() {
+        last.modes = freqs
+            .iter()
+            .zip(&vectors)
+            .enumerate()
+            .filter(|(_, (&f, _))| f != 0.0)
+            .map(|(k, (&frequency, v))| NormalMode {
+                frequency,
+                reduced_mass: None,
+                ir_intensity: ir.get(&k).copied(),
+                displacements: v
+                    .chunks(3)
+                    .map(|d| [d[0], d[1], d[2]])
+                    .collect(),
+            })
+            .collect();
+    }
+    Ok(Trajectory { frames })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUT: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/testfiles/orca_water_opt_freq.out"
+    );
+
+    #[test]
+    fn optimization_charges_and_modes() {
+        let traj = load_orca(OUT).unwrap();
+        assert_eq!(traj.frames.len(), 2);
+        let first = &traj.frames[0];
+        let elements: Vec<u8> = first.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [8, 1, 1]);
+        assert_eq!(first.atoms[0].z, 0.119_262);
+        assert_eq!(
+            first.info,
+            [(String::from("energy"), String::from("-76.408914069234"))]
+        );
+        let charges = first.property("mulliken_charge").unwrap();
+        assert_eq!(
+            charges.values,
+            PropertyValues::Real(vec![-0.33, 0.165, 0.165])
+        );
+        let charges = first.property("loewdin_charge").unwrap();
+        assert_eq!(charges.values, PropertyValues::Real(vec![-0.2, 0.1, 0.1]));
+        assert!(first.modes.is_empty());
+
+        let last = &traj.frames[1];
+        assert_eq!(last.atoms[0].z, 0.119);
+        let info: Vec<(&str, &str)> = last
+            .info
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            info,
+            [
+                ("optimization", "converged"),
+                ("energy", "-76.409000000000")
+            ]
+        );
+
+        // without the six zero-frequency modes
+        let frequencies: Vec<f32> =
+            last.modes.iter().map(|m| m.frequency).collect();
+        assert_eq!(frequencies, [1634.5, 3795.0, 3905.0]);
+        let intensities: Vec<Option<f32>> =
+            last.modes.iter().map(|m| m.ir_intensity).collect();
+        assert_eq!(intensities, [Some(73.49), Some(4.51), Some(51.19)]);
+        assert_eq!(last.modes[2].displacements[2], [0.0, -0.56, -0.43]);
+    }
+}
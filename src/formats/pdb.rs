@@ -0,0 +1,296 @@
+//! Protein Data Bank files. ATOM and HETATM records give the atoms and their
+//! place in the chain/residue hierarchy, MODEL blocks give trajectory frames,
+//! CONECT records give explicit bonds and CRYST1 the unit cell. Only the
+//! first alternate location of each atom is kept.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use crate::{
+    cell::Cell,
+    element,
+    error::{self, Error, ErrorKind, Field, Line, ParseError},
+    hierarchy::{AtomSite, Chain, Hierarchy, Residue},
+    molecule::{Atom, Bond, BondOrder, Molecule, Trajectory},
+};
+
+/// the element of an atom without an element column, from its name in
+/// columns 13-16. Names of single-letter elements conventionally start in
+/// column 14, so a name starting in column 13 is a two-letter element
+fn element_from_name(line: &Line) -> Option<u8> {
+    let raw = line.text.get(12..16.min(line.text.len()))?;
+    let letters: String =
+        raw.chars().skip_while(|c| c.is_ascii_digit()).collect();
+    if raw.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        if let Some(z) = letters.get(..2).and_then(element::lookup) {
+            return Some(z);
+        }
+    }
+    letters.trim().get(..1).and_then(element::lookup)
+}
+
+fn optional_char(field: Field) -> Option<char> {
+    field.text.chars().next()
+}
+
+/// the number in `field`, or `default` if it is blank
+fn number_or<T: std::str::FromStr>(
+    line: &Line,
+    field: Field,
+    default: T,
+) -> Result<T, ParseError> {
+    if field.text.is_empty() {
+        Ok(default)
+    } else {
+        line.number(field)
+    }
+}
+
+#[derive(Default)]
+struct Model {
+    atoms: Vec<Atom>,
+    hierarchy: Hierarchy,
+    /// altloc already chosen for each (chain, residue number, insertion,
+    /// atom name)
+    altlocs: HashMap<(String, i32, Option<char>, String), Option<char>>,
+}
+
+impl Model {
+    fn push_atom(&mut self, line: &Line) -> Result<(), ParseError> {
+        let name = line.columns(13, 16).text.to_owned();
+        let altloc = optional_char(line.columns(17, 17));
+        let res_name = line.columns(18, 20).text;
+        let chain = line.columns(22, 22).text;
+        let res_seq = line.columns(23, 26);
+        let number = line.number(res_seq)?;
+        let insertion = optional_char(line.columns(27, 27));
+
+        let key = (chain.to_owned(), number, insertion, name.clone());
+        match self.altlocs.get(&key) {
+            Some(&first) if first != altloc => return Ok(()),
+            _ => {
+                self.altlocs.insert(key, altloc);
+            }
+        }
+
+        let sym = line.columns(77, 78);
+        let w = if sym.text.is_empty() {
+            element_from_name(line).ok_or_else(|| {
+                line.error(line.columns(13, 16), ErrorKind::UnknownElement)
+            })?
+        } else {
+            element::lookup(sym.text)
+                .ok_or_else(|| line.error(sym, ErrorKind::UnknownElement))?
+        };
+        let coord = |start, what| {
+            let f = line.columns(start, start + 7);
+            if f.text.is_empty() {
+                Err(line.missing(what))
+            } else {
+                line.number(f)
+            }
+        };
+        self.atoms.push(Atom {
+            x: coord(31, "x coordinate")?,
+            y: coord(39, "y coordinate")?,
+            z: coord(47, "z coordinate")?,
+            w,
+        });
+        // serial numbers overflow their columns in very large files, so fall
+        // back on counting
+        let serial = line
+            .columns(7, 11)
+            .text
+            .parse()
+            .unwrap_or(self.atoms.len() as i64);
+        let site = AtomSite {
+            serial,
+            name,
+            altloc,
+            occupancy: number_or(line, line.columns(55, 60), 1.0)?,
+            b_factor: number_or(line, line.columns(61, 66), 0.0)?,
+            hetero: line.text.starts_with("HETATM"),
+            residue: 0,
+        };
+        let chain = Chain {
+            id: chain.to_owned(),
+            label_id: None,
+        };
+        let residue = Residue {
+            name: res_name.to_owned(),
+            number,
+            insertion,
+            label_number: None,
+            chain: 0,
+        };
+        self.hierarchy.push(chain, residue, site);
+        Ok(())
+    }
+}
+
+fn parse_cryst1(line: &Line) -> Result<Cell, ParseError> {
+    let f = |start, end| line.number(line.columns(start, end));
+    // only the asymmetric unit is listed, so the atoms themselves don't
+    // repeat with the cell
+    Ok(Cell {
+        pbc: [false; 3],
+        ..Cell::from_parameters(
+            [f(7, 15)?, f(16, 24)?, f(25, 33)?],
+            [f(34, 40)?, f(41, 47)?, f(48, 54)?],
+        )
+    })
+}
+
+/// the serial numbers in a CONECT record: the atom followed by the atoms
+/// bonded to it
+fn parse_conect(line: &Line) -> Result<Vec<i64>, ParseError> {
+    (0..5)
+        .map(|k| line.columns(7 + 5 * k, 11 + 5 * k))
+        .filter(|f| !f.text.is_empty())
+        .map(|f| line.number(f))
+        .collect()
+}
+
+pub fn load_pdb(path: impl AsRef<Path>) -> Result<Trajectory, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let eof = Line {
+        path,
+        number: s.lines().count() + 1,
+        text: "",
+    };
+    let mut title = String::new();
+    let mut cell = None;
+    let mut models: Vec<Model> = Vec::new();
+    let mut cur = Model::default();
+    // the MODEL line of each model, for reporting mismatched frames
+    let mut starts = Vec::new();
+    let mut conect = Vec::new();
+    for line in error::lines(path, &s) {
+        let record = line.text.get(..6).unwrap_or(line.text).trim_end();
+        match record {
+            "HEADER" if title.is_empty() => {
+                title = line.columns(11, 80).text.to_owned();
+            }
+            "TITLE" => {
+                // continuation lines carry on the same title
+                if line.columns(9, 10).text.is_empty() {
+                    title.clear();
+                } else {
+                    title.push(' ');
+                }
+                title.push_str(line.columns(11, 80).text);
+            }
+            "CRYST1" => cell = Some(parse_cryst1(&line)?),
+            "MODEL" => starts.push(line),
+            "ATOM" | "HETATM" => cur.push_atom(&line)?,
+            "ENDMDL" => models.push(std::mem::take(&mut cur)),
+            "CONECT" => conect.push(line),
+            "END" => break,
+            _ => {}
+        }
+    }
+    if !cur.atoms.is_empty() || models.is_empty() {
+        models.push(cur);
+    }
+    if models[0].atoms.is_empty() {
+        Err(eof.missing("ATOM or HETATM records"))?;
+    }
+
+    // CONECT records refer to atoms by serial number
+    let serials: HashMap<i64, usize> = models[0]
+        .hierarchy
+        .sites
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.serial, i))
+        .collect();
+    let mut bonds = Vec::new();
+    let mut seen = HashSet::new();
+    for line in &conect {
+        let serial = parse_conect(line)?;
+        // serials of atoms that aren't in the file, such as those of a
+        // trimmed-down structure, are ignored
+        let Some(&i) = serial.first().and_then(|s| serials.get(s)) else {
+            continue;
+        };
+        for s in &serial[1..] {
+            let Some(&j) = serials.get(s) else {
+                continue;
+            };
+            let (i, j) = (i.min(j), i.max(j));
+            if i != j && seen.insert((i, j)) {
+                bonds.push(Bond::new(i, j, BondOrder::Single));
+            }
+        }
+    }
+
+    let mut frames = Vec::with_capacity(models.len());
+    for (k, model) in models.into_iter().enumerate() {
+        let mol = Molecule {
+            atoms: model.atoms,
+            bonds: bonds.clone(),
+            comment: title.clone(),
+            cell: cell.clone(),
+            properties: model.hierarchy.properties(),
+            hierarchy: Some(model.hierarchy),
+            ..Default::default()
+        };
+        if k > 0 && !mol.same_atoms(&frames[0]) {
+            let line = starts.get(k).copied().unwrap_or(eof);
+            let field = line.columns(1, 6);
+            Err(line.error(field, ErrorKind::InconsistentFrame))?;
+        }
+        frames.push(mol);
+    }
+    Ok(Trajectory { frames })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PDB: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/testfiles/gly_ser_altloc.pdb");
+
+    #[test]
+    fn residues_altlocs_and_bonds() {
+        let traj = load_pdb(PDB).unwrap();
+        assert_eq!(traj.frames.len(), 1);
+        let mol = &traj.frames[0];
+        // only the first of the alternate locations is kept
+        assert_eq!(mol.atoms.len(), 9);
+        let elements: Vec<u8> = mol.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [7, 6, 6, 8, 7, 6, 6, 8, 8]);
+        let cb = &mol.atoms[6];
+        assert_eq!([cb.x, cb.y, cb.z], [4.455, 1.118, 1.212]);
+        assert_eq!(mol.comment, "GLY-SER DIPEPTIDE WITH ALTERNATE SIDE CHAINS");
+        let (lengths, angles) = mol.cell.as_ref().unwrap().parameters();
+        for (l, expected) in lengths.into_iter().zip([20.0, 21.0, 22.0]) {
+            assert!((l - expected).abs() < 1e-4);
+        }
+        assert!(angles.iter().all(|a| (a - 90.0).abs() < 1e-4));
+
+        let h = mol.hierarchy.as_ref().unwrap();
+        let chains: Vec<&str> =
+            h.chains.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(chains, ["A", "W"]);
+        let residues: Vec<(&str, i32)> = h
+            .residues
+            .iter()
+            .map(|r| (r.name.as_str(), r.number))
+            .collect();
+        assert_eq!(residues, [("GLY", 1), ("SER", 2), ("HOH", 101)]);
+        let serials: Vec<i64> = h.sites.iter().map(|s| s.serial).collect();
+        assert_eq!(serials, [1, 2, 3, 4, 5, 6, 7, 9, 11]);
+        assert_eq!(h.sites[6].altloc, Some('A'));
+        assert_eq!(h.sites[6].occupancy, 0.6);
+        assert!(h.sites[8].hetero);
+
+        // the CONECT records naming the missing atom 99 are skipped
+        assert_eq!(mol.bonds.len(), 1);
+        assert_eq!((mol.bonds[0].i, mol.bonds[0].j), (2, 3));
+    }
+}
@@ -0,0 +1,194 @@
+//! Psi4 output files. Every `Geometry (in Angstrom)` or `Geometry (in Bohr)`
+//! block is a trajectory frame, the `@... Final Energy:` lines give the
+//! energy (in Hartree) of the current geometry, and the harmonic vibrational
+//! analysis gives the [NormalMode]s of the last frame.
+
+use std::path::Path;
+
+use super::Lines;
+use crate::{
+    element,
+    error::{Error, ErrorKind, Field, Line, ParseError},
+    molecule::{Atom, Molecule, Trajectory},
+    units::Unit,
+    vibration::NormalMode,
+};
+
+fn is_rule(line: &Line) -> bool {
+    line.text.trim_start().starts_with("----")
+}
+
+/// a frequency, with imaginary ones written `123.4i` read as negative
+pub(super) fn frequency(line: &Line, field: Field) -> Result<f32, ParseError> {
+    match field.text.strip_suffix('i') {
+        Some(text) => Ok(-line.number::<f32>(Field { text, ..field })?),
+        None => line.number(field),
+    }
+}
+
+/// the atoms of a geometry block, starting just after its title line.
+/// Ghost atoms, written `Gh(H)`, are read as dummies
+fn read_geometry(lines: &mut Lines) -> Result<Vec<Atom>, ParseError> {
+    while !is_rule(&lines.require("geometry")?) {}
+    let mut atoms = Vec::new();
+    for line in lines.by_ref() {
+        let fields = line.fields();
+        let Some(&label) = fields.first() else {
+            break;
+        };
+        // labels may carry a suffix, as in `H1` or `O_a`
+        let symbol: String = label
+            .text
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect();
+        let w = if label.text.starts_with("Gh(") {
+            0
+        } else {
+            element::lookup(&symbol)
+                .ok_or_else(|| line.error(label, ErrorKind::UnknownElement))?
+        };
+        let coord = |i, what| line.number(line.field(&fields, i, what)?);
+        atoms.push(Atom {
+            x: coord(1, "x coordinate")?,
+            y: coord(2, "y coordinate")?,
+            z: coord(3, "z coordinate")?,
+            w,
+        });
+    }
+    Ok(atoms)
+}
+
+/// the values of a row of the vibrational analysis table, after a label of
+/// `skip` fields
+fn row<T>(
+    line: &Line,
+    skip: usize,
+    parse: impl Fn(&Line, Field) -> Result<T, ParseError>,
+) -> Result<Vec<T>, ParseError> {
+    line.fields()[skip..]
+        .iter()
+        .map(|&f| parse(line, f))
+        .collect()
+}
+
+/// one block of modes of the vibrational analysis, starting at its
+/// `Freq [cm^-1]` line
+fn read_modes(
+    lines: &mut Lines,
+    first: Line,
+    natoms: usize,
+) -> Result<Vec<NormalMode>, ParseError> {
+    let mut modes: Vec<NormalMode> = row(&first, 2, frequency)?
+        .into_iter()
+        .map(|frequency| NormalMode { frequency, ..Default::default() })
+        .collect();
+    loop {
+        let line = lines.require("normal mode displacements")?;
+        let text = line.text.trim_start();
+        if text.starts_with("Reduced mass [u]") {
+            let values = row(&line, 3, |l, f| l.number(f))?;
+            for (mode, mass) in modes.iter_mut().zip(values) {
+                mode.reduced_mass = Some(mass);
+            }
+        } else if text.starts_with("IR activ [km/mol]") {
+            let values = row(&line, 3, |l, f| l.number(f))?;
+            for (mode, ir) in modes.iter_mut().zip(values) {
+                mode.ir_intensity = Some(ir);
+            }
+        } else if is_rule(&line) {
+            break;
+        }
+    }
+    for _ in 0..natoms {
+        let line = lines.require("normal mode displacements")?;
+        let fields = line.fields();
+        if fields.len() != 2 + 3 * modes.len() {
+            Err(line.missing("displacements"))?;
+        }
+        for (k, mode) in modes.iter_mut().enumerate() {
+            let d = |c| line.number(fields[2 + 3 * k + c]);
+            mode.displacements.push([d(0)?, d(1)?, d(2)?]);
+        }
+    }
+    Ok(modes)
+}
+
+pub fn load_psi4(path: impl AsRef<Path>) -> Result<Trajectory, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let mut lines = Lines::new(path, &s);
+    let mut frames: Vec<Molecule> = Vec::new();
+    while let Some(line) = lines.next() {
+        let text = line.text.trim();
+        if text.starts_with("Geometry (in ") {
+            let units = if text.starts_with("Geometry (in Bohr") {
+                Unit::Bohr
+            } else {
+                Unit::Angstrom
+            };
+            let atoms = read_geometry(&mut lines)?;
+            let mut mol = Molecule { atoms, ..Default::default() };
+            mol.convert_from(units);
+            if frames.first().is_some_and(|f| !f.same_atoms(&mol)) {
+                let field = line.fields()[0];
+                Err(line.error(field, ErrorKind::InconsistentFrame))?;
+            }
+            frames.push(mol);
+            continue;
+        }
+        // everything else describes the most recent geometry
+        let Some(mol) = frames.last_mut() else {
+            continue;
+        };
+        if text.starts_with('@') && text.contains("Final Energy:") {
+            let fields = line.fields();
+            let energy = line.field(&fields, fields.len() - 1, "energy")?;
+            line.number::<f64>(energy)?;
+            mol.info.retain(|(k, _)| k != "energy");
+            mol.info
+                .push((String::from("energy"), energy.text.to_owned()));
+        } else if text.contains("==> Harmonic Vibrational Analysis <==") {
+            mol.modes.clear();
+        } else if text.starts_with("Freq [cm^-1]") {
+            let natoms = mol.atoms.len();
+            mol.modes.extend(read_modes(&mut lines, line, natoms)?);
+        }
+    }
+    if frames.is_empty() {
+        Err(lines.eof.missing("a Geometry block"))?;
+    }
+    Ok(Trajectory { frames })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::BOHR_TO_ANGSTROM;
+
+    const OUT: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/testfiles/psi4_water.out");
+
+    #[test]
+    fn geometry_energy_and_modes() {
+        let traj = load_psi4(OUT).unwrap();
+        assert_eq!(traj.frames.len(), 1);
+        let mol = &traj.frames[0];
+        let elements: Vec<u8> = mol.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [8, 1, 1]);
+        assert_eq!(mol.atoms[2].z, 1.027_446_1 * BOHR_TO_ANGSTROM);
+        assert_eq!(
+            mol.info,
+            [(String::from("energy"), String::from("-76.02663273410715"))]
+        );
+
+        // in the order printed, with imaginary modes negative
+        let frequencies: Vec<f32> =
+            mol.modes.iter().map(|m| m.frequency).collect();
+        assert_eq!(frequencies, [1775.6535, 4113.3813, -4212.3]);
+        let mode = &mol.modes[0];
+        assert_eq!(mode.reduced_mass, Some(1.0825));
+        assert_eq!(mode.ir_intensity, Some(53.6242));
+        assert_eq!(mode.displacements[1], [0.0, -0.42, 0.56]);
+    }
+}
@@ -0,0 +1,345 @@
+//! MDL molfiles and SD files. Both V2000 (fixed-column) and V3000 connection
+//! tables are read, with their bond orders and formal charges. An SD file is
+//! a sequence of molfiles each followed by optional `> <NAME>` data fields
+//! and terminated by `$$$$`; the data fields go in [Molecule::info].
+//! Coordinates are in Å.
+
+use std::{collections::HashMap, path::Path};
+
+use super::Lines;
+use crate::{
+    element,
+    error::{Error, ErrorKind, Field, Line, ParseError},
+    molecule::{Atom, AtomProperty, Bond, BondOrder, Molecule, PropertyValues},
+};
+
+/// atom symbols for query atoms and R groups, read as dummy atoms
+const QUERY_ATOMS: [&str; 5] = ["A", "Q", "*", "L", "R#"];
+
+fn parse_element(line: &Line, field: Field) -> Result<u8, ParseError> {
+    if QUERY_ATOMS.contains(&field.text) {
+        return Ok(0);
+    }
+    element::lookup(field.text)
+        .ok_or_else(|| line.error(field, ErrorKind::UnknownElement))
+}
+
+/// the bond order for a bond type code. The query types 5 to 8 (single or
+/// double, single or aromatic, double or aromatic, any) are read as single
+fn parse_order(line: &Line, field: Field) -> Result<BondOrder, ParseError> {
+    match line.number::<u8>(field)? {
+        1 | 5..=8 => Ok(BondOrder::Single),
+        2 => Ok(BondOrder::Double),
+        3 => Ok(BondOrder::Triple),
+        4 => Ok(BondOrder::Aromatic),
+        _ => Err(line.error(field, ErrorKind::Expected("bond type 1 to 8"))),
+    }
+}
+
+/// the bond between 1-based atom numbers `a` and `b`
+fn make_bond(
+    line: &Line,
+    (a, b): (Field, Field),
+    index: impl Fn(i64) -> Option<usize>,
+    order: BondOrder,
+) -> Result<Bond, ParseError> {
+    let atom = |f: Field| {
+        index(line.number(f)?)
+            .ok_or_else(|| line.error(f, ErrorKind::Expected("atom number")))
+    };
+    let (i, j) = (atom(a)?, atom(b)?);
+    Ok(Bond::new(i.min(j), i.max(j), order))
+}
+
+/// the V2000 atom and bond blocks and properties block, up to `M  END`
+fn read_v2000(
+    lines: &mut Lines,
+    counts: Line,
+    mol: &mut Molecule,
+    charges: &mut Vec<i64>,
+) -> Result<(), ParseError> {
+    let natoms: usize = counts.number(counts.columns(1, 3))?;
+    let nbonds: usize = counts.number(counts.columns(4, 6))?;
+    for _ in 0..natoms {
+        let line = lines.require("atom line")?;
+        let coord = |start| line.number(line.columns(start, start + 9));
+        mol.atoms.push(Atom {
+            x: coord(1)?,
+            y: coord(11)?,
+            z: coord(21)?,
+            w: parse_element(&line, line.columns(32, 34))?,
+        });
+        // the old charge field, superseded by any M  CHG lines
+        let field = line.columns(37, 39);
+        let code = if field.text.is_empty() {
+            0
+        } else {
+            line.number(field)?
+        };
+        // 1 to 3 are +3 to +1 and 5 to 7 are -1 to -3; 4 is a radical
+        charges.push(match code {
+            1..=3 | 5..=7 => 4 - code,
+            _ => 0,
+        });
+    }
+    for _ in 0..nbonds {
+        let line = lines.require("bond line")?;
+        let ends = (line.columns(1, 3), line.columns(4, 6));
+        let order = parse_order(&line, line.columns(7, 9))?;
+        let index =
+            |n: i64| (1..=natoms as i64).contains(&n).then(|| n as usize - 1);
+        mol.bonds.push(make_bond(&line, ends, index, order)?);
+    }
+    let mut reset = false;
+    loop {
+        let line = lines.require("M  END")?;
+        if line.text.starts_with("M  END") {
+            return Ok(());
+        }
+        if line.text.starts_with("$$$$") {
+            return Err(line.missing("M  END"));
+        }
+        if !line.text.starts_with("M  CHG") {
+            continue;
+        }
+        if !reset {
+            charges.iter_mut().for_each(|c| *c = 0);
+            reset = true;
+        }
+        let fields = line.fields();
+        let n: usize = line.number(line.field(&fields, 2, "entry count")?)?;
+        for k in 0..n {
+            let atom = line.field(&fields, 3 + 2 * k, "atom number")?;
+            let charge = line.field(&fields, 4 + 2 * k, "charge")?;
+            let i = line.number::<usize>(atom)?;
+            if !(1..=natoms).contains(&i) {
+                Err(line.error(atom, ErrorKind::Expected("atom number")))?;
+            }
+            charges[i - 1] = line.number(charge)?;
+        }
+    }
+}
+
+/// the next V3000 entry, joining continuation lines ending in `-`. Returns
+/// each field with its line, skipping the `M  V30` prefix
+fn next_v3000<'a>(
+    lines: &mut Lines<'a>,
+) -> Result<(Line<'a>, Vec<(Line<'a>, Field<'a>)>), ParseError> {
+    let first = lines.require("M  END")?;
+    let mut line = first;
+    let mut ret = Vec::new();
+    loop {
+        if !line.text.starts_with("M  V30") {
+            return Ok((first, ret));
+        }
+        let mut fields = line.fields().split_off(2);
+        let cont = fields.last().is_some_and(|f| f.text == "-");
+        if cont {
+            fields.pop();
+        }
+        ret.extend(fields.into_iter().map(|f| (line, f)));
+        if !cont {
+            return Ok((first, ret));
+        }
+        line = lines.require("continuation line")?;
+    }
+}
+
+/// the V3000 connection table, up to `M  END`
+fn read_v3000(
+    lines: &mut Lines,
+    mol: &mut Molecule,
+    charges: &mut Vec<i64>,
+) -> Result<(), ParseError> {
+    #[derive(PartialEq)]
+    enum Section {
+        Atom,
+        Bond,
+        Other,
+    }
+    let mut section = Section::Other;
+    // atoms are referred to by their index field, which need not be
+    // sequential
+    let mut indices = HashMap::new();
+    loop {
+        let (first, fields) = next_v3000(lines)?;
+        if first.text.starts_with("M  END") {
+            return Ok(());
+        }
+        if !first.text.starts_with("M  V30") {
+            return Err(first.error(
+                first.columns(1, 6),
+                ErrorKind::Expected("M  V30 or M  END"),
+            ));
+        }
+        let text: Vec<&str> = fields.iter().map(|(_, f)| f.text).collect();
+        match text.as_slice() {
+            ["BEGIN", "ATOM", ..] => section = Section::Atom,
+            ["BEGIN", "BOND", ..] => section = Section::Bond,
+            ["BEGIN", ..] | ["END", ..] => section = Section::Other,
+            _ if section == Section::Atom => {
+                let get = |i, what| {
+                    fields.get(i).copied().ok_or_else(|| first.missing(what))
+                };
+                let number = |(line, f): (Line, Field)| line.number(f);
+                let (line, index) = get(0, "atom index")?;
+                indices.insert(line.number::<i64>(index)?, mol.atoms.len());
+                let (line, symbol) = get(1, "atom type")?;
+                mol.atoms.push(Atom {
+                    x: number(get(2, "x coordinate")?)?,
+                    y: number(get(3, "y coordinate")?)?,
+                    z: number(get(4, "z coordinate")?)?,
+                    w: parse_element(&line, symbol)?,
+                });
+                let mut charge = 0;
+                for &(line, f) in fields.iter().skip(6) {
+                    if let Some(value) = f.text.strip_prefix("CHG=") {
+                        let field = Field {
+                            text: value,
+                            column: f.column + 4,
+                        };
+                        charge = line.number(field)?;
+                    }
+                }
+                charges.push(charge);
+            }
+            _ if section == Section::Bond => {
+                let get = |i, what| {
+                    fields.get(i).copied().ok_or_else(|| first.missing(what))
+                };
+                let (line, kind) = get(1, "bond type")?;
+                let order = parse_order(&line, kind)?;
+                let (line, a) = get(2, "first atom")?;
+                let (_, b) = get(3, "second atom")?;
+                let index = |n| indices.get(&n).copied();
+                mol.bonds.push(make_bond(&line, (a, b), index, order)?);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// read the data fields after `M  END` up to the end of the record
+fn read_data(lines: &mut Lines, mol: &mut Molecule) {
+    while let Some(line) = lines.next() {
+        if line.text.starts_with("$$$$") {
+            return;
+        }
+        let Some(header) = line.text.strip_prefix('>') else {
+            continue;
+        };
+        let name = match (header.find('<'), header.rfind('>')) {
+            (Some(a), Some(b)) if a < b => &header[a + 1..b],
+            _ => header.trim(),
+        };
+        let mut value = Vec::new();
+        while let Some(line) = lines.peek() {
+            if line.text.trim().is_empty() || line.text.starts_with("$$$$") {
+                break;
+            }
+            value.push(line.text.trim_end());
+            lines.next();
+        }
+        mol.info.push((name.to_owned(), value.join(" ")));
+    }
+}
+
+fn read_record(lines: &mut Lines) -> Result<Molecule, ParseError> {
+    let name = lines.require("molecule name")?;
+    lines.require("program line")?;
+    let comment = lines.require("comment line")?;
+    let counts = lines.require("counts line")?;
+    let mut mol = Molecule {
+        comment: if name.text.trim().is_empty() {
+            comment.text.trim().to_owned()
+        } else {
+            name.text.trim().to_owned()
+        },
+        ..Default::default()
+    };
+    let mut charges = Vec::new();
+    match counts.columns(35, 39).text {
+        "V3000" => read_v3000(lines, &mut mol, &mut charges)?,
+        "V2000" | "" => read_v2000(lines, counts, &mut mol, &mut charges)?,
+        _ => {
+            let field = counts.columns(35, 39);
+            Err(counts.error(field, ErrorKind::Expected("V2000 or V3000")))?
+        }
+    }
+    mol.properties.push(AtomProperty {
+        name: String::from("formal_charge"),
+        ncols: 1,
+        values: PropertyValues::Int(charges),
+    });
+    read_data(lines, &mut mol);
+    Ok(mol)
+}
+
+/// read every record of a molfile or SD file
+pub fn load_sdf(path: impl AsRef<Path>) -> Result<Vec<Molecule>, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let mut lines = Lines::new(path, &s);
+    let mut ret = Vec::new();
+    loop {
+        let rest = &lines.lines[lines.pos..];
+        if rest.iter().all(|l| l.text.trim().is_empty()) && !ret.is_empty() {
+            return Ok(ret);
+        }
+        ret.push(read_record(&mut lines)?);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SDF: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/testfiles/acetate_and_hcn.sdf");
+
+    fn bonds(mol: &Molecule) -> Vec<(usize, usize, BondOrder)> {
+        mol.bonds.iter().map(|b| (b.i, b.j, b.order)).collect()
+    }
+
+    #[test]
+    fn v2000_and_v3000_records() {
+        let mols = load_sdf(SDF).unwrap();
+        assert_eq!(mols.len(), 2);
+
+        let acetate = &mols[0];
+        assert_eq!(acetate.comment, "acetate");
+        let elements: Vec<u8> = acetate.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [6, 6, 8, 8]);
+        let o = &acetate.atoms[3];
+        assert_eq!([o.x, o.y, o.z], [2.2, -1.0, 0.0]);
+        assert_eq!(
+            bonds(acetate),
+            [
+                (0, 1, BondOrder::Single),
+                (1, 2, BondOrder::Double),
+                (1, 3, BondOrder::Single)
+            ]
+        );
+        // from the charge column of the atom block
+        let charges = acetate.property("formal_charge").unwrap();
+        assert_eq!(charges.values, PropertyValues::Int(vec![0, 0, 0, -1]));
+        let info: Vec<(&str, &str)> = acetate
+            .info
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(info, [("ID", "mol-1"), ("SMILES", "CC(=O) [O-]")]);
+
+        // atoms numbered 10, 20 and 30, and a continued line
+        let hcn = &mols[1];
+        let elements: Vec<u8> = hcn.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [7, 6, 1]);
+        assert_eq!(hcn.atoms[1].x, 1.4);
+        assert_eq!(
+            bonds(hcn),
+            [(0, 1, BondOrder::Triple), (0, 2, BondOrder::Single)]
+        );
+        let charges = hcn.property("formal_charge").unwrap();
+        assert_eq!(charges.values, PropertyValues::Int(vec![1, 0, 0]));
+    }
+}
@@ -0,0 +1,464 @@
+//! VASP files. A POSCAR or CONTCAR holds one structure: a comment, a scaling
+//! factor (the cell volume if negative, or one factor per Cartesian
+//! direction if there are three), the lattice vectors, the species and the
+//! number of atoms of each, an optional `Selective dynamics` line, and the
+//! positions in `Direct` (fractional) or `Cartesian` coordinates. Files from
+//! VASP 4 have no species line, so the species are taken from the POTCAR
+//! next to the file or, failing that, the comment. The selective dynamics
+//! flags become the `selective_dynamics` property.
+//!
+//! An XDATCAR has the same header followed by the positions of each ionic
+//! step, repeating the header before each step when the cell changes. An
+//! OUTCAR gives the positions and forces of each ionic step, with the free
+//! energy (in eV) of each in its info.
+
+use std::{fs::read_to_string, path::Path};
+
+use super::Lines;
+use crate::{
+    cell::Cell,
+    element,
+    error::{Error, ErrorKind, Line, ParseError},
+    molecule::{Atom, AtomProperty, Molecule, PropertyValues, Trajectory},
+};
+
+/// the lattice and atoms described at the top of a POSCAR or XDATCAR
+struct Header<'a> {
+    comment: Line<'a>,
+    /// the scaled lattice vectors in Å
+    vectors: [[f32; 3]; 3],
+    /// factors applied to each Cartesian coordinate
+    scale: [f32; 3],
+    /// the element of each atom
+    elements: Vec<u8>,
+}
+
+/// the first three fields of `line` as a vector
+fn vector(line: &Line, what: &'static str) -> Result<[f32; 3], ParseError> {
+    let fields = line.fields();
+    let mut ret = [0.0; 3];
+    for (c, x) in ret.iter_mut().enumerate() {
+        *x = line.number(line.field(&fields, c, what)?)?;
+    }
+    Ok(ret)
+}
+
+fn det([a, b, c]: [[f32; 3]; 3]) -> f32 {
+    a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+        + a[2] * (b[0] * c[1] - b[1] * c[0])
+}
+
+/// the element of a species name such as `Fe`, `Fe_pv` or `Fe/a1b2c3`
+fn species_element(name: &str) -> Option<u8> {
+    let symbol: String = name
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+    element::lookup(&symbol)
+}
+
+/// the species in the `TITEL` lines of a POTCAR, such as `Fe`
+/// in `TITEL  = PAW_PBE Fe 06Sep2000`
+fn titel_species(s: &str) -> Vec<&str> {
+    s.lines()
+        .filter_map(|l| l.trim_start().strip_prefix("TITEL"))
+        .filter_map(|rest| rest.split_whitespace().nth(2))
+        .collect()
+}
+
+/// the elements of the species of a VASP 4 file, which doesn't list them:
+/// from the POTCAR next to `path` if there is one, or else the comment
+fn vasp4_species(path: &Path, comment: &Line) -> Option<Vec<u8>> {
+    let from_potcar = read_to_string(path.with_file_name("POTCAR"))
+        .ok()
+        .and_then(|s| {
+            titel_species(&s).into_iter().map(species_element).collect()
+        });
+    from_potcar.or_else(|| {
+        comment
+            .fields()
+            .iter()
+            .map(|f| species_element(f.text))
+            .collect()
+    })
+}
+
+/// the atom counts of each species, from the species line of VASP 5 files
+/// or the line after it
+fn read_counts(line: &Line) -> Result<Vec<usize>, ParseError> {
+    line.fields()
+        .into_iter()
+        .take_while(|f| !f.text.starts_with(['#', '!']))
+        .map(|f| line.number(f))
+        .collect()
+}
+
+fn read_header<'a>(
+    lines: &mut Lines<'a>,
+    path: &Path,
+) -> Result<Header<'a>, ParseError> {
+    let comment = lines.require("comment line")?;
+    let line = lines.require("scaling factor")?;
+    let fields = line.fields();
+    let first: f32 =
+        line.number(line.field(&fields, 0, "scaling factor")?)?;
+    let three: Option<Vec<f32>> = fields
+        .get(..3)
+        .and_then(|f| f.iter().map(|f| f.text.parse().ok()).collect());
+    let mut vectors = [[0.0; 3]; 3];
+    for v in &mut vectors {
+        *v = vector(&lines.require("lattice vector")?, "lattice vector")?;
+    }
+    let scale = match three {
+        Some(s) => [s[0], s[1], s[2]],
+        // the volume of the cell
+        None if first < 0.0 => [(-first / det(vectors).abs()).cbrt(); 3],
+        None => [first; 3],
+    };
+    for v in &mut vectors {
+        for (x, s) in v.iter_mut().zip(scale) {
+            *x *= s;
+        }
+    }
+
+    let line = lines.require("species names")?;
+    let fields = line.fields();
+    let name = line.field(&fields, 0, "species names")?;
+    let (species, counts) = if name.text.parse::<usize>().is_ok() {
+        let species = vasp4_species(path, &comment).ok_or_else(|| {
+            line.error(name, ErrorKind::Expected("species names"))
+        })?;
+        (species, read_counts(&line)?)
+    } else {
+        let species = fields
+            .iter()
+            .take_while(|f| !f.text.starts_with(['#', '!']))
+            .map(|&f| {
+                species_element(f.text)
+                    .ok_or_else(|| line.error(f, ErrorKind::UnknownElement))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let counts = read_counts(&lines.require("atom counts")?)?;
+        (species, counts)
+    };
+    if counts.len() != species.len() {
+        Err(line.missing("a count for every species"))?;
+    }
+    let elements = species
+        .iter()
+        .zip(counts)
+        .flat_map(|(&w, n)| std::iter::repeat_n(w, n))
+        .collect();
+    Ok(Header {
+        comment,
+        vectors,
+        scale,
+        elements,
+    })
+}
+
+/// whether the line naming the coordinate system of the positions says
+/// they are Cartesian
+fn is_cartesian(line: &Line) -> bool {
+    line.text.trim_start().starts_with(['C', 'c', 'K', 'k'])
+}
+
+/// the positions of the atoms in `header`, and with `selective` their
+/// selective dynamics flags
+fn read_positions(
+    lines: &mut Lines,
+    header: &Header,
+    cartesian: bool,
+    selective: bool,
+) -> Result<(Vec<Atom>, Vec<bool>), ParseError> {
+    let mut atoms = Vec::with_capacity(header.elements.len());
+    let mut flags = Vec::new();
+    for &w in &header.elements {
+        let line = lines.require("atom position")?;
+        let p = vector(&line, "coordinate")?;
+        let [x, y, z] = if cartesian {
+            [0, 1, 2].map(|c| p[c] * header.scale[c])
+        } else {
+            let v = header.vectors;
+            [0, 1, 2].map(|c| p[0] * v[0][c] + p[1] * v[1][c] + p[2] * v[2][c])
+        };
+        atoms.push(Atom { x, y, z, w });
+        if selective {
+            let fields = line.fields();
+            for k in 3..6 {
+                let f = line.field(&fields, k, "selective dynamics flag")?;
+                flags.push(match f.text {
+                    "T" | "t" => true,
+                    "F" | "f" => false,
+                    _ => Err(line.error(f, ErrorKind::Expected("T or F")))?,
+                });
+            }
+        }
+    }
+    Ok((atoms, flags))
+}
+
+pub fn load_poscar(path: impl AsRef<Path>) -> Result<Molecule, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let mut lines = Lines::new(path, &s);
+    let header = read_header(&mut lines, path)?;
+    let mut line = lines.require("coordinate system")?;
+    let selective = line.text.trim_start().starts_with(['S', 's']);
+    if selective {
+        line = lines.require("coordinate system")?;
+    }
+    let (atoms, flags) =
+        read_positions(&mut lines, &header, is_cartesian(&line), selective)?;
+    let mut mol = Molecule {
+        atoms,
+        comment: header.comment.text.trim().to_owned(),
+        cell: Some(Cell::new(header.vectors)),
+        ..Default::default()
+    };
+    if selective {
+        mol.properties.push(AtomProperty {
+            name: String::from("selective_dynamics"),
+            ncols: 3,
+            values: PropertyValues::Bool(flags),
+        });
+    }
+    Ok(mol)
+}
+
+pub fn load_xdatcar(path: impl AsRef<Path>) -> Result<Trajectory, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let mut lines = Lines::new(path, &s);
+    let mut header = read_header(&mut lines, path)?;
+    let mut frames: Vec<Molecule> = Vec::new();
+    while let Some(line) = lines.peek() {
+        let text = line.text.trim_start();
+        if text.is_empty() {
+            lines.next();
+            continue;
+        }
+        if !text.contains("configuration") {
+            // the cell changed, so a new header precedes this step
+            header = read_header(&mut lines, path)?;
+            continue;
+        }
+        lines.next();
+        let cartesian = is_cartesian(&line);
+        let (atoms, _) = read_positions(&mut lines, &header, cartesian, false)?;
+        let mol = Molecule {
+            atoms,
+            comment: header.comment.text.trim().to_owned(),
+            cell: Some(Cell::new(header.vectors)),
+            ..Default::default()
+        };
+        if frames.first().is_some_and(|f| !f.same_atoms(&mol)) {
+            let field = line.fields()[0];
+            Err(line.error(field, ErrorKind::InconsistentFrame))?;
+        }
+        frames.push(mol);
+    }
+    if frames.is_empty() {
+        Err(lines.eof.missing("a Direct configuration block"))?;
+    }
+    Ok(Trajectory { frames })
+}
+
+/// the positions and forces of a `POSITION ... TOTAL-FORCE` block, starting
+/// just after its title line
+fn read_position_block(
+    lines: &mut Lines,
+    elements: &[u8],
+) -> Result<(Vec<Atom>, Vec<f32>), ParseError> {
+    lines.require("positions")?;
+    let mut atoms = Vec::with_capacity(elements.len());
+    let mut forces = Vec::with_capacity(3 * elements.len());
+    for &w in elements {
+        let line = lines.require("atom position")?;
+        let fields = line.fields();
+        let mut row = [0.0; 6];
+        for (k, x) in row.iter_mut().enumerate() {
+            *x =
+                line.number(line.field(&fields, k, "position and force")?)?;
+        }
+        let [x, y, z, fx, fy, fz] = row;
+        atoms.push(Atom { x, y, z, w });
+        forces.extend([fx, fy, fz]);
+    }
+    Ok((atoms, forces))
+}
+
+pub fn load_outcar(path: impl AsRef<Path>) -> Result<Trajectory, Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let mut lines = Lines::new(path, &s);
+    let mut species = Vec::new();
+    let mut elements: Option<Vec<u8>> = None;
+    let mut vectors = None;
+    let mut frames: Vec<Molecule> = Vec::new();
+    while let Some(line) = lines.next() {
+        let text = line.text.trim();
+        if text.starts_with("TITEL") {
+            let fields = line.fields();
+            let name = line.field(&fields, 3, "species")?;
+            species.push(
+                species_element(name.text).ok_or_else(|| {
+                    line.error(name, ErrorKind::UnknownElement)
+                })?,
+            );
+        } else if text.starts_with("ions per type") {
+            let fields = line.fields();
+            let start = fields.iter().position(|f| f.text == "=").unwrap_or(0);
+            let counts = fields[start + 1..]
+                .iter()
+                .map(|&f| line.number::<usize>(f))
+                .collect::<Result<Vec<_>, _>>()?;
+            if counts.len() != species.len() {
+                Err(line.missing("a TITEL line for every species"))?;
+            }
+            elements = Some(
+                species
+                    .iter()
+                    .zip(counts)
+                    .flat_map(|(&w, n)| std::iter::repeat_n(w, n))
+                    .collect(),
+            );
+        } else if text.starts_with("direct lattice vectors") {
+            let mut v = [[0.0; 3]; 3];
+            for row in &mut v {
+                *row = vector(
+                    &lines.require("lattice vector")?,
+                    "lattice vector",
+                )?;
+            }
+            vectors = Some(v);
+        } else if text.starts_with("POSITION") && text.contains("TOTAL-FORCE") {
+            let Some(elements) = &elements else {
+                Err(line.missing("`ions per type` before the positions"))?
+            };
+            let (atoms, forces) = read_position_block(&mut lines, elements)?;
+            let mol = Molecule {
+                atoms,
+                cell: vectors.map(Cell::new),
+                properties: vec![AtomProperty {
+                    name: String::from("forces"),
+                    ncols: 3,
+                    values: PropertyValues::Real(forces),
+                }],
+                ..Default::default()
+            };
+            frames.push(mol);
+        } else if text.starts_with("FREE ENERGIE OF THE ION-ELECTRON SYSTEM") {
+            let Some(mol) = frames.last_mut() else {
+                continue;
+            };
+            loop {
+                let line = lines.require("free energy TOTEN")?;
+                let fields = line.fields();
+                if let Some(k) = fields.iter().position(|f| f.text == "TOTEN") {
+                    let energy = line.field(&fields, k + 2, "free energy")?;
+                    line.number::<f64>(energy)?;
+                    mol.info.push((
+                        String::from("free_energy"),
+                        energy.text.to_owned(),
+                    ));
+                    break;
+                }
+            }
+        }
+    }
+    if frames.is_empty() {
+        Err(lines.eof.missing("a POSITION block"))?;
+    }
+    Ok(Trajectory { frames })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testfile(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testfiles/vasp")
+            .join(name)
+    }
+
+    fn elements(mol: &Molecule) -> Vec<u8> {
+        mol.atoms.iter().map(|a| a.w).collect()
+    }
+
+    #[test]
+    fn direct_coordinates_with_selective_dynamics() {
+        let mol = load_poscar(testfile("POSCAR_NaCl")).unwrap();
+        assert_eq!(mol.comment, "NaCl rocksalt");
+        assert_eq!(elements(&mol), [11, 17]);
+        let cell = mol.cell.as_ref().unwrap();
+        assert_eq!(
+            cell.vectors,
+            [[0.0, 2.82, 2.82], [2.82, 0.0, 2.82], [2.82, 2.82, 0.0]]
+        );
+        assert_eq!(cell.pbc, [true; 3]);
+        let cl = &mol.atoms[1];
+        assert_eq!([cl.x, cl.y, cl.z], [2.82; 3]);
+        let flags = mol.property("selective_dynamics").unwrap();
+        assert_eq!(flags.ncols, 3);
+        let expected = vec![true, true, false, false, false, false];
+        assert_eq!(flags.values, PropertyValues::Bool(expected));
+    }
+
+    #[test]
+    fn cartesian_coordinates_scaled_to_a_volume() {
+        // a volume of 27 Å³ scales the unit cube by 3
+        let mol = load_poscar(testfile("CONTCAR_water")).unwrap();
+        assert_eq!(elements(&mol), [8, 1, 1]);
+        assert_eq!(mol.cell.as_ref().unwrap().parameters().0, [3.0; 3]);
+        let h = &mol.atoms[2];
+        assert!((h.y + 2.271).abs() < 1e-5 && (h.z - 1.758).abs() < 1e-5);
+        assert!(mol.property("selective_dynamics").is_none());
+    }
+
+    #[test]
+    fn species_from_the_comment_of_vasp4_files() {
+        let mol = load_poscar(testfile("POSCAR_Si_vasp4")).unwrap();
+        assert_eq!(elements(&mol), [14, 14]);
+        let si = &mol.atoms[1];
+        assert!((si.x - 1.3575).abs() < 1e-5);
+    }
+
+    #[test]
+    fn xdatcar_steps_and_changing_cells() {
+        let traj = load_xdatcar(testfile("XDATCAR")).unwrap();
+        assert_eq!(traj.frames.len(), 3);
+        let x: Vec<f32> = traj.frames.iter().map(|f| f.atoms[1].x).collect();
+        assert!((x[0] - 0.75).abs() < 1e-5);
+        assert!((x[1] - 0.8).abs() < 1e-5);
+        // the last step has a new header with a larger cell
+        assert!((x[2] - 0.72).abs() < 1e-5);
+        assert_eq!(
+            traj.frames[2].cell.as_ref().unwrap().parameters().0,
+            [6.0; 3]
+        );
+        assert_eq!(elements(&traj.frames[2]), [1, 1]);
+    }
+
+    #[test]
+    fn outcar_forces_and_energies() {
+        let traj = load_outcar(testfile("OUTCAR")).unwrap();
+        assert_eq!(traj.frames.len(), 2);
+        let mol = &traj.frames[1];
+        assert_eq!(elements(mol), [8, 1, 1]);
+        let h = &mol.atoms[1];
+        assert_eq!([h.x, h.y, h.z], [0.0, 0.76, 0.59]);
+        assert_eq!(mol.cell.as_ref().unwrap().parameters().0, [10.0; 3]);
+        assert_eq!(
+            mol.info,
+            [(String::from("free_energy"), String::from("-14.31"))]
+        );
+        let forces = mol.property("forces").unwrap();
+        let PropertyValues::Real(f) = &forces.values else {
+            panic!("forces aren't real numbers");
+        };
+        assert_eq!(f[3..6], [0.0, 0.02, 0.005]);
+        assert_eq!(traj.frames[0].info[0].1, "-14.22");
+    }
+}
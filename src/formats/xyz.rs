@@ -0,0 +1,196 @@
+//! XYZ files: an atom count line, a free-form comment line, and then one
+//! `symbol x y z` line per atom. Concatenating several of these gives a
+//! trajectory. Bare lists of atom lines without the two header lines are also
+//! accepted as a single frame. See [super::extxyz] for the extended
+//! variant. XYZ files don't specify their units, so coordinates are returned
+//! as written.
+
+use std::{iter::Peekable, path::Path};
+
+use super::extxyz;
+use crate::{
+    element,
+    error::{self, Error, ErrorKind, Line, ParseError},
+    molecule::{Atom, Molecule, Trajectory},
+};
+
+/// parse an atom line, ignoring any columns after the coordinates. The
+/// element may be given as a symbol or an atomic number
+fn parse_atom(line: &Line) -> Result<Atom, ParseError> {
+    let fields = line.fields();
+    let sym = line.field(&fields, 0, "element symbol")?;
+    Ok(Atom {
+        w: element::parse(sym.text)
+            .ok_or_else(|| line.error(sym, ErrorKind::UnknownElement))?,
+        x: line.number(line.field(&fields, 1, "x coordinate")?)?,
+        y: line.number(line.field(&fields, 2, "y coordinate")?)?,
+        z: line.number(line.field(&fields, 3, "z coordinate")?)?,
+    })
+}
+
+fn is_blank(line: &Line) -> bool {
+    line.text.trim().is_empty()
+}
+
+/// the atom count from the first line of a frame, if it looks like one
+fn atom_count(line: &Line) -> Option<usize> {
+    match line.fields()[..] {
+        [count] => count.text.parse().ok(),
+        _ => None,
+    }
+}
+
+/// read a frame made up of a count line, a comment line, and the atom lines.
+/// Also returns the count line for error reporting, and whether the comment
+/// line was an extended XYZ header
+fn read_frame<'a>(
+    lines: &mut Peekable<impl Iterator<Item = Line<'a>>>,
+    eof: Line<'a>,
+) -> Result<(Line<'a>, Molecule, bool), ParseError> {
+    let header = lines.next().unwrap_or(eof);
+    let count = header.field(&header.fields(), 0, "atom count")?;
+    let count = atom_count(&header).ok_or_else(|| {
+        header.error(count, ErrorKind::Expected("atom count"))
+    })?;
+    let comment = lines.next().ok_or_else(|| eof.missing("comment line"))?;
+    let ext = extxyz::parse_comment(&comment)?;
+    let columns = ext.as_ref().map_or(&[][..], |h| &h.columns);
+    let mut properties = extxyz::properties(columns);
+    let mut atoms = Vec::with_capacity(count);
+    for _ in 0..count {
+        let line = lines.next().ok_or_else(|| eof.missing("atom line"))?;
+        atoms.push(if columns.is_empty() {
+            parse_atom(&line)?
+        } else {
+            extxyz::parse_atom(&line, columns, &mut properties)?
+        });
+    }
+    let extended = ext.is_some();
+    let (info, cell) = ext.map_or((Vec::new(), None), |h| (h.info, h.cell));
+    let mol = Molecule {
+        atoms,
+        comment: comment.text.trim().to_owned(),
+        cell,
+        properties,
+        info,
+        ..Default::default()
+    };
+    Ok((header, mol, extended))
+}
+
+/// read an XYZ file, and whether its first frame is extended XYZ, whose
+/// coordinates are always in Å
+pub fn load_xyz(path: impl AsRef<Path>) -> Result<(Trajectory, bool), Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let eof = Line {
+        path,
+        number: s.lines().count() + 1,
+        text: "",
+    };
+    let mut lines = error::lines(path, &s).peekable();
+
+    if let Some(line) = lines.peek() {
+        if atom_count(line).is_none() && !is_blank(line) {
+            let atoms: Vec<_> = lines
+                .filter(|line| !is_blank(line))
+                .map(|line| parse_atom(&line))
+                .collect::<Result<_, _>>()?;
+            let mol = Molecule { atoms, ..Default::default() };
+            return Ok((Trajectory::from(mol), false));
+        }
+    }
+
+    let (_, first, extended) = read_frame(&mut lines, eof)?;
+    let mut frames = vec![first];
+    loop {
+        while lines.next_if(is_blank).is_some() {}
+        if lines.peek().is_none() {
+            break;
+        }
+        let (header, frame, _) = read_frame(&mut lines, eof)?;
+        if !frame.same_atoms(&frames[0]) {
+            let count = header.fields()[0];
+            Err(header.error(count, ErrorKind::InconsistentFrame))?;
+        }
+        frames.push(frame);
+    }
+
+    Ok((Trajectory { frames }, extended))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testfile(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testfiles")
+            .join(name)
+    }
+
+    #[test]
+    fn count_and_comment_lines() {
+        let (traj, extended) = load_xyz(testfile("acetaldehyde.xyz")).unwrap();
+        assert!(!extended);
+        assert_eq!(traj.frames.len(), 1);
+        let mol = &traj.frames[0];
+        assert_eq!(
+            mol.comment,
+            "taken from CrawfordGroup/ProgrammingProjects project 01"
+        );
+        let elements: Vec<u8> = mol.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [6, 6, 8, 1, 1, 1, 1]);
+        // as written, whatever the units
+        let o = &mol.atoms[2];
+        assert_eq!([o.x, o.y, o.z], [1.899_115_9, 0.0, 4.139_062_4]);
+    }
+
+    #[test]
+    fn bare_atom_lines() {
+        let (traj, _) = load_xyz(testfile("methane_bare.xyz")).unwrap();
+        let mol = &traj.frames[0];
+        assert!(mol.comment.is_empty());
+        // symbols in any case, or atomic numbers
+        let elements: Vec<u8> = mol.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [6, 1, 1, 1, 1]);
+        let h = &mol.atoms[4];
+        assert_eq!([h.x, h.y, h.z], [0.629_118, -0.629_118, -0.629_118]);
+    }
+
+    #[test]
+    fn too_few_atom_lines() {
+        match load_xyz(testfile("ammonia_truncated.xyz")) {
+            Err(Error::Parse(e)) => {
+                assert_eq!(e.kind, ErrorKind::Missing("atom line"));
+                assert_eq!(e.line, 6);
+            }
+            _ => panic!("read an XYZ file with atoms missing"),
+        }
+    }
+
+    #[test]
+    fn trajectory_frames() {
+        let (traj, _) = load_xyz(testfile("water_traj.xyz")).unwrap();
+        let comments: Vec<&str> =
+            traj.frames.iter().map(|f| f.comment.as_str()).collect();
+        assert_eq!(comments, ["step 0", "step 1", "step 2"]);
+        for frame in &traj.frames {
+            let elements: Vec<u8> = frame.atoms.iter().map(|a| a.w).collect();
+            assert_eq!(elements, [8, 1, 1]);
+        }
+        let h = &traj.frames[2].atoms[1];
+        assert_eq!([h.x, h.y, h.z], [0.0, 0.777, -0.491]);
+    }
+
+    #[test]
+    fn frames_with_different_atoms() {
+        match load_xyz(testfile("water_then_ammonia.xyz")) {
+            Err(Error::Parse(e)) => {
+                assert_eq!(e.kind, ErrorKind::InconsistentFrame);
+                assert_eq!(e.line, 6);
+            }
+            _ => panic!("read a trajectory whose atoms change"),
+        }
+    }
+}
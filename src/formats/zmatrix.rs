@@ -0,0 +1,554 @@
+//! Z-matrices: each atom placed by its distance to an earlier atom, its angle
+//! with a second and its dihedral with a third, as in the geometry section
+//! of CFOUR, Gaussian and Molpro inputs. Earlier atoms are referred to by
+//! number or by label, fields may be separated by commas, and any value may
+//! be the name of a variable, optionally negated, defined in the block after
+//! the first blank line. Dummy atoms (`X`) can be referred to like any other
+//! but are left out of the result. A line of just a symbol and three numbers
+//! (or a symbol, `0` and three numbers, as Gaussian writes) gives Cartesian
+//! coordinates instead.
+//!
+//! A Z-matrix file is either this alone or a Gaussian input deck, whose
+//! route section gives the units and whose title becomes the comment.
+
+use std::{collections::HashMap, path::Path};
+
+use super::Lines;
+use crate::{
+    element,
+    error::{Error, ErrorKind, Field, Line, ParseError},
+    molecule::{Atom, Molecule},
+    units::Unit,
+    vector::{cross3, dot3, sub3},
+};
+
+type Vec3 = [f64; 3];
+
+fn unit(a: Vec3) -> Vec3 {
+    let n = dot3(a, a).sqrt();
+    a.map(|x| x / n)
+}
+
+/// the angle a-b-c in degrees
+fn angle(a: Vec3, b: Vec3, c: Vec3) -> f64 {
+    let (u, v) = (unit(sub3(a, b)), unit(sub3(c, b)));
+    dot3(u, v).clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+/// the dihedral a-b-c-d in degrees, or 0 if it is undefined
+fn dihedral(a: Vec3, b: Vec3, c: Vec3, d: Vec3) -> f64 {
+    let (b1, b2, b3) = (sub3(b, a), sub3(c, b), sub3(d, c));
+    let n2 = cross3(b2, b3);
+    let y = dot3(b2, b2).sqrt() * dot3(b1, n2);
+    let x = dot3(cross3(b1, b2), n2);
+    if x == 0.0 && y == 0.0 {
+        0.0
+    } else {
+        y.atan2(x).to_degrees()
+    }
+}
+
+/// the position at distance `r` from `b`, making angle `theta` with `c` and
+/// dihedral `phi` with `d` (both in degrees). If `d` is on the line b-c the
+/// dihedral is measured from an arbitrary plane through it
+fn place(b: Vec3, c: Vec3, d: Vec3, r: f64, theta: f64, phi: f64) -> Vec3 {
+    let (theta, phi) = (theta.to_radians(), phi.to_radians());
+    let bc = unit(sub3(b, c));
+    let mut normal = cross3(sub3(c, d), bc);
+    if dot3(normal, normal) < 1e-12 {
+        let other = if bc[0].abs() < 0.9 {
+            [1.0, 0.0, 0.0]
+        } else {
+            [0.0, 1.0, 0.0]
+        };
+        normal = cross3(other, bc);
+    }
+    let n = unit(normal);
+    let m = cross3(n, bc);
+    let local = [
+        -r * theta.cos(),
+        r * theta.sin() * phi.cos(),
+        r * theta.sin() * phi.sin(),
+    ];
+    [0, 1, 2]
+        .map(|k| b[k] + local[0] * bc[k] + local[1] * m[k] + local[2] * n[k])
+}
+
+/// whether the angle a-b-c is far enough from 0 and 180° to define a plane
+fn bent(a: Vec3, b: Vec3, c: Vec3) -> bool {
+    (1.0..179.0).contains(&angle(a, b, c))
+}
+
+/// the fields of `line` separated by whitespace, commas or equals signs
+fn split<'a>(line: &Line<'a>) -> Vec<Field<'a>> {
+    let mut ret = Vec::new();
+    let mut start = None;
+    let text = line.text;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        let sep = c.is_whitespace() || c == ',' || c == '=';
+        match (start, sep) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                ret.push(Field {
+                    text: &text[s..i],
+                    column: text[..s].chars().count() + 1,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    ret
+}
+
+/// a line with nothing but separators on it
+fn is_blank(line: &Line) -> bool {
+    split(line).is_empty()
+}
+
+/// a variable name as written in the variable block, where CFOUR marks the
+/// ones to optimize with `*`
+fn variable_name(text: &str) -> String {
+    text.trim_end_matches('*').to_ascii_lowercase()
+}
+
+/// a distance, angle or coordinate: a number or a variable
+#[derive(Clone, Copy)]
+struct Value<'a> {
+    line: Line<'a>,
+    field: Field<'a>,
+}
+
+impl Value<'_> {
+    fn resolve(&self, vars: &HashMap<String, f64>) -> Result<f64, ParseError> {
+        let text = self.field.text;
+        if let Ok(x) = text.parse() {
+            return Ok(x);
+        }
+        let (sign, name) = match text.strip_prefix('-') {
+            Some(name) => (-1.0, name),
+            None => (1.0, text.strip_prefix('+').unwrap_or(text)),
+        };
+        vars.get(&variable_name(name))
+            .map(|x| sign * x)
+            .ok_or_else(|| {
+                let kind = ErrorKind::Expected("a number or defined variable");
+                self.line.error(self.field, kind)
+            })
+    }
+}
+
+/// how one atom is placed
+enum Placement<'a> {
+    Cartesian([Value<'a>; 3]),
+    /// earlier atoms and the distance, angle and dihedral to each, as far as
+    /// the atom's place in the Z-matrix requires
+    Internal(Vec<(usize, Value<'a>)>),
+}
+
+struct Row<'a> {
+    label: &'a str,
+    w: u8,
+    placement: Placement<'a>,
+}
+
+/// the element named by an atom label such as `C`, `C1`, `Cl2` or `6`
+fn label_element(text: &str) -> Option<u8> {
+    element::parse(text).or_else(|| {
+        let end = text
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(text.len());
+        element::lookup(&text[..end])
+    })
+}
+
+/// the index of the earlier atom `field` refers to, by number or label
+fn reference(
+    line: &Line,
+    field: Field,
+    rows: &[Row],
+) -> Result<usize, ParseError> {
+    let err = || line.error(field, ErrorKind::Expected("an earlier atom"));
+    match field.text.parse::<usize>() {
+        Ok(n) if (1..=rows.len()).contains(&n) => Ok(n - 1),
+        Ok(_) => Err(err()),
+        Err(_) => rows
+            .iter()
+            .rposition(|r| r.label.eq_ignore_ascii_case(field.text))
+            .ok_or_else(err),
+    }
+}
+
+fn parse_row<'a>(
+    line: Line<'a>,
+    rows: &[Row<'a>],
+) -> Result<Row<'a>, ParseError> {
+    let mut fields = split(&line);
+    let label = fields[0];
+    let w = label_element(label.text)
+        .ok_or_else(|| line.error(label, ErrorKind::UnknownElement))?;
+    let value = |field| Value { line, field };
+    // Gaussian's `C 0 x y z`
+    if fields.len() == 5 && fields[1].text == "0" {
+        fields.remove(1);
+    }
+    // Gaussian allows a trailing 0 to say that the last value is a dihedral
+    if fields.len() == 8 && fields[7].text == "0" {
+        fields.pop();
+    }
+    let placement = match fields.len() {
+        4 => Placement::Cartesian([1, 2, 3].map(|k| value(fields[k]))),
+        n if n == (2 * rows.len() + 1).min(7) => {
+            let mut refs = Vec::with_capacity(3);
+            for pair in fields[1..].chunks(2) {
+                let i = reference(&line, pair[0], rows)?;
+                if refs.iter().any(|&(j, _)| j == i) {
+                    let kind = ErrorKind::Expected("a different earlier atom");
+                    return Err(line.error(pair[0], kind));
+                }
+                refs.push((i, value(pair[1])));
+            }
+            Placement::Internal(refs)
+        }
+        _ => {
+            let kind = ErrorKind::Expected("a Z-matrix or Cartesian line");
+            return Err(line.error(label, kind));
+        }
+    };
+    Ok(Row { label: label.text, w, placement })
+}
+
+/// read a Z-matrix from the lines up to the next blank line, and its
+/// variables from the block after that if it uses any. Lengths are in the
+/// units of the file and angles in degrees
+pub(super) fn read_zmatrix(lines: &mut Lines) -> Result<Vec<Atom>, ParseError> {
+    let mut rows: Vec<Row> = Vec::new();
+    for line in lines.by_ref() {
+        if is_blank(&line) {
+            break;
+        }
+        let row = parse_row(line, &rows)?;
+        rows.push(row);
+    }
+    if rows.is_empty() {
+        return Err(lines.eof.missing("Z-matrix"));
+    }
+
+    let uses_variables = rows.iter().any(|r| {
+        let values: Vec<Value> = match &r.placement {
+            Placement::Cartesian(v) => v.to_vec(),
+            Placement::Internal(refs) => refs.iter().map(|&(_, v)| v).collect(),
+        };
+        values.iter().any(|v| v.field.text.parse::<f64>().is_err())
+    });
+    let mut vars = HashMap::new();
+    if uses_variables {
+        for line in lines.by_ref() {
+            if is_blank(&line) {
+                break;
+            }
+            let fields = split(&line);
+            // Gaussian's `Variables:` and `Constants:` headings
+            if fields.len() == 1 && fields[0].text.ends_with(':') {
+                continue;
+            }
+            let name = fields[0];
+            let value = line.field(&fields, 1, "variable value")?;
+            vars.insert(variable_name(name.text), line.number(value)?);
+        }
+    }
+
+    let mut positions: Vec<Vec3> = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let p = match &row.placement {
+            Placement::Cartesian(v) => [
+                v[0].resolve(&vars)?,
+                v[1].resolve(&vars)?,
+                v[2].resolve(&vars)?,
+            ],
+            Placement::Internal(refs) => {
+                let mut values = [0.0; 3];
+                for (x, (_, v)) in values.iter_mut().zip(refs) {
+                    *x = v.resolve(&vars)?;
+                }
+                let [r, theta, phi] = values;
+                let at = |k: usize| refs.get(k).map(|&(i, _)| positions[i]);
+                match (at(0), at(1), at(2)) {
+                    (None, _, _) => [0.0; 3],
+                    (Some(b), None, _) => [b[0], b[1], b[2] + r],
+                    (Some(b), Some(c), None) => place(b, c, c, r, theta, 0.0),
+                    (Some(b), Some(c), Some(d)) => {
+                        place(b, c, d, r, theta, phi)
+                    }
+                }
+            }
+        };
+        positions.push(p);
+    }
+
+    Ok(rows
+        .iter()
+        .zip(positions)
+        .filter(|(row, _)| row.w != 0)
+        .map(|(row, [x, y, z])| Atom {
+            x: x as f32,
+            y: y as f32,
+            z: z as f32,
+            w: row.w,
+        })
+        .collect())
+}
+
+/// read a bare Z-matrix, or the molecule specification of a Gaussian input
+/// file, and the units of a Gaussian input, which a bare Z-matrix doesn't
+/// give. The coordinates are left in the units of the file
+pub fn load_zmatrix(
+    path: impl AsRef<Path>,
+) -> Result<(Molecule, Option<Unit>), Error> {
+    let path = path.as_ref();
+    let s = super::read(path)?;
+    let mut lines = Lines::new(path, &s);
+    while lines.peek().is_some_and(|l| is_blank(&l)) {
+        lines.next();
+    }
+    let gaussian = lines
+        .peek()
+        .is_some_and(|l| l.text.trim_start().starts_with(['%', '#']));
+    let mut mol = Molecule::default();
+    let mut units = None;
+    if gaussian {
+        // Link 0 commands and the route section, then the title section,
+        // each ended by a blank line, then the charge and multiplicity
+        let mut route = String::new();
+        for line in lines.by_ref().take_while(|l| !is_blank(l)) {
+            if !line.text.trim_start().starts_with('%') {
+                route += &line.text.to_ascii_lowercase();
+            }
+        }
+        let route: String = route.split_whitespace().collect();
+        // Gaussian itself defaults to Å
+        units = if ["units=au", "units=bohr", "units(au", "units(bohr"]
+            .iter()
+            .any(|u| route.contains(u))
+        {
+            Some(Unit::Bohr)
+        } else {
+            Some(Unit::Angstrom)
+        };
+        let title: Vec<&str> = lines
+            .by_ref()
+            .take_while(|l| !is_blank(l))
+            .map(|l| l.text.trim())
+            .collect();
+        mol.comment = title.join(" ");
+        lines.require("charge and multiplicity")?;
+    }
+    mol.atoms = read_zmatrix(&mut lines)?;
+    Ok((mol, units))
+}
+
+/// `x` to `digits` decimal places, without the sign of a negative zero
+fn fixed(x: f64, digits: usize) -> String {
+    let s = format!("{x:.digits$}");
+    match s.strip_prefix('-') {
+        Some(rest) if rest.chars().all(|c| c == '0' || c == '.') => {
+            rest.to_owned()
+        }
+        _ => s,
+    }
+}
+
+/// the atoms to place atom `a` relative to, chosen from those already
+/// `placed`: first those bonded to it, then the nearest. The second and
+/// third are taken so that each consecutive three define a plane where
+/// possible
+fn references(
+    pos: &[Vec3],
+    neighbors: &[Vec<usize>],
+    placed: &[usize],
+    a: usize,
+    b: usize,
+) -> (Option<usize>, Option<usize>) {
+    // neighbors of `around` first, in the order they were placed, then the
+    // other atoms by their distance from it
+    let candidates = |around: usize, skip: &[usize]| {
+        let mut ret: Vec<usize> = placed
+            .iter()
+            .copied()
+            .filter(|&k| neighbors[around].contains(&k) && !skip.contains(&k))
+            .collect();
+        let mut rest: Vec<usize> = placed
+            .iter()
+            .copied()
+            .filter(|&k| !ret.contains(&k) && !skip.contains(&k))
+            .collect();
+        let dist = |k: usize| {
+            let d = sub3(pos[k], pos[around]);
+            dot3(d, d)
+        };
+        rest.sort_by(|&i, &j| dist(i).total_cmp(&dist(j)));
+        ret.extend(rest);
+        ret
+    };
+    let cs = candidates(b, &[a, b]);
+    let Some(&first) = cs.first() else {
+        return (None, None);
+    };
+    let c = cs
+        .iter()
+        .copied()
+        .find(|&c| bent(pos[a], pos[b], pos[c]))
+        .unwrap_or(first);
+    let ds = candidates(c, &[a, b, c]);
+    let d = ds
+        .iter()
+        .copied()
+        .find(|&d| bent(pos[b], pos[c], pos[d]))
+        .or(ds.first().copied());
+    (Some(c), d)
+}
+
+/// a Z-matrix for `mol`, with a variable for every value. The atoms are
+/// ordered by a breadth-first walk of the bonds from the first atom, so
+/// that each is placed relative to an atom it is bonded to, and the first
+/// atom of each further fragment relative to the nearest atom before it
+pub fn write_zmatrix(mol: &Molecule) -> String {
+    let n = mol.atoms.len();
+    let pos: Vec<Vec3> = mol
+        .atoms
+        .iter()
+        .map(|a| [a.x as f64, a.y as f64, a.z as f64])
+        .collect();
+    let mut neighbors = vec![Vec::new(); n];
+    for b in &mol.bonds {
+        neighbors[b.i].push(b.j);
+        neighbors[b.j].push(b.i);
+    }
+    for v in &mut neighbors {
+        v.sort_unstable();
+    }
+
+    // each atom in order with the atom it is placed relative to
+    let mut order: Vec<(usize, Option<usize>)> = Vec::with_capacity(n);
+    let mut seen = vec![false; n];
+    for start in 0..n {
+        if seen[start] {
+            continue;
+        }
+        let nearest = order.iter().map(|&(k, _)| k).min_by(|&i, &j| {
+            let dist = |k: usize| {
+                let d = sub3(pos[k], pos[start]);
+                dot3(d, d)
+            };
+            dist(i).total_cmp(&dist(j))
+        });
+        seen[start] = true;
+        let mut next = order.len();
+        order.push((start, nearest));
+        while next < order.len() {
+            let a = order[next].0;
+            for &k in &neighbors[a] {
+                if !seen[k] {
+                    seen[k] = true;
+                    order.push((k, Some(a)));
+                }
+            }
+            next += 1;
+        }
+    }
+
+    let mut rank = vec![0; n];
+    let mut lines = Vec::with_capacity(n);
+    let mut vars = Vec::new();
+    let mut placed = Vec::with_capacity(n);
+    for (k, &(a, parent)) in order.iter().enumerate() {
+        rank[a] = k + 1;
+        let mut line = format!("{:<2}", mol.atoms[a].element().symbol);
+        if let Some(b) = parent {
+            let r = dot3(sub3(pos[a], pos[b]), sub3(pos[a], pos[b])).sqrt();
+            line += &format!(" {:>3} R{}", rank[b], k + 1);
+            vars.push(format!("R{}={}", k + 1, fixed(r, 6)));
+            let (c, d) = references(&pos, &neighbors, &placed, a, b);
+            if let Some(c) = c {
+                line += &format!(" {:>3} A{}", rank[c], k + 1);
+                let theta = angle(pos[a], pos[b], pos[c]);
+                vars.push(format!("A{}={}", k + 1, fixed(theta, 4)));
+                if let Some(d) = d {
+                    line += &format!(" {:>3} D{}", rank[d], k + 1);
+                    let phi = dihedral(pos[a], pos[b], pos[c], pos[d]);
+                    vars.push(format!("D{}={}", k + 1, fixed(phi, 4)));
+                }
+            }
+        }
+        lines.push(line.trim_end().to_owned());
+        placed.push(a);
+    }
+
+    let mut ret = lines.join("\n");
+    if !vars.is_empty() {
+        ret += "\n\n";
+        ret += &vars.join("\n");
+    }
+    ret.push('\n');
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::molecule::{Bond, BondOrder};
+
+    const GJF: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/testfiles/h2o2.gjf");
+
+    fn distance(a: &Atom, b: &Atom) -> f32 {
+        let d = [a.x - b.x, a.y - b.y, a.z - b.z];
+        (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt()
+    }
+
+    #[test]
+    fn gaussian_input_with_labels_and_variables() {
+        let (mol, units) = load_zmatrix(GJF).unwrap();
+        assert_eq!(units, Some(Unit::Angstrom));
+        assert_eq!(mol.comment, "hydrogen peroxide");
+        let elements: Vec<u8> = mol.atoms.iter().map(|a| a.w).collect();
+        assert_eq!(elements, [8, 8, 1, 1]);
+        let a = &mol.atoms;
+        assert_eq!([a[0].x, a[0].y, a[0].z], [0.0; 3]);
+        assert_eq!([a[1].x, a[1].y, a[1].z], [0.0, 0.0, 1.4]);
+        assert!((distance(&a[2], &a[0]) - 0.96).abs() < 1e-5);
+        assert!((distance(&a[3], &a[1]) - 0.96).abs() < 1e-5);
+        let p: Vec<Vec3> = a
+            .iter()
+            .map(|a| [a.x as f64, a.y as f64, a.z as f64])
+            .collect();
+        assert!((angle(p[3], p[1], p[0]) - 100.0).abs() < 1e-4);
+        assert!((dihedral(p[3], p[1], p[0], p[2]) + 115.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn separators_alone_end_the_matrix() {
+        let s = "H\nH 1 0.74\n , \n";
+        let mut lines = Lines::new(Path::new("h2.zmat"), s);
+        assert_eq!(read_zmatrix(&mut lines).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn written_matrix_reads_back() {
+        let (mut mol, _) = load_zmatrix(GJF).unwrap();
+        mol.bonds = [(0, 1), (0, 2), (1, 3)]
+            .map(|(i, j)| Bond::new(i, j, BondOrder::Single))
+            .into();
+        let s = write_zmatrix(&mol);
+        let mut lines = Lines::new(Path::new("h2o2.zmat"), &s);
+        let atoms = read_zmatrix(&mut lines).unwrap();
+        assert_eq!(atoms.len(), 4);
+        for (i, a) in atoms.iter().enumerate() {
+            assert_eq!(a.w, mol.atoms[i].w);
+            for (j, b) in atoms.iter().enumerate() {
+                let d = distance(a, b) - distance(&mol.atoms[i], &mol.atoms[j]);
+                assert!(d.abs() < 1e-4, "{s}");
+            }
+        }
+    }
+}
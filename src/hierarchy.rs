@@ -0,0 +1,140 @@
+//! The chain/residue/atom hierarchy of macromolecular structures
+
+use crate::molecule::{AtomProperty, PropertyValues};
+
+/// per-atom data from a macromolecular file, parallel to [Molecule::atoms]
+///
+/// [Molecule::atoms]: crate::molecule::Molecule::atoms
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtomSite {
+    pub serial: i64,
+    /// atom name such as `CA`
+    pub name: String,
+    pub altloc: Option<char>,
+    pub occupancy: f32,
+    pub b_factor: f32,
+    /// from a HETATM rather than an ATOM record
+    pub hetero: bool,
+    /// index into [Hierarchy::residues]
+    pub residue: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Residue {
+    pub name: String,
+    pub number: i32,
+    pub insertion: Option<char>,
+    /// the residue number assigned by the archive (mmCIF `label_seq_id`)
+    /// when it differs from the author's numbering in `number`. `None` for
+    /// PDB files and for residues outside a polymer
+    pub label_number: Option<i32>,
+    /// index into [Hierarchy::chains]
+    pub chain: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chain {
+    /// the author's chain identifier
+    pub id: String,
+    /// the chain identifier assigned by the archive (mmCIF `label_asym_id`)
+    pub label_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Hierarchy {
+    pub chains: Vec<Chain>,
+    pub residues: Vec<Residue>,
+    pub sites: Vec<AtomSite>,
+}
+
+impl Hierarchy {
+    /// add a site belonging to `residue` of `chain`, starting a new residue
+    /// (and chain) whenever these differ from the previous site's. The
+    /// `chain` index of `residue` is filled in here
+    pub fn push(&mut self, chain: Chain, residue: Residue, mut site: AtomSite) {
+        if self.chains.last() != Some(&chain) {
+            self.chains.push(chain);
+        }
+        let chain = self.chains.len() - 1;
+        let residue = Residue { chain, ..residue };
+        if self.residues.last() != Some(&residue) {
+            self.residues.push(residue);
+        }
+        site.residue = self.residues.len() - 1;
+        self.sites.push(site);
+    }
+
+    /// expose the per-atom data as [AtomProperty]s, mostly for coloring
+    pub fn properties(&self) -> Vec<AtomProperty> {
+        let prop = |name: &str, values| AtomProperty {
+            name: name.to_owned(),
+            ncols: 1,
+            values,
+        };
+        let strs = |f: &dyn Fn(&AtomSite) -> String| {
+            PropertyValues::Str(self.sites.iter().map(f).collect())
+        };
+        let residue = |s: &AtomSite| &self.residues[s.residue];
+        let mut props = vec![
+            prop(
+                "serial",
+                PropertyValues::Int(
+                    self.sites.iter().map(|s| s.serial).collect(),
+                ),
+            ),
+            prop("atom_name", strs(&|s| s.name.clone())),
+            prop("chain", strs(&|s| self.chains[residue(s).chain].id.clone())),
+            prop(
+                "residue",
+                strs(&|s| {
+                    let r = residue(s);
+                    let ins = r.insertion.map(String::from).unwrap_or_default();
+                    format!("{} {}{ins}", r.name, r.number)
+                }),
+            ),
+            prop("residue_name", strs(&|s| residue(s).name.clone())),
+            prop(
+                "altloc",
+                strs(&|s| s.altloc.map(String::from).unwrap_or_default()),
+            ),
+            prop(
+                "occupancy",
+                PropertyValues::Real(
+                    self.sites.iter().map(|s| s.occupancy).collect(),
+                ),
+            ),
+            prop(
+                "b_factor",
+                PropertyValues::Real(
+                    self.sites.iter().map(|s| s.b_factor).collect(),
+                ),
+            ),
+            prop(
+                "hetero",
+                PropertyValues::Bool(
+                    self.sites.iter().map(|s| s.hetero).collect(),
+                ),
+            ),
+        ];
+        if self.chains.iter().any(|c| c.label_id.is_some()) {
+            props.push(prop(
+                "label_chain",
+                strs(&|s| {
+                    let chain = &self.chains[residue(s).chain];
+                    chain.label_id.clone().unwrap_or_default()
+                }),
+            ));
+            props.push(prop(
+                "label_residue",
+                strs(&|s| {
+                    let r = residue(s);
+                    match r.label_number {
+                        Some(n) => format!("{} {n}", r.name),
+                        None => r.name.clone(),
+                    }
+                }),
+            ));
+        }
+        props
+    }
+}
@@ -0,0 +1,371 @@
+//! Isosurfaces of volumetric data by marching tetrahedra: each cube of the
+//! grid is split into six tetrahedra around its main diagonal, which avoids
+//! the ambiguous cases of marching cubes and gives a closed mesh
+
+use std::collections::HashMap;
+
+use donkey::colors::color;
+use raylib_sys::{
+    Color, KeyboardKey_KEY_LEFT_BRACKET, KeyboardKey_KEY_RIGHT_BRACKET,
+    KeyboardKey_KEY_V, Vector3,
+};
+
+use crate::{
+    molecule::Molecule,
+    render, ui,
+    vector::{cross3, dot3},
+    volume::VolumeGrid,
+};
+
+/// isovalue when none is given, as usual for orbitals
+const DEFAULT_ISOVALUE: f32 = 0.02;
+
+/// factor by which [ and ] change the isovalue
+const ISOVALUE_STEP: f32 = 1.25;
+
+/// translucent colors of the positive and negative surfaces
+const POSITIVE_COLOR: u32 = 0x3070FFA0;
+const NEGATIVE_COLOR: u32 = 0xFF5030A0;
+
+/// offsets of the corners of a grid cube
+const CORNERS: [[usize; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [1, 1, 0],
+    [0, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [1, 1, 1],
+    [0, 1, 1],
+];
+
+/// the tetrahedra of a cube as indices into [CORNERS], one for each path
+/// along the edges from corner 0 to corner 6. Neighboring cubes split their
+/// shared faces the same way, so the surface has no cracks
+const TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 2, 6],
+    [0, 1, 5, 6],
+    [0, 3, 2, 6],
+    [0, 3, 7, 6],
+    [0, 4, 5, 6],
+    [0, 4, 7, 6],
+];
+
+/// a triangle mesh with a unit normal at each vertex
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub vertices: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+/// builds a [Mesh] for the surface where `sign * value == isovalue`
+struct Builder<'a> {
+    grid: &'a VolumeGrid,
+    sign: f32,
+    isovalue: f32,
+    /// the rows of the inverse of the matrix whose rows are the grid axes,
+    /// up to a positive factor, for turning index-space gradients into
+    /// Cartesian ones
+    inverse: [[f32; 3]; 3],
+    /// vertex on the edge between two grid points, by their indices
+    edges: HashMap<(usize, usize), u32>,
+    mesh: Mesh,
+}
+
+impl<'a> Builder<'a> {
+    fn new(grid: &'a VolumeGrid, sign: f32, isovalue: f32) -> Self {
+        let [a, b, c] = grid.axes;
+        let det = dot3(a, cross3(b, c));
+        let inverse = [cross3(b, c), cross3(c, a), cross3(a, b)]
+            .map(|r| r.map(|x| x * det.signum()));
+        Self {
+            grid,
+            sign,
+            isovalue,
+            inverse,
+            edges: HashMap::new(),
+            mesh: Mesh::default(),
+        }
+    }
+
+    fn index(&self, [i, j, k]: [usize; 3]) -> usize {
+        let [_, n1, n2] = self.grid.shape;
+        (i * n1 + j) * n2 + k
+    }
+
+    fn value(&self, p: [usize; 3]) -> f32 {
+        self.sign * self.grid.values[self.index(p)]
+    }
+
+    fn position(&self, p: [usize; 3]) -> [f32; 3] {
+        let mut ret = self.grid.origin;
+        for (axis, &n) in self.grid.axes.iter().zip(&p) {
+            for (r, x) in ret.iter_mut().zip(axis) {
+                *r += n as f32 * x;
+            }
+        }
+        ret
+    }
+
+    /// the direction in which the value falls fastest at grid point `p`,
+    /// which is the outward normal of the surface
+    fn normal(&self, p: [usize; 3]) -> [f32; 3] {
+        let mut ret = [0.0; 3];
+        for d in 0..3 {
+            let (mut lo, mut hi) = (p, p);
+            lo[d] = p[d].saturating_sub(1);
+            hi[d] = (p[d] + 1).min(self.grid.shape[d] - 1);
+            let g = (self.value(lo) - self.value(hi)) / (hi[d] - lo[d]) as f32;
+            for (r, x) in ret.iter_mut().zip(self.inverse[d]) {
+                *r += g * x;
+            }
+        }
+        ret
+    }
+
+    /// the index of the vertex where the surface crosses from `a` to `b`
+    fn vertex(&mut self, a: [usize; 3], b: [usize; 3]) -> u32 {
+        let key = {
+            let (ia, ib) = (self.index(a), self.index(b));
+            (ia.min(ib), ia.max(ib))
+        };
+        if let Some(&v) = self.edges.get(&key) {
+            return v;
+        }
+        let (va, vb) = (self.value(a), self.value(b));
+        let t = (self.isovalue - va) / (vb - va);
+        let lerp = |x: [f32; 3], y: [f32; 3]| {
+            [0, 1, 2].map(|c| x[c] + t * (y[c] - x[c]))
+        };
+        let n = lerp(self.normal(a), self.normal(b));
+        let len = n.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let v = self.mesh.vertices.len() as u32;
+        self.mesh
+            .vertices
+            .push(lerp(self.position(a), self.position(b)));
+        self.mesh.normals.push(if len > 0.0 {
+            n.map(|x| x / len)
+        } else {
+            [0.0; 3]
+        });
+        self.edges.insert(key, v);
+        v
+    }
+
+    fn tetrahedron(&mut self, corners: [[usize; 3]; 4]) {
+        let (inside, outside): (Vec<_>, Vec<_>) = corners
+            .into_iter()
+            .partition(|&p| self.value(p) > self.isovalue);
+        match (inside.as_slice(), outside.as_slice()) {
+            (&[a], &[b, c, d]) | (&[b, c, d], &[a]) => {
+                let tri =
+                    [self.vertex(a, b), self.vertex(a, c), self.vertex(a, d)];
+                self.mesh.triangles.push(tri);
+            }
+            (&[a, b], &[c, d]) => {
+                let (ac, ad) = (self.vertex(a, c), self.vertex(a, d));
+                let (bc, bd) = (self.vertex(b, c), self.vertex(b, d));
+                self.mesh.triangles.push([ac, ad, bd]);
+                self.mesh.triangles.push([ac, bd, bc]);
+            }
+            _ => {}
+        }
+    }
+
+    fn build(mut self) -> Mesh {
+        let [n0, n1, n2] = self.grid.shape;
+        for i in 0..n0.saturating_sub(1) {
+            for j in 0..n1.saturating_sub(1) {
+                for k in 0..n2.saturating_sub(1) {
+                    let corner = |c: usize| {
+                        let [di, dj, dk] = CORNERS[c];
+                        [i + di, j + dj, k + dk]
+                    };
+                    for tet in TETRAHEDRA {
+                        self.tetrahedron(tet.map(corner));
+                    }
+                }
+            }
+        }
+        self.mesh
+    }
+}
+
+/// the surface where `grid` equals `isovalue`
+pub fn isosurface(grid: &VolumeGrid, isovalue: f32) -> Mesh {
+    if isovalue >= 0.0 {
+        Builder::new(grid, 1.0, isovalue).build()
+    } else {
+        Builder::new(grid, -1.0, -isovalue).build()
+    }
+}
+
+/// the default isovalue for `grid`: [DEFAULT_ISOVALUE], or half the largest
+/// magnitude for grids whose values are all smaller than that
+fn default_isovalue(grid: &VolumeGrid) -> f32 {
+    let (lo, hi) = grid.range();
+    DEFAULT_ISOVALUE.min(0.5 * lo.abs().max(hi.abs()))
+}
+
+/// which volume is shown as isosurfaces, at what isovalue, and the meshes
+/// for it
+pub struct Isosurfaces {
+    /// index into [Molecule::volumes], or `None` to show nothing
+    pub volume: Option<usize>,
+    /// the magnitude of the isovalue, or `None` for [default_isovalue]
+    pub isovalue: Option<f32>,
+    /// what `meshes` were built for: the trajectory, frame, volume and
+    /// isovalue
+    built: Option<(usize, usize, usize, f32)>,
+    /// the positive and negative surfaces
+    meshes: [Mesh; 2],
+}
+
+impl Isosurfaces {
+    pub fn new(isovalue: Option<f32>) -> Self {
+        Self {
+            volume: Some(0),
+            isovalue,
+            built: None,
+            meshes: Default::default(),
+        }
+    }
+
+    fn grid<'a>(&self, mol: &'a Molecule) -> Option<&'a VolumeGrid> {
+        mol.volumes.get(self.volume?)
+    }
+
+    fn current_isovalue(&self, grid: &VolumeGrid) -> f32 {
+        self.isovalue.unwrap_or_else(|| default_isovalue(grid))
+    }
+
+    /// handle the isosurface keys, and rebuild the meshes if anything
+    /// changed. `key` identifies the trajectory and frame that `mol` comes
+    /// from:
+    ///
+    /// - V: cycle through the volumes of `mol`, then none
+    /// - [/]: decrease/increase the isovalue
+    pub fn update(&mut self, mol: &Molecule, key: (usize, usize)) {
+        if ui::key_pressed(KeyboardKey_KEY_V) {
+            self.volume = match self.volume {
+                Some(v) if v + 1 < mol.volumes.len() => Some(v + 1),
+                Some(_) => None,
+                None => Some(0),
+            };
+        }
+        let Some(grid) = self.grid(mol) else {
+            return;
+        };
+        let isovalue = self.current_isovalue(grid);
+        if ui::key_pressed(KeyboardKey_KEY_RIGHT_BRACKET) {
+            self.isovalue = Some(isovalue * ISOVALUE_STEP);
+        }
+        if ui::key_pressed(KeyboardKey_KEY_LEFT_BRACKET) {
+            self.isovalue = Some(isovalue / ISOVALUE_STEP);
+        }
+        let isovalue = self.current_isovalue(grid);
+        let want = (key.0, key.1, self.volume.unwrap_or_default(), isovalue);
+        if self.built != Some(want) {
+            self.meshes =
+                [isosurface(grid, isovalue), isosurface(grid, -isovalue)];
+            self.built = Some(want);
+        }
+    }
+
+    /// draw the surfaces of the current volume of `mol` as seen from `eye`
+    pub fn draw(&self, mol: &Molecule, eye: Vector3) {
+        if self.grid(mol).is_none() {
+            return;
+        }
+        let [pos, neg] = &self.meshes;
+        let colors: [Color; 2] = [color(POSITIVE_COLOR), color(NEGATIVE_COLOR)];
+        render::draw_meshes(&[(pos, colors[0]), (neg, colors[1])], eye);
+    }
+
+    /// a one-line description for the overlay, if a volume is shown
+    pub fn status(&self, mol: &Molecule) -> Option<String> {
+        let grid = self.grid(mol)?;
+        let isovalue = self.current_isovalue(grid);
+        Some(format!("isosurface: {} at ±{isovalue:.4}", grid.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `f(x, y, z)` on a grid of `n` points with spacing `h` along each
+    /// axis, centered on the origin
+    fn sample(n: usize, h: f32, f: impl Fn([f32; 3]) -> f32) -> VolumeGrid {
+        let origin = [-h * (n - 1) as f32 / 2.0; 3];
+        let mut values = Vec::with_capacity(n * n * n);
+        for i in 0..n {
+            for j in 0..n {
+                for k in 0..n {
+                    let p = [i, j, k].map(|m| m as f32 * h);
+                    values.push(f([0, 1, 2].map(|c| origin[c] + p[c])));
+                }
+            }
+        }
+        VolumeGrid {
+            name: String::from("test"),
+            origin,
+            axes: [[h, 0.0, 0.0], [0.0, h, 0.0], [0.0, 0.0, h]],
+            shape: [n; 3],
+            values,
+        }
+    }
+
+    fn norm(p: [f32; 3]) -> f32 {
+        p.iter().map(|x| x * x).sum::<f32>().sqrt()
+    }
+
+    #[test]
+    fn closed_sphere_with_outward_normals() {
+        // 0.5 on a sphere of radius 1.5
+        let grid = sample(21, 0.2, |p| 2.75 - norm(p).powi(2));
+        let mesh = isosurface(&grid, 0.5);
+        assert!(!mesh.triangles.is_empty());
+        for (v, n) in mesh.vertices.iter().zip(&mesh.normals) {
+            assert!((norm(*v) - 1.5).abs() < 0.02, "{v:?}");
+            assert!((norm(*n) - 1.0).abs() < 1e-4);
+            let radial = v.map(|x| x / norm(*v));
+            let cos: f32 = radial.iter().zip(n).map(|(a, b)| a * b).sum();
+            assert!(cos > 0.99, "{v:?} {n:?}");
+        }
+
+        // every edge is shared by exactly two triangles
+        let mut edges: HashMap<(u32, u32), usize> = HashMap::new();
+        for t in &mesh.triangles {
+            for k in 0..3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        assert!(edges.values().all(|&n| n == 2));
+    }
+
+    #[test]
+    fn negative_isovalues() {
+        let grid = sample(21, 0.2, |p| norm(p).powi(2) - 2.75);
+        let mesh = isosurface(&grid, -0.5);
+        assert!(!mesh.triangles.is_empty());
+        for v in &mesh.vertices {
+            assert!((norm(*v) - 1.5).abs() < 0.02);
+        }
+        // and the positive one further out
+        for v in &isosurface(&grid, 0.5).vertices {
+            assert!((norm(*v) - 3.25_f32.sqrt()).abs() < 0.02);
+        }
+        assert!(isosurface(&grid, 100.0).triangles.is_empty());
+    }
+
+    #[test]
+    fn default_isovalue_of_weak_grids() {
+        let grid = sample(3, 1.0, |p| 0.01 * p[0]);
+        assert_eq!(default_isovalue(&grid), 0.005);
+        let grid = sample(3, 1.0, |p| p[0]);
+        assert_eq!(default_isovalue(&grid), DEFAULT_ISOVALUE);
+    }
+}
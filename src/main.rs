@@ -1,145 +1,173 @@
-use std::{error::Error, fs::read_to_string, path::Path, str::FromStr};
-
-use donkey::{colors::color, vector3, Window};
-use raylib_sys::{Camera3D, Vector3};
-
-const NATOMS: usize = 13;
-
-const SYMS: [&str; NATOMS] = [
-    "X", "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg",
-];
-
-const COLORS: [raylib_sys::Color; NATOMS] = [
-    donkey::colors::BLACK, // X
-    donkey::colors::WHITE, // H
-    donkey::colors::BLACK, // He
-    donkey::colors::BLACK, // Li
-    donkey::colors::BLACK, // Be
-    donkey::colors::BLACK, // B
-    donkey::colors::BLACK, // C
-    donkey::colors::BLUE,  // N
-    donkey::colors::RED,   // O
-    donkey::colors::BLACK, // F
-    donkey::colors::BLACK, // Ne
-    donkey::colors::BLACK, // Na
-    donkey::colors::BLACK, // Mg
-];
-
-const RADII: [f32; NATOMS] = [
-    1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0,
-];
-
-struct Atom {
-    x: f32,
-    y: f32,
-    z: f32,
-    w: u8,
+use std::process::exit;
+
+use cli::{Args, Command, USAGE};
+use donkey::{colors::color, Window};
+use molecule::{Molecule, Trajectory};
+use playback::Playback;
+use raylib_sys::{
+    CameraProjection_CAMERA_ORTHOGRAPHIC, CameraProjection_CAMERA_PERSPECTIVE,
+    KeyboardKey_KEY_B, KeyboardKey_KEY_C, KeyboardKey_KEY_I,
+    KeyboardKey_KEY_PAGE_DOWN, KeyboardKey_KEY_PAGE_UP,
+};
+
+mod bonds;
+mod cell;
+mod cli;
+mod element;
+mod error;
+mod formats;
+mod hierarchy;
+mod isosurface;
+mod modes;
+mod molecule;
+mod neighbors;
+mod orbital;
+mod overlay;
+mod playback;
+mod render;
+mod ui;
+mod units;
+mod vector;
+mod vibration;
+mod volume;
+
+fn make_window(args: &Args) -> Window {
+    Window::init(args.width, args.height, &args.title)
 }
 
-impl Atom {
-    fn as_vec(&self) -> Vector3 {
-        vector3!(self.x, self.y, self.z)
+/// the property to color by after `cur` when cycling through the properties of
+/// `mol`, finishing with `None` for coloring by element
+fn next_color_by(mol: &Molecule, cur: Option<&str>) -> Option<String> {
+    let mut names = mol.properties.iter().map(|p| &p.name);
+    match cur {
+        None => names.next(),
+        Some(cur) => names.skip_while(|&n| n != cur).nth(1),
     }
+    .cloned()
 }
 
-impl FromStr for Atom {
-    type Err = Box<dyn Error>;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let sp: Vec<_> = s.split_ascii_whitespace().collect();
-        if sp.len() != 4 {
-            Err("invalid line length for Atom")?;
+fn main() {
+    let args = match Command::parse(std::env::args().skip(1)) {
+        Ok(Command::Run(args)) => *args,
+        Ok(Command::Help) => {
+            print!("{USAGE}");
+            return;
         }
-        Ok(Atom {
-            w: SYMS.iter().position(|&s| s == sp[0]).expect("unknown atom")
-                as u8,
-            x: sp[1].parse()?,
-            y: sp[2].parse()?,
-            z: sp[3].parse()?,
+        Err(e) => {
+            eprintln!("review: {e}");
+            eprintln!("Try 'review --help' for more information.");
+            exit(2);
+        }
+    };
+
+    // report every file that fails to load, but keep going as long as at
+    // least one succeeds
+    let trajs: Vec<Trajectory> = args
+        .files
+        .iter()
+        .flat_map(|path| match formats::load(path, &args.load) {
+            Ok(trajs) => trajs,
+            Err(e) => {
+                eprintln!("review: {e}");
+                Vec::new()
+            }
         })
+        .collect();
+    if trajs.is_empty() {
+        exit(1);
     }
-}
-
-struct Molecule {
-    atoms: Vec<Atom>,
-    bonds: Vec<(usize, usize)>,
-}
-
-fn load_xyz(path: impl AsRef<Path>) -> Molecule {
-    let s = read_to_string(path).unwrap();
-    let mut lines = s.lines().enumerate();
-    let mut atoms = Vec::new();
-    while let Some((i, line)) = lines.next() {
-        if i == 0 && line.len() == 1 {
-            lines.next(); // discard comment
-            continue;
+    if args.zmatrix {
+        for (k, traj) in trajs.iter().enumerate() {
+            if k > 0 {
+                println!();
+            }
+            if let Some(mol) = traj.frames.last() {
+                print!("{}", formats::write_zmatrix(mol));
+            }
         }
-        atoms.push(line.parse().unwrap());
+        return;
     }
-
-    let mut bonds = Vec::new();
-    for i in 0..atoms.len() {
-        for j in i + 1..atoms.len() {
-            let Atom { x: xi, y: yi, z: zi, .. } = atoms[i];
-            let Atom { x: xj, y: yj, z: zj, .. } = atoms[j];
-            let dist =
-                ((xi - xj).powi(2) + (yi - yj).powi(2) + (zi - zj).powi(2))
-                    .sqrt();
-            if dist < 3.0 {
-                bonds.push((i, j));
-            }
+    if let Some(name) = &args.color_by {
+        let found = trajs
+            .iter()
+            .any(|t| t.frames.iter().any(|m| m.property(name).is_some()));
+        if !found {
+            eprintln!("review: warning: no atom property named `{name}`");
         }
     }
-
-    Molecule { atoms, bonds }
-}
-
-fn make_window() -> Window {
-    let width = 800;
-    let height = 600;
-    let title = "review";
-    Window::init(width, height, title)
-}
-
-fn main() {
-    let win = make_window();
-    win.set_target_fps(30);
-    let mut camera = Camera3D {
-        position: vector3!(0.0, 0.0, -5.0),
-        target: vector3!(0.0, 0.0, 0.0),
-        up: vector3!(0.0, 1.0, 0.0),
-        fovy: 90.0,
-        projection: raylib_sys::CameraProjection_CAMERA_PERSPECTIVE as i32,
+    let mut players: Vec<Playback> = trajs
+        .iter()
+        .map(|t| Playback::new(t.frames.len()))
+        .collect();
+
+    let win = make_window(&args);
+    win.set_target_fps(args.fps);
+    let projection = if args.orthographic {
+        CameraProjection_CAMERA_ORTHOGRAPHIC
+    } else {
+        CameraProjection_CAMERA_PERSPECTIVE
+    };
+    let mut camera = raylib_sys::Camera3D {
+        position: args.camera,
+        target: args.target,
+        up: donkey::vector3!(0.0, 1.0, 0.0),
+        fovy: args.fovy,
+        projection: projection as i32,
     };
     let background = color(0x383838AA);
 
-    let mol = load_xyz("testfiles/acetaldehyde.xyz");
-
+    let mut cur = 0;
+    let mut color_by = args.color_by.clone();
+    let mut show_info = false;
+    let mut show_cell = true;
+    let mut surfaces = isosurface::Isosurfaces::new(args.isovalue);
+    let mut modes = modes::ModeView::new();
     while !win.should_close() {
+        if ui::key_pressed(KeyboardKey_KEY_PAGE_DOWN) {
+            cur = (cur + 1) % trajs.len();
+        }
+        if ui::key_pressed(KeyboardKey_KEY_PAGE_UP) {
+            cur = (cur + trajs.len() - 1) % trajs.len();
+        }
+        let player = &mut players[cur];
+        player.update(ui::frame_time());
+        let mol = &trajs[cur].frames[player.frame];
+        if ui::key_pressed(KeyboardKey_KEY_C) {
+            color_by = next_color_by(mol, color_by.as_deref());
+        }
+        if ui::key_pressed(KeyboardKey_KEY_I) {
+            show_info = !show_info;
+        }
+        if ui::key_pressed(KeyboardKey_KEY_B) {
+            show_cell = !show_cell;
+        }
+        surfaces.update(mol, (cur, player.frame));
+        modes.update(mol, ui::frame_time());
+
         win.begin_drawing();
         win.clear_background(background);
         win.begin_mode3d(camera);
 
         win.update_camera(&mut camera, donkey::CameraMode::ThirdPerson);
 
-        for atom in &mol.atoms {
-            win.draw_sphere(
-                atom.as_vec(),
-                RADII[atom.w as usize] / 2.0,
-                COLORS[atom.w as usize],
-            );
-        }
-
-        for (i, j) in &mol.bonds {
-            win.draw_cylinder(
-                mol.atoms[*i].as_vec(),
-                mol.atoms[*j].as_vec(),
-                0.1,
-                donkey::colors::LIGHTGRAY,
-            );
+        let colors = render::atom_colors(mol, color_by.as_deref());
+        let displaced = modes.displaced(mol);
+        let shown = displaced.as_ref().unwrap_or(mol);
+        render::draw_molecule(&win, shown, args.style, &colors);
+        if let Some(cell) = mol.cell.as_ref().filter(|_| show_cell) {
+            render::draw_cell(cell);
         }
+        modes.draw(mol);
+        surfaces.draw(mol, camera.position);
 
         win.end_mode3d();
+
+        let status: Vec<String> = [surfaces.status(mol), modes.status(mol)]
+            .into_iter()
+            .flatten()
+            .collect();
+        overlay::draw(mol, player, &status, color_by.as_deref(), show_info);
+        modes.draw_list(mol);
         win.end_drawing();
     }
 }
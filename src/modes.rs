@@ -0,0 +1,308 @@
+//! Showing a normal mode, either by animating the atoms back and forth along
+//! it or by drawing its displacements as arrows, and the clickable list of
+//! modes to choose from
+
+use std::f32::consts::TAU;
+
+use donkey::colors::{color, WHITE};
+use raylib_sys::{
+    KeyboardKey_KEY_COMMA, KeyboardKey_KEY_J, KeyboardKey_KEY_K,
+    KeyboardKey_KEY_M, KeyboardKey_KEY_N, KeyboardKey_KEY_P,
+    KeyboardKey_KEY_PERIOD, KeyboardKey_KEY_R,
+};
+
+use crate::{
+    molecule::{Atom, Molecule},
+    overlay::{LINE_HEIGHT, MARGIN},
+    render, ui,
+    vector::{add, norm, scale},
+    vibration::NormalMode,
+};
+
+/// the largest displacement of any atom, in Å
+const DEFAULT_AMPLITUDE: f32 = 0.3;
+const MIN_AMPLITUDE: f32 = 0.02;
+const MAX_AMPLITUDE: f32 = 2.0;
+
+/// vibrations per second, the same for every mode whatever its frequency
+const DEFAULT_SPEED: f32 = 1.0;
+const MIN_SPEED: f32 = 0.125;
+const MAX_SPEED: f32 = 8.0;
+
+/// factor by which the amplitude and speed keys change them
+const STEP: f32 = 1.5;
+
+/// length of the longest arrow as a multiple of the amplitude
+const ARROW_SCALE: f32 = 4.0;
+
+const ARROW_COLOR: u32 = 0x40E040FF;
+
+/// color of the current mode in the list
+const SELECTED_COLOR: u32 = 0xFFD040FF;
+
+/// width of the mode list in pixels
+const LIST_WIDTH: i32 = 320;
+
+/// `1650.3 cm-1` for a frequency, or `412.7i cm-1` for an imaginary one
+fn wavenumber(frequency: f32) -> String {
+    if frequency < 0.0 {
+        format!("{:.1}i cm-1", -frequency)
+    } else {
+        format!("{frequency:.1} cm-1")
+    }
+}
+
+/// the frequency of `mode` and whatever else the program reported about it
+fn describe(mode: &NormalMode) -> String {
+    let mut ret = wavenumber(mode.frequency);
+    if let Some(mass) = mode.reduced_mass {
+        ret += &format!(", {mass:.2} amu");
+    }
+    if let Some(ir) = mode.ir_intensity {
+        ret += &format!(", IR {ir:.1} km/mol");
+    }
+    ret
+}
+
+/// which normal mode is shown and how
+pub struct ModeView {
+    /// index into [Molecule::modes] of the mode shown, if any
+    pub mode: Option<usize>,
+    pub show_list: bool,
+    /// draw the displacements as arrows instead of animating them
+    pub arrows: bool,
+    /// the largest displacement of any atom, in Å
+    pub amplitude: f32,
+    /// vibrations per second
+    pub speed: f32,
+    /// how far through a vibration the animation is, from 0 to 1
+    phase: f32,
+}
+
+impl ModeView {
+    pub fn new() -> Self {
+        Self {
+            mode: None,
+            show_list: false,
+            arrows: false,
+            amplitude: DEFAULT_AMPLITUDE,
+            speed: DEFAULT_SPEED,
+            phase: 0.0,
+        }
+    }
+
+    fn current<'a>(&self, mol: &'a Molecule) -> Option<&'a NormalMode> {
+        mol.modes.get(self.mode?)
+    }
+
+    /// the first mode in the list and the number of modes shown, scrolled to
+    /// keep the current mode in view
+    fn list_rows(&self, nmodes: usize) -> (usize, usize) {
+        let (_, height) = ui::screen_size();
+        let fit = ((height - 2 * MARGIN) / LINE_HEIGHT).max(1) as usize;
+        let rows = nmodes.min(fit);
+        let first = self.mode.unwrap_or(0).saturating_sub(rows / 2);
+        (first.min(nmodes - rows), rows)
+    }
+
+    /// handle the normal mode keys and clicks on the mode list, and advance
+    /// the animation by `dt` seconds:
+    ///
+    /// - N/P: show the next/previous mode, then none
+    /// - M: toggle the list of modes, where clicking a mode shows it
+    /// - ,/.: decrease/increase the amplitude
+    /// - J/K: slow down/speed up the vibration
+    /// - R: toggle drawing arrows instead of animating
+    pub fn update(&mut self, mol: &Molecule, dt: f32) {
+        let n = mol.modes.len();
+        if n == 0 {
+            return;
+        }
+        if ui::key_pressed(KeyboardKey_KEY_N) {
+            self.mode = match self.mode {
+                Some(m) if m + 1 < n => Some(m + 1),
+                Some(_) => None,
+                None => Some(0),
+            };
+        }
+        if ui::key_pressed(KeyboardKey_KEY_P) {
+            self.mode = match self.mode {
+                Some(0) => None,
+                Some(m) => Some(m.min(n) - 1),
+                None => Some(n - 1),
+            };
+        }
+        if ui::key_pressed(KeyboardKey_KEY_M) {
+            self.show_list = !self.show_list;
+        }
+        if ui::key_pressed(KeyboardKey_KEY_R) {
+            self.arrows = !self.arrows;
+        }
+        if ui::key_pressed(KeyboardKey_KEY_PERIOD) {
+            self.amplitude = (self.amplitude * STEP).min(MAX_AMPLITUDE);
+        }
+        if ui::key_pressed(KeyboardKey_KEY_COMMA) {
+            self.amplitude = (self.amplitude / STEP).max(MIN_AMPLITUDE);
+        }
+        if ui::key_pressed(KeyboardKey_KEY_K) {
+            self.speed = (self.speed * STEP).min(MAX_SPEED);
+        }
+        if ui::key_pressed(KeyboardKey_KEY_J) {
+            self.speed = (self.speed / STEP).max(MIN_SPEED);
+        }
+        if let Some((x, y)) = ui::clicked().filter(|_| self.show_list) {
+            let (width, _) = ui::screen_size();
+            let (first, rows) = self.list_rows(n);
+            let row = (y - MARGIN).div_euclid(LINE_HEIGHT);
+            if x >= width - LIST_WIDTH && (0..rows as i32).contains(&row) {
+                let k = first + row as usize;
+                self.mode = if self.mode == Some(k) { None } else { Some(k) };
+            }
+        }
+        self.phase = (self.phase + dt * self.speed).fract();
+    }
+
+    /// the displacement of each atom along `mode`, scaled so that the
+    /// largest is [ModeView::amplitude]
+    fn displacements(&self, mode: &NormalMode) -> Vec<[f32; 3]> {
+        let largest = mode
+            .displacements
+            .iter()
+            .map(|&[x, y, z]| norm(donkey::vector3!(x, y, z)))
+            .fold(0.0, f32::max);
+        let f = if largest > 0.0 {
+            self.amplitude / largest
+        } else {
+            0.0
+        };
+        mode.displacements
+            .iter()
+            .map(|d| d.map(|x| x * f))
+            .collect()
+    }
+
+    /// `mol` with its atoms moved along the current mode, or `None` if no
+    /// mode is being animated. Only the atoms, bonds and cell are copied,
+    /// which is all that drawing the molecule needs
+    pub fn displaced(&self, mol: &Molecule) -> Option<Molecule> {
+        let mode = self.current(mol).filter(|_| !self.arrows)?;
+        let s = (TAU * self.phase).sin();
+        let atoms = mol
+            .atoms
+            .iter()
+            .zip(self.displacements(mode))
+            .map(|(a, [dx, dy, dz])| Atom {
+                x: a.x + s * dx,
+                y: a.y + s * dy,
+                z: a.z + s * dz,
+                w: a.w,
+            })
+            .collect();
+        Some(Molecule {
+            atoms,
+            bonds: mol.bonds.clone(),
+            cell: mol.cell.clone(),
+            ..Default::default()
+        })
+    }
+
+    /// draw the arrows of the current mode, if it is shown that way
+    pub fn draw(&self, mol: &Molecule) {
+        let Some(mode) = self.current(mol).filter(|_| self.arrows) else {
+            return;
+        };
+        for (atom, [x, y, z]) in mol.atoms.iter().zip(self.displacements(mode))
+        {
+            let d = scale(donkey::vector3!(x, y, z), ARROW_SCALE);
+            let start = atom.as_vec();
+            render::draw_arrow(start, add(start, d), color(ARROW_COLOR));
+        }
+    }
+
+    /// draw the list of modes along the right edge of the window, if shown
+    pub fn draw_list(&self, mol: &Molecule) {
+        if !self.show_list || mol.modes.is_empty() {
+            return;
+        }
+        let (width, _) = ui::screen_size();
+        let (first, rows) = self.list_rows(mol.modes.len());
+        for (row, k) in (first..first + rows).enumerate() {
+            let text = format!("{:>3}  {}", k + 1, describe(&mol.modes[k]));
+            let c = if self.mode == Some(k) {
+                color(SELECTED_COLOR)
+            } else {
+                WHITE
+            };
+            let y = MARGIN + row as i32 * LINE_HEIGHT;
+            ui::draw_text(&text, width - LIST_WIDTH, y, c);
+        }
+    }
+
+    /// a one-line description of the current mode for the overlay, if any
+    pub fn status(&self, mol: &Molecule) -> Option<String> {
+        let mode = self.current(mol)?;
+        let how = if self.arrows {
+            String::from("arrows")
+        } else {
+            format!("amplitude {:.2} Å, {:.2} Hz", self.amplitude, self.speed)
+        };
+        Some(format!(
+            "mode {}/{}: {} [{how}]",
+            self.mode? + 1,
+            mol.modes.len(),
+            describe(mode)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// CO stretching along z, the oxygen moving half as far as the carbon
+    fn co_stretch() -> Molecule {
+        Molecule {
+            atoms: vec![
+                Atom { x: 0.0, y: 0.0, z: 0.0, w: 6 },
+                Atom { x: 0.0, y: 0.0, z: 1.13, w: 8 },
+            ],
+            modes: vec![NormalMode {
+                frequency: 2143.0,
+                reduced_mass: Some(6.86),
+                ir_intensity: None,
+                displacements: vec![[0.0, 0.0, -0.8], [0.0, 0.0, 0.4]],
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn displaced_at_the_peak_of_a_vibration() {
+        let mol = co_stretch();
+        let mut view = ModeView::new();
+        assert!(view.displaced(&mol).is_none());
+
+        view.mode = Some(0);
+        view.phase = 0.25;
+        let moved = view.displaced(&mol).unwrap();
+        // the carbon moves by the whole amplitude, the oxygen by half of it
+        assert!((moved.atoms[0].z + DEFAULT_AMPLITUDE).abs() < 1e-6);
+        assert!(
+            (moved.atoms[1].z - 1.13 - DEFAULT_AMPLITUDE / 2.0).abs() < 1e-6
+        );
+        assert_eq!(moved.atoms[1].w, 8);
+
+        view.arrows = true;
+        assert!(view.displaced(&mol).is_none());
+    }
+
+    #[test]
+    fn descriptions() {
+        let mut mode = co_stretch().modes.remove(0);
+        assert_eq!(describe(&mode), "2143.0 cm-1, 6.86 amu");
+        mode.frequency = -412.7;
+        mode.reduced_mass = None;
+        mode.ir_intensity = Some(55.25);
+        assert_eq!(describe(&mode), "412.7i cm-1, IR 55.2 km/mol");
+    }
+}
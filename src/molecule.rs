@@ -0,0 +1,330 @@
+use donkey::vector3;
+use raylib_sys::Vector3;
+
+use crate::{
+    cell::Cell,
+    element::{Element, ELEMENTS},
+    hierarchy::Hierarchy,
+    units::Unit,
+    vibration::NormalMode,
+    volume::VolumeGrid,
+};
+
+pub struct Atom {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: u8,
+}
+
+impl Atom {
+    pub fn element(&self) -> &'static Element {
+        &ELEMENTS[self.w as usize]
+    }
+
+    pub fn as_vec(&self) -> Vector3 {
+        vector3!(self.x, self.y, self.z)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BondOrder {
+    #[default]
+    Single,
+    Double,
+    Triple,
+    Aromatic,
+}
+
+/// a bond between atoms `i` and `j`, with `i < j` for perceived bonds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bond {
+    pub i: usize,
+    pub j: usize,
+    pub order: BondOrder,
+}
+
+impl Bond {
+    pub fn new(i: usize, j: usize, order: BondOrder) -> Self {
+        Self { i, j, order }
+    }
+}
+
+/// the values of an [AtomProperty], stored row-major with one row per atom
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValues {
+    Real(Vec<f32>),
+    Int(Vec<i64>),
+    Bool(Vec<bool>),
+    Str(Vec<String>),
+}
+
+impl PropertyValues {
+    /// repeat the whole array `n` times
+    fn repeat(&mut self, n: usize) {
+        match self {
+            PropertyValues::Real(v) => *v = v.repeat(n),
+            PropertyValues::Int(v) => *v = v.repeat(n),
+            PropertyValues::Bool(v) => *v = v.repeat(n),
+            PropertyValues::Str(v) => {
+                *v = v.iter().cycle().take(n * v.len()).cloned().collect()
+            }
+        }
+    }
+}
+
+/// an arbitrary per-atom array such as forces or partial charges
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtomProperty {
+    pub name: String,
+    /// number of values per atom
+    pub ncols: usize,
+    pub values: PropertyValues,
+}
+
+impl AtomProperty {
+    /// reduce each atom's row to a single number for coloring: the value
+    /// itself for one column, or the norm of the row otherwise. Booleans map
+    /// to 0 and 1, and strings to the order in which each distinct value
+    /// first appears
+    pub fn scalars(&self) -> Vec<f32> {
+        let values: Vec<f32> = match &self.values {
+            PropertyValues::Real(v) => v.clone(),
+            PropertyValues::Int(v) => v.iter().map(|&i| i as f32).collect(),
+            PropertyValues::Bool(v) => {
+                v.iter().map(|&b| b as u8 as f32).collect()
+            }
+            PropertyValues::Str(v) => {
+                let mut seen: Vec<&String> = Vec::new();
+                v.iter()
+                    .map(|s| match seen.iter().position(|&t| t == s) {
+                        Some(i) => i as f32,
+                        None => {
+                            seen.push(s);
+                            (seen.len() - 1) as f32
+                        }
+                    })
+                    .collect()
+            }
+        };
+        if self.ncols == 1 {
+            return values;
+        }
+        values
+            .chunks(self.ncols)
+            .map(|row| row.iter().map(|x| x * x).sum::<f32>().sqrt())
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct Molecule {
+    pub atoms: Vec<Atom>,
+    pub bonds: Vec<Bond>,
+    /// free-form title or comment line from the input file
+    pub comment: String,
+    pub cell: Option<Cell>,
+    pub properties: Vec<AtomProperty>,
+    /// per-frame key/value metadata, such as energies
+    pub info: Vec<(String, String)>,
+    /// the length unit of the input file. Coordinates and cell vectors are
+    /// converted to Å on loading regardless
+    pub units: Unit,
+    /// chains and residues for macromolecular formats
+    pub hierarchy: Option<Hierarchy>,
+    /// vibrations from a frequency calculation at this geometry
+    pub modes: Vec<NormalMode>,
+    /// volumetric data such as densities and orbitals
+    pub volumes: Vec<VolumeGrid>,
+}
+
+impl Molecule {
+    pub fn property(&self, name: &str) -> Option<&AtomProperty> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    pub fn positions(&self) -> Vec<[f32; 3]> {
+        self.atoms.iter().map(|a| [a.x, a.y, a.z]).collect()
+    }
+
+    /// convert coordinates and cell vectors given in `units` to Å
+    pub fn convert_from(&mut self, units: Unit) {
+        let f = units.to_angstrom();
+        for atom in &mut self.atoms {
+            atom.x *= f;
+            atom.y *= f;
+            atom.z *= f;
+        }
+        if let Some(cell) = &mut self.cell {
+            for v in cell.vectors.iter_mut().flatten() {
+                *v *= f;
+            }
+        }
+        self.units = units;
+    }
+
+    /// the vector from atom `i` to atom `j`, or to its nearest periodic image
+    /// if `self` has a periodic cell
+    pub fn separation(&self, i: usize, j: usize) -> [f32; 3] {
+        let (a, b) = (&self.atoms[i], &self.atoms[j]);
+        let d = [b.x - a.x, b.y - a.y, b.z - a.z];
+        match &self.cell {
+            Some(cell) if cell.is_periodic() => cell.minimum_image(d),
+            _ => d,
+        }
+    }
+
+    /// move every atom into the cell along its periodic directions
+    pub fn wrap(&mut self) {
+        let Some(cell) = &self.cell else {
+            return;
+        };
+        for atom in &mut self.atoms {
+            [atom.x, atom.y, atom.z] = cell.wrap([atom.x, atom.y, atom.z]);
+        }
+    }
+
+    /// repeat the contents of the cell `n[k]` times along each periodic
+    /// lattice vector k, making the cell the whole supercell. Bonds,
+    /// per-atom properties and normal mode displacements are repeated along
+    /// with the atoms, but the chains of a hierarchy can't be, so it is
+    /// dropped
+    pub fn replicate(&mut self, n: [usize; 3]) {
+        let Some(cell) = &mut self.cell else {
+            return;
+        };
+        let n = [0, 1, 2].map(|k| if cell.pbc[k] { n[k].max(1) } else { 1 });
+        let copies: usize = n.iter().product();
+        if copies == 1 {
+            return;
+        }
+        let natoms = self.atoms.len();
+        let mut atoms = Vec::with_capacity(copies * natoms);
+        for i in 0..n[0] {
+            for j in 0..n[1] {
+                for k in 0..n[2] {
+                    let [dx, dy, dz] =
+                        cell.cartesian([i, j, k].map(|m| m as f32));
+                    atoms.extend(self.atoms.iter().map(|a| Atom {
+                        x: a.x + dx,
+                        y: a.y + dy,
+                        z: a.z + dz,
+                        w: a.w,
+                    }));
+                }
+            }
+        }
+        self.atoms = atoms;
+        self.bonds = (0..copies)
+            .flat_map(|c| {
+                let offset = c * natoms;
+                self.bonds.iter().map(move |b| Bond {
+                    i: b.i + offset,
+                    j: b.j + offset,
+                    ..*b
+                })
+            })
+            .collect();
+        for prop in &mut self.properties {
+            prop.values.repeat(copies);
+        }
+        for mode in &mut self.modes {
+            mode.displacements = mode.displacements.repeat(copies);
+        }
+        for (v, m) in cell.vectors.iter_mut().zip(n) {
+            *v = v.map(|x| x * m as f32);
+        }
+        self.hierarchy = None;
+    }
+
+    /// whether `self` and `other` have the same elements in the same order
+    pub fn same_atoms(&self, other: &Molecule) -> bool {
+        self.atoms.len() == other.atoms.len()
+            && self.atoms.iter().zip(&other.atoms).all(|(a, b)| a.w == b.w)
+    }
+}
+
+/// a sequence of geometries for the same atoms, such as the steps of an
+/// optimization or an MD run. There is always at least one frame
+pub struct Trajectory {
+    pub frames: Vec<Molecule>,
+}
+
+impl From<Molecule> for Trajectory {
+    fn from(mol: Molecule) -> Self {
+        Self { frames: vec![mol] }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a hydrogen molecule straddling the x face of a 3 Å cube
+    fn h2_across_face(pbc: [bool; 3]) -> Molecule {
+        let cell = Cell {
+            pbc,
+            ..Cell::new([[3.0, 0.0, 0.0], [0.0, 3.0, 0.0], [0.0, 0.0, 3.0]])
+        };
+        Molecule {
+            atoms: vec![
+                Atom { x: 0.2, y: 1.0, z: 1.0, w: 1 },
+                Atom { x: 3.3, y: 1.0, z: 1.0, w: 1 },
+            ],
+            bonds: vec![Bond::new(0, 1, BondOrder::Single)],
+            cell: Some(cell),
+            properties: vec![AtomProperty {
+                name: String::from("label"),
+                ncols: 1,
+                values: PropertyValues::Str(vec!["a".into(), "b".into()]),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn separation_uses_the_nearest_image() {
+        let mol = h2_across_face([true; 3]);
+        let d = mol.separation(0, 1);
+        assert!((d[0] - 0.1).abs() < 1e-5 && d[1] == 0.0 && d[2] == 0.0);
+        let mol = h2_across_face([false; 3]);
+        assert_eq!(mol.separation(0, 1), [3.1, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn wrap_into_the_cell() {
+        let mut mol = h2_across_face([true; 3]);
+        mol.wrap();
+        assert!((mol.atoms[1].x - 0.3).abs() < 1e-5);
+        assert!((mol.atoms[0].x - 0.2).abs() < 1e-5);
+
+        let mut mol = h2_across_face([false, true, true]);
+        mol.wrap();
+        assert!((mol.atoms[1].x - 3.3).abs() < 1e-5);
+    }
+
+    #[test]
+    fn replicate_atoms_bonds_and_properties() {
+        let mut mol = h2_across_face([true, true, false]);
+        // the non-periodic direction isn't repeated
+        mol.replicate([2, 3, 4]);
+        assert_eq!(mol.atoms.len(), 12);
+        let cell = mol.cell.as_ref().unwrap();
+        assert_eq!(cell.vectors[0], [6.0, 0.0, 0.0]);
+        assert_eq!(cell.vectors[1], [0.0, 9.0, 0.0]);
+        assert_eq!(cell.vectors[2], [0.0, 0.0, 3.0]);
+
+        // the copies go along c fastest, then b, then a
+        let last = &mol.atoms[11];
+        assert_eq!([last.x, last.y, last.z], [6.3, 7.0, 1.0]);
+        let bonds: Vec<(usize, usize)> =
+            mol.bonds.iter().map(|b| (b.i, b.j)).collect();
+        assert_eq!(bonds, [(0, 1), (2, 3), (4, 5), (6, 7), (8, 9), (10, 11)]);
+        let labels = &mol.property("label").unwrap().values;
+        let PropertyValues::Str(labels) = labels else {
+            panic!("labels aren't strings");
+        };
+        assert_eq!(labels.len(), 12);
+        assert_eq!(labels[10..], ["a", "b"]);
+    }
+}
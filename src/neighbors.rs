@@ -0,0 +1,189 @@
+//! Uniform grid (cell list) neighbor search. Points are binned into cubic
+//! cells at least as wide as the search cutoff, so every pair within the
+//! cutoff lies in the same or adjacent cells and finding them all takes time
+//! linear in the number of points.
+
+/// cap the number of cells at this multiple of the number of points so that
+/// sparse systems don't allocate huge, mostly empty grids
+const MAX_CELLS_PER_POINT: usize = 8;
+
+pub struct Grid {
+    origin: [f32; 3],
+    /// edge length of each cell
+    size: f32,
+    dims: [usize; 3],
+    /// `items[start[c]..start[c + 1]]` are the points in cell `c`
+    start: Vec<usize>,
+    items: Vec<usize>,
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2))
+        .sqrt()
+}
+
+impl Grid {
+    /// bin `points` into cells with edges of at least `cutoff`
+    pub fn new(points: &[[f32; 3]], cutoff: f32) -> Self {
+        let mut lo = [f32::INFINITY; 3];
+        let mut hi = [f32::NEG_INFINITY; 3];
+        for p in points {
+            for k in 0..3 {
+                lo[k] = lo[k].min(p[k]);
+                hi[k] = hi[k].max(p[k]);
+            }
+        }
+        if points.is_empty() {
+            (lo, hi) = ([0.0; 3], [0.0; 3]);
+        }
+
+        let mut size = cutoff.max(f32::EPSILON);
+        let extent = |k: usize| hi[k] - lo[k];
+        let ncells = |size: f32| -> f64 {
+            (0..3)
+                .map(|k| (extent(k) / size).floor() as f64 + 1.0)
+                .product()
+        };
+        let max_cells = (MAX_CELLS_PER_POINT * points.len()).max(1) as f64;
+        while ncells(size) > max_cells {
+            size *= (ncells(size) / max_cells).cbrt().max(1.01) as f32;
+        }
+        let dims = [0, 1, 2].map(|k| (extent(k) / size) as usize + 1);
+
+        let mut ret = Self {
+            origin: lo,
+            size,
+            dims,
+            start: vec![0; dims[0] * dims[1] * dims[2] + 1],
+            items: vec![0; points.len()],
+        };
+        // counting sort of the points by cell
+        let cells: Vec<usize> =
+            points.iter().map(|&p| ret.cell_of(p)).collect();
+        for &c in &cells {
+            ret.start[c + 1] += 1;
+        }
+        for c in 1..ret.start.len() {
+            ret.start[c] += ret.start[c - 1];
+        }
+        let mut next = ret.start.clone();
+        for (i, &c) in cells.iter().enumerate() {
+            ret.items[next[c]] = i;
+            next[c] += 1;
+        }
+        ret
+    }
+
+    fn coords_of(&self, p: [f32; 3]) -> [usize; 3] {
+        [0, 1, 2].map(|k| {
+            (((p[k] - self.origin[k]) / self.size) as usize)
+                .min(self.dims[k] - 1)
+        })
+    }
+
+    fn cell_of(&self, p: [f32; 3]) -> usize {
+        let [x, y, z] = self.coords_of(p);
+        (x * self.dims[1] + y) * self.dims[2] + z
+    }
+
+    /// call `f(i, j, distance)` for every pair of `points` with `i < j` that
+    /// are closer than `cutoff`. `points` must be the points the grid was
+    /// built from, and `cutoff` no larger than the one it was built with
+    pub fn for_each_pair(
+        &self,
+        points: &[[f32; 3]],
+        cutoff: f32,
+        mut f: impl FnMut(usize, usize, f32),
+    ) {
+        for (i, &p) in points.iter().enumerate() {
+            let [x, y, z] = self.coords_of(p);
+            for cx in x.saturating_sub(1)..=(x + 1).min(self.dims[0] - 1) {
+                for cy in y.saturating_sub(1)..=(y + 1).min(self.dims[1] - 1) {
+                    for cz in
+                        z.saturating_sub(1)..=(z + 1).min(self.dims[2] - 1)
+                    {
+                        let c = (cx * self.dims[1] + cy) * self.dims[2] + cz;
+                        for &j in &self.items[self.start[c]..self.start[c + 1]]
+                        {
+                            if j <= i {
+                                continue;
+                            }
+                            let d = distance(p, points[j]);
+                            if d < cutoff {
+                                f(i, j, d);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a small xorshift generator, as in the benchmark
+    fn random_points(n: usize, side: f32, mut seed: u64) -> Vec<[f32; 3]> {
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed >> 40) as f32 / (1u64 << 24) as f32 * side
+        };
+        (0..n).map(|_| [next(), next(), next()]).collect()
+    }
+
+    fn grid_pairs(points: &[[f32; 3]], cutoff: f32) -> Vec<(usize, usize)> {
+        let mut ret = Vec::new();
+        Grid::new(points, cutoff).for_each_pair(points, cutoff, |i, j, d| {
+            assert!(i < j);
+            assert_eq!(d, distance(points[i], points[j]));
+            ret.push((i, j));
+        });
+        ret.sort_unstable();
+        ret
+    }
+
+    fn brute_pairs(points: &[[f32; 3]], cutoff: f32) -> Vec<(usize, usize)> {
+        let n = points.len();
+        (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .filter(|&(i, j)| distance(points[i], points[j]) < cutoff)
+            .collect()
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for (n, side, cutoff) in
+            [(500, 17.0, 1.6), (300, 10.0, 3.5), (50, 2.0, 0.7)]
+        {
+            let points = random_points(n, side, 0x2545_f491_4f6c_dd1d);
+            let expected = brute_pairs(&points, cutoff);
+            assert!(!expected.is_empty());
+            assert_eq!(grid_pairs(&points, cutoff), expected);
+        }
+    }
+
+    #[test]
+    fn sparse_points_cap_the_grid() {
+        // a few points far apart would need billions of cells of this size
+        let mut points = random_points(20, 1000.0, 7);
+        points.push([0.5, 0.5, 0.5]);
+        points.push([0.5, 0.5, 1.5]);
+        let grid = Grid::new(&points, 1.1);
+        let ncells: usize = grid.dims.iter().product();
+        assert!(ncells <= MAX_CELLS_PER_POINT * points.len());
+        assert_eq!(grid_pairs(&points, 1.1), brute_pairs(&points, 1.1));
+    }
+
+    #[test]
+    fn degenerate_inputs() {
+        assert!(grid_pairs(&[], 1.0).is_empty());
+        assert!(grid_pairs(&[[1.0, 2.0, 3.0]], 1.0).is_empty());
+        // coincident points are at distance zero
+        let points = [[1.0, 2.0, 3.0]; 3];
+        assert_eq!(grid_pairs(&points, 0.5), [(0, 1), (0, 2), (1, 2)]);
+    }
+}
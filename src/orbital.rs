@@ -0,0 +1,428 @@
+//! Molecular orbitals expanded in contracted Gaussian basis functions, and
+//! their values or the electron density on a grid around the molecule.
+//! Lengths are in bohr here, as in the programs that write them, and the
+//! values are in atomic units
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::{molecule::Molecule, units::BOHR_TO_ANGSTROM, volume::VolumeGrid};
+
+/// distance in Å from the atoms to the edges of an orbital grid
+const GRID_MARGIN: f32 = 4.0;
+
+/// spacing in Å of the points of an orbital grid, unless that would take
+/// more than [GRID_POINTS] along some axis
+const GRID_SPACING: f32 = 0.2;
+
+/// the most points along each axis of an orbital grid
+const GRID_POINTS: usize = 100;
+
+/// primitives are taken to vanish where their exponent times r² exceeds
+/// this
+const CUTOFF: f64 = 40.0;
+
+/// the exponents of x, y and z of each Cartesian function, in the order of
+/// Molden files
+const CARTESIAN: [&[[i32; 3]]; 5] = [
+    &[[0, 0, 0]],
+    &[[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+    &[
+        [2, 0, 0],
+        [0, 2, 0],
+        [0, 0, 2],
+        [1, 1, 0],
+        [1, 0, 1],
+        [0, 1, 1],
+    ],
+    &[
+        [3, 0, 0],
+        [0, 3, 0],
+        [0, 0, 3],
+        [1, 2, 0],
+        [2, 1, 0],
+        [2, 0, 1],
+        [1, 0, 2],
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 1, 1],
+    ],
+    &[
+        [4, 0, 0],
+        [0, 4, 0],
+        [0, 0, 4],
+        [3, 1, 0],
+        [3, 0, 1],
+        [1, 3, 0],
+        [0, 3, 1],
+        [1, 0, 3],
+        [0, 1, 3],
+        [2, 2, 0],
+        [2, 0, 2],
+        [0, 2, 2],
+        [2, 1, 1],
+        [1, 2, 1],
+        [1, 1, 2],
+    ],
+];
+
+/// the highest angular momentum supported
+pub const MAX_L: usize = CARTESIAN.len() - 1;
+
+/// (2n - 1)!!
+fn double_factorial(n: i32) -> f64 {
+    (1..=n).map(|k| (2 * k - 1) as f64).product()
+}
+
+/// the real solid harmonics of degree `l` at `(x, y, z)`, in the order m =
+/// 0, 1, -1, 2, -2, ... used by Molden files. They are normalized like x^l
+/// over the sphere, so that they share the normalization of Cartesian
+/// functions
+fn solid_harmonics(l: usize, [x, y, z]: [f64; 3]) -> Vec<f64> {
+    let r2 = x * x + y * y + z * z;
+    let (s3, s5, s10, s15) =
+        (3f64.sqrt(), 5f64.sqrt(), 10f64.sqrt(), 15f64.sqrt());
+    match l {
+        2 => vec![
+            (3.0 * z * z - r2) / 2.0,
+            s3 * x * z,
+            s3 * y * z,
+            s3 / 2.0 * (x * x - y * y),
+            s3 * x * y,
+        ],
+        3 => {
+            let t = 5.0 * z * z - r2;
+            vec![
+                z * (5.0 * z * z - 3.0 * r2) / 2.0,
+                (3.0f64 / 8.0).sqrt() * x * t,
+                (3.0f64 / 8.0).sqrt() * y * t,
+                s15 / 2.0 * z * (x * x - y * y),
+                s15 * x * y * z,
+                (5.0f64 / 8.0).sqrt() * x * (x * x - 3.0 * y * y),
+                (5.0f64 / 8.0).sqrt() * y * (3.0 * x * x - y * y),
+            ]
+        }
+        4 => {
+            let (z2, x2, y2) = (z * z, x * x, y * y);
+            vec![
+                (35.0 * z2 * z2 - 30.0 * z2 * r2 + 3.0 * r2 * r2) / 8.0,
+                s10 / 4.0 * x * z * (7.0 * z2 - 3.0 * r2),
+                s10 / 4.0 * y * z * (7.0 * z2 - 3.0 * r2),
+                s5 / 4.0 * (x2 - y2) * (7.0 * z2 - r2),
+                s5 / 2.0 * x * y * (7.0 * z2 - r2),
+                70f64.sqrt() / 4.0 * x * z * (x2 - 3.0 * y2),
+                70f64.sqrt() / 4.0 * y * z * (3.0 * x2 - y2),
+                35f64.sqrt() / 8.0 * (x2 * x2 - 6.0 * x2 * y2 + y2 * y2),
+                35f64.sqrt() / 2.0 * x * y * (x2 - y2),
+            ]
+        }
+        _ => unreachable!("no solid harmonics of degree {l}"),
+    }
+}
+
+/// a shell of contracted Gaussian functions of one angular momentum on one
+/// atom
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shell {
+    /// position of the atom in bohr
+    center: [f64; 3],
+    l: usize,
+    /// real solid harmonics rather than Cartesian functions. Only matters
+    /// for d functions and above
+    spherical: bool,
+    exponents: Vec<f64>,
+    /// the contraction coefficients, including the normalization of the
+    /// primitives and of the contracted function
+    weights: Vec<f64>,
+}
+
+impl Shell {
+    /// a shell contracting the normalized primitives with `exponents` by
+    /// `coefficients`. The contracted function is renormalized, since
+    /// programs often print coefficients that don't quite normalize it
+    pub fn new(
+        center: [f64; 3],
+        l: usize,
+        spherical: bool,
+        exponents: Vec<f64>,
+        coefficients: &[f64],
+    ) -> Self {
+        assert!(l <= MAX_L, "no Gaussian functions of angular momentum {l}");
+        let lf = l as f64;
+        let mut norm = 0.0;
+        for (a, ca) in exponents.iter().zip(coefficients) {
+            for (b, cb) in exponents.iter().zip(coefficients) {
+                let overlap = (2.0 * (a * b).sqrt() / (a + b)).powf(lf + 1.5);
+                norm += ca * cb * overlap;
+            }
+        }
+        let norm = norm.sqrt();
+        let weights = exponents
+            .iter()
+            .zip(coefficients)
+            .map(|(a, c)| {
+                let primitive = (2.0 * a / std::f64::consts::PI).powf(0.75)
+                    * (4.0 * a).powf(lf / 2.0);
+                c * primitive / norm
+            })
+            .collect();
+        Self {
+            center,
+            l,
+            spherical,
+            exponents,
+            weights,
+        }
+    }
+
+    /// the number of basis functions in the shell
+    pub fn len(&self) -> usize {
+        if self.spherical && self.l >= 2 {
+            2 * self.l + 1
+        } else {
+            CARTESIAN[self.l].len()
+        }
+    }
+
+    /// append the value of each function of the shell at `p` to `out`
+    fn evaluate(&self, p: [f64; 3], out: &mut Vec<f64>) {
+        let d = [0, 1, 2].map(|c| p[c] - self.center[c]);
+        let r2: f64 = d.iter().map(|x| x * x).sum();
+        let radial: f64 = self
+            .exponents
+            .iter()
+            .zip(&self.weights)
+            .filter(|(a, _)| *a * r2 < CUTOFF)
+            .map(|(a, w)| w * (-a * r2).exp())
+            .sum();
+        if radial == 0.0 {
+            out.resize(out.len() + self.len(), 0.0);
+        } else if self.spherical && self.l >= 2 {
+            let scale = radial / double_factorial(self.l as i32).sqrt();
+            out.extend(
+                solid_harmonics(self.l, d).into_iter().map(|s| s * scale),
+            );
+        } else {
+            out.extend(CARTESIAN[self.l].iter().map(|&[a, b, c]| {
+                let norm = double_factorial(a)
+                    * double_factorial(b)
+                    * double_factorial(c);
+                radial * d[0].powi(a) * d[1].powi(b) * d[2].powi(c)
+                    / norm.sqrt()
+            }));
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Spin {
+    #[default]
+    Alpha,
+    Beta,
+}
+
+/// a molecular orbital as coefficients of the basis functions
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Orbital {
+    /// in Hartree
+    pub energy: f64,
+    pub spin: Spin,
+    pub occupation: f64,
+    pub coefficients: Vec<f64>,
+}
+
+impl Orbital {
+    fn occupied(&self) -> bool {
+        self.occupation > 1e-6
+    }
+}
+
+/// an orbital, or the electron density, to evaluate on a grid
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selection {
+    /// the HOMO, or the `n`th orbital below it
+    Homo(usize),
+    /// the LUMO, or the `n`th orbital above it
+    Lumo(usize),
+    /// an orbital by its 1-based position in the file
+    Index(usize),
+    Density,
+}
+
+impl Display for Selection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Selection::Homo(0) => write!(f, "HOMO"),
+            Selection::Homo(n) => write!(f, "HOMO-{n}"),
+            Selection::Lumo(0) => write!(f, "LUMO"),
+            Selection::Lumo(n) => write!(f, "LUMO+{n}"),
+            Selection::Index(n) => write!(f, "MO {n}"),
+            Selection::Density => write!(f, "density"),
+        }
+    }
+}
+
+impl FromStr for Selection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_ascii_lowercase();
+        let offset = |rest: &str, sign: char| match rest.strip_prefix(sign) {
+            _ if rest.is_empty() => Some(0),
+            Some(n) => n.parse().ok(),
+            None => None,
+        };
+        let ret = if lower == "density" {
+            Some(Selection::Density)
+        } else if let Some(rest) = lower.strip_prefix("homo") {
+            offset(rest, '-').map(Selection::Homo)
+        } else if let Some(rest) = lower.strip_prefix("lumo") {
+            offset(rest, '+').map(Selection::Lumo)
+        } else {
+            lower.parse().ok().filter(|&n| n > 0).map(Selection::Index)
+        };
+        ret.ok_or_else(|| {
+            format!(
+                "unknown orbital `{s}`: expected homo, homo-N, lumo, lumo+N, \
+                 an orbital number or density"
+            )
+        })
+    }
+}
+
+/// computes the value on a grid at a point from the values of the basis
+/// functions there
+type Evaluate<'a> = Box<dyn Fn(&[f64]) -> f64 + 'a>;
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// the orbitals of a calculation and the basis they are expanded in
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Wavefunction {
+    pub shells: Vec<Shell>,
+    pub orbitals: Vec<Orbital>,
+}
+
+impl Wavefunction {
+    /// the number of basis functions
+    pub fn nbasis(&self) -> usize {
+        self.shells.iter().map(Shell::len).sum()
+    }
+
+    /// the value of every basis function at `p`, in bohr
+    fn basis_values(&self, p: [f64; 3], out: &mut Vec<f64>) {
+        out.clear();
+        for shell in &self.shells {
+            shell.evaluate(p, out);
+        }
+    }
+
+    /// the index into [Wavefunction::orbitals] of `sel`. The HOMO and LUMO
+    /// are taken from the alpha orbitals
+    fn find(&self, sel: Selection) -> Option<usize> {
+        let mut alpha: Vec<usize> = (0..self.orbitals.len())
+            .filter(|&i| self.orbitals[i].spin == Spin::Alpha)
+            .collect();
+        alpha.sort_by(|&i, &j| {
+            self.orbitals[i].energy.total_cmp(&self.orbitals[j].energy)
+        });
+        let occupied = |i: &&usize| self.orbitals[**i].occupied();
+        match sel {
+            Selection::Homo(n) => {
+                alpha.iter().rev().filter(occupied).nth(n).copied()
+            }
+            Selection::Lumo(n) => {
+                alpha.iter().filter(|i| !occupied(i)).nth(n).copied()
+            }
+            Selection::Index(n) => {
+                n.checked_sub(1).filter(|&i| i < self.orbitals.len())
+            }
+            Selection::Density => None,
+        }
+    }
+
+    /// the name of the grid of `sel` and a function computing its value from
+    /// the values of the basis functions at a point, or `None` if there is
+    /// no such orbital
+    fn target(&self, sel: Selection) -> Option<(String, Evaluate<'_>)> {
+        if sel == Selection::Density {
+            let occupied: Vec<&Orbital> =
+                self.orbitals.iter().filter(|o| o.occupied()).collect();
+            let f = move |phi: &[f64]| {
+                occupied
+                    .iter()
+                    .map(|o| o.occupation * dot(&o.coefficients, phi).powi(2))
+                    .sum()
+            };
+            return Some((sel.to_string(), Box::new(f)));
+        }
+        let i = self.find(sel)?;
+        let orbital = &self.orbitals[i];
+        let mut labels = Vec::new();
+        if !matches!(sel, Selection::Index(_)) {
+            labels.push(sel.to_string());
+        }
+        if orbital.spin == Spin::Beta {
+            labels.push(String::from("beta"));
+        }
+        labels.push(format!("{:.4} Eh", orbital.energy));
+        let name = format!("MO {} ({})", i + 1, labels.join(", "));
+        let f = |phi: &[f64]| dot(&orbital.coefficients, phi);
+        Some((name, Box::new(f)))
+    }
+
+    /// each of `sels` sampled on the same grid around the atoms of `mol`,
+    /// leaving out orbitals that don't exist. The basis functions are
+    /// evaluated once per point for all of them
+    pub fn grids(&self, mol: &Molecule, sels: &[Selection]) -> Vec<VolumeGrid> {
+        let targets: Vec<_> =
+            sels.iter().filter_map(|&sel| self.target(sel)).collect();
+        if targets.is_empty() {
+            return Vec::new();
+        }
+        let (mut lo, mut hi) = ([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]);
+        for p in mol.positions() {
+            for c in 0..3 {
+                lo[c] = lo[c].min(p[c] - GRID_MARGIN);
+                hi[c] = hi[c].max(p[c] + GRID_MARGIN);
+            }
+        }
+        let extent = (0..3).map(|c| hi[c] - lo[c]).fold(0.0, f32::max);
+        let spacing = GRID_SPACING.max(extent / (GRID_POINTS - 1) as f32);
+        let shape =
+            [0, 1, 2].map(|c| ((hi[c] - lo[c]) / spacing).ceil() as usize + 1);
+        let mut axes = [[0.0; 3]; 3];
+        for (c, axis) in axes.iter_mut().enumerate() {
+            axis[c] = spacing;
+        }
+
+        let mut phi = Vec::new();
+        let npoints = shape.iter().product();
+        let mut values = vec![Vec::with_capacity(npoints); targets.len()];
+        for i in 0..shape[0] {
+            for j in 0..shape[1] {
+                for k in 0..shape[2] {
+                    let p = [i, j, k].map(|n| n as f32 * spacing);
+                    let p = [0, 1, 2]
+                        .map(|c| ((lo[c] + p[c]) / BOHR_TO_ANGSTROM) as f64);
+                    self.basis_values(p, &mut phi);
+                    for ((_, f), v) in targets.iter().zip(&mut values) {
+                        v.push(f(&phi) as f32);
+                    }
+                }
+            }
+        }
+        targets
+            .into_iter()
+            .zip(values)
+            .map(|((name, _), values)| VolumeGrid {
+                name,
+                origin: lo,
+                axes,
+                shape,
+                values,
+            })
+            .collect()
+    }
+}
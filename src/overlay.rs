@@ -0,0 +1,88 @@
+//! The 2D text drawn over the molecule
+
+use donkey::colors::WHITE;
+
+use crate::{molecule::Molecule, playback::Playback, ui};
+
+pub const MARGIN: i32 = 10;
+pub const LINE_HEIGHT: i32 = ui::FONT_SIZE + 5;
+
+/// the lines of the info panel toggled with I
+fn info_lines(mol: &Molecule, color_by: Option<&str>) -> Vec<String> {
+    let mut ret: Vec<String> =
+        mol.info.iter().map(|(k, v)| format!("{k} = {v}")).collect();
+    ret.push(format!("input units: {}", mol.units));
+    if let Some(cell) = &mol.cell {
+        let ([a, b, c], [alpha, beta, gamma]) = cell.parameters();
+        ret.push(format!(
+            "cell: a={a:.3} b={b:.3} c={c:.3} \
+             alpha={alpha:.2} beta={beta:.2} gamma={gamma:.2}"
+        ));
+    }
+    if let Some(h) = &mol.hierarchy {
+        ret.push(format!(
+            "chains: {}, residues: {}",
+            h.chains.len(),
+            h.residues.len()
+        ));
+    }
+    if !mol.properties.is_empty() {
+        let names: Vec<String> = mol
+            .properties
+            .iter()
+            .map(|p| match p.ncols {
+                1 => p.name.clone(),
+                n => format!("{} ({n})", p.name),
+            })
+            .collect();
+        ret.push(format!("properties: {}", names.join(", ")));
+    }
+    if let (Some(first), Some(last)) = (mol.modes.first(), mol.modes.last()) {
+        ret.push(format!(
+            "normal modes: {}, {:.1} to {:.1} cm-1",
+            mol.modes.len(),
+            first.frequency,
+            last.frequency
+        ));
+    }
+    for v in &mol.volumes {
+        let [n1, n2, n3] = v.shape;
+        let [s1, s2, s3] = v.spacing();
+        let (lo, hi) = v.range();
+        ret.push(format!(
+            "volume {}: {n1}x{n2}x{n3} points, spacing {s1:.3} {s2:.3} \
+             {s3:.3}, values {lo:.4} to {hi:.4}",
+            v.name
+        ));
+    }
+    if let Some(name) = color_by {
+        ret.push(format!("colored by: {name}"));
+    }
+    ret
+}
+
+pub fn draw(
+    mol: &Molecule,
+    player: &Playback,
+    status: &[String],
+    color_by: Option<&str>,
+    show_info: bool,
+) {
+    let mut y = MARGIN;
+    let mut line = |text: &str| {
+        ui::draw_text(text, MARGIN, y, WHITE);
+        y += LINE_HEIGHT;
+    };
+    line(&mol.comment);
+    if player.nframes > 1 {
+        line(&player.status());
+    }
+    for text in status {
+        line(text);
+    }
+    if show_info {
+        for text in info_lines(mol, color_by) {
+            line(&text);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use raylib_sys::{
+    KeyboardKey_KEY_END, KeyboardKey_KEY_EQUAL, KeyboardKey_KEY_HOME,
+    KeyboardKey_KEY_L, KeyboardKey_KEY_LEFT, KeyboardKey_KEY_MINUS,
+    KeyboardKey_KEY_RIGHT, KeyboardKey_KEY_SPACE,
+};
+
+use crate::ui;
+
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 240.0;
+
+/// the current position in a trajectory and how to advance it
+pub struct Playback {
+    pub frame: usize,
+    pub nframes: usize,
+    pub playing: bool,
+    pub looping: bool,
+    /// frames per second of playback
+    pub speed: f32,
+    /// seconds since the last frame change
+    elapsed: f32,
+}
+
+impl Playback {
+    pub fn new(nframes: usize) -> Self {
+        Self {
+            frame: 0,
+            nframes,
+            playing: false,
+            looping: true,
+            speed: 10.0,
+            elapsed: 0.0,
+        }
+    }
+
+    fn step(&mut self, forward: bool) {
+        let last = self.nframes - 1;
+        self.frame = match (forward, self.frame) {
+            (true, f) if f < last => f + 1,
+            (true, _) if self.looping => 0,
+            (false, 0) if self.looping => last,
+            (false, f) => f.saturating_sub(1),
+            (true, f) => f,
+        };
+    }
+
+    /// handle the playback keys and advance by `dt` seconds if playing:
+    ///
+    /// - Space: play/pause
+    /// - Left/Right: pause and step back/forward one frame
+    /// - Home/End: jump to the first/last frame
+    /// - L: toggle looping
+    /// - -/=: halve/double the playback speed
+    pub fn update(&mut self, dt: f32) {
+        if self.nframes < 2 {
+            return;
+        }
+        if ui::key_pressed(KeyboardKey_KEY_SPACE) {
+            // restart from the beginning if we already played to the end
+            if !self.playing && !self.looping && self.frame == self.nframes - 1
+            {
+                self.frame = 0;
+            }
+            self.playing = !self.playing;
+        }
+        if ui::key_pressed(KeyboardKey_KEY_RIGHT) {
+            self.playing = false;
+            self.step(true);
+        }
+        if ui::key_pressed(KeyboardKey_KEY_LEFT) {
+            self.playing = false;
+            self.step(false);
+        }
+        if ui::key_pressed(KeyboardKey_KEY_HOME) {
+            self.frame = 0;
+        }
+        if ui::key_pressed(KeyboardKey_KEY_END) {
+            self.frame = self.nframes - 1;
+        }
+        if ui::key_pressed(KeyboardKey_KEY_L) {
+            self.looping = !self.looping;
+        }
+        if ui::key_pressed(KeyboardKey_KEY_EQUAL) {
+            self.speed = (self.speed * 2.0).min(MAX_SPEED);
+        }
+        if ui::key_pressed(KeyboardKey_KEY_MINUS) {
+            self.speed = (self.speed / 2.0).max(MIN_SPEED);
+        }
+
+        if !self.playing {
+            self.elapsed = 0.0;
+            return;
+        }
+        self.elapsed += dt;
+        while self.elapsed >= 1.0 / self.speed {
+            self.elapsed -= 1.0 / self.speed;
+            if !self.looping && self.frame == self.nframes - 1 {
+                self.playing = false;
+                break;
+            }
+            self.step(true);
+        }
+    }
+
+    /// a one-line summary of the playback state for the overlay
+    pub fn status(&self) -> String {
+        format!(
+            "frame {}/{} [{}, {} fps{}]",
+            self.frame + 1,
+            self.nframes,
+            if self.playing { "playing" } else { "paused" },
+            self.speed,
+            if self.looping { ", loop" } else { "" },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stepping_wraps_around_only_when_looping() {
+        let mut p = Playback::new(3);
+        p.step(false);
+        assert_eq!(p.frame, 2);
+        p.step(true);
+        assert_eq!(p.frame, 0);
+
+        p.looping = false;
+        p.step(false);
+        assert_eq!(p.frame, 0);
+        p.frame = 2;
+        p.step(true);
+        assert_eq!(p.frame, 2);
+    }
+
+    #[test]
+    fn status_line() {
+        let mut p = Playback::new(12);
+        assert_eq!(p.status(), "frame 1/12 [paused, 10 fps, loop]");
+        p.frame = 11;
+        p.playing = true;
+        p.looping = false;
+        p.speed = 2.5;
+        assert_eq!(p.status(), "frame 12/12 [playing, 2.5 fps]");
+    }
+}
@@ -0,0 +1,353 @@
+use std::str::FromStr;
+
+use donkey::{vector3, Window};
+use raylib_sys::{Color, Vector3};
+
+use crate::{
+    cell::Cell,
+    isosurface::Mesh,
+    molecule::{BondOrder, Molecule},
+    vector::{add, cross, dot, lerp, norm, normalize, scale, sub},
+};
+
+/// scale factor from van der Waals radius to drawn sphere radius in
+/// ball-and-stick mode
+const BALL_SCALE: f32 = 0.3;
+
+const BOND_RADIUS: f32 = 0.1;
+
+/// radius of each cylinder in a double, triple or aromatic bond
+const MULTI_RADIUS: f32 = 0.05;
+
+/// distance between the cylinders of a multiple bond
+const MULTI_SPACING: f32 = 0.15;
+
+/// number of dashes in the dashed half of an aromatic bond
+const DASHES: usize = 4;
+
+/// bonds whose atoms are further apart than this from where the nearest
+/// periodic images put them are drawn to the images
+const IMAGE_TOLERANCE: f32 = 1e-3;
+
+const CELL_COLOR: u32 = 0xB0B0B0FF;
+
+const ARROW_RADIUS: f32 = 0.04;
+
+/// length of the cone at the tip of an arrow
+const ARROW_HEAD: f32 = 0.2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Style {
+    #[default]
+    BallAndStick,
+    Spacefill,
+    Sticks,
+    Wireframe,
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ball-and-stick" | "balls" => Ok(Style::BallAndStick),
+            "spacefill" | "cpk" => Ok(Style::Spacefill),
+            "sticks" | "licorice" => Ok(Style::Sticks),
+            "wireframe" | "lines" => Ok(Style::Wireframe),
+            _ => Err(format!("unknown render style `{s}`")),
+        }
+    }
+}
+
+/// map `t` in [0, 1] onto a blue-white-red ramp
+fn ramp(t: f32) -> Color {
+    let t = if t.is_finite() {
+        t.clamp(0.0, 1.0)
+    } else {
+        0.5
+    };
+    let (r, g, b) = if t < 0.5 {
+        let s = t * 2.0;
+        (s, s, 1.0)
+    } else {
+        let s = (1.0 - t) * 2.0;
+        (1.0, s, s)
+    };
+    let c = |x: f32| (x * 255.0).round() as u8;
+    Color {
+        r: c(r),
+        g: c(g),
+        b: c(b),
+        a: 255,
+    }
+}
+
+/// the color of each atom in `mol`, either by element or by the per-atom
+/// property named `color_by`. Properties spanning zero are centered on white,
+/// and otherwise the full range of values is used. Falls back on element
+/// colors if `mol` has no such property
+pub fn atom_colors(mol: &Molecule, color_by: Option<&str>) -> Vec<Color> {
+    let Some(prop) = color_by.and_then(|name| mol.property(name)) else {
+        return mol.atoms.iter().map(|a| a.element().color()).collect();
+    };
+    let values = prop.scalars();
+    let (lo, hi) = values
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    let (lo, hi) = if lo < 0.0 && hi > 0.0 {
+        let m = hi.max(-lo);
+        (-m, m)
+    } else {
+        (lo, hi)
+    };
+    values
+        .iter()
+        .map(|v| {
+            if hi > lo {
+                ramp((v - lo) / (hi - lo))
+            } else {
+                ramp(0.5)
+            }
+        })
+        .collect()
+}
+
+/// [Molecule::separation] as a vector
+fn separation(mol: &Molecule, i: usize, j: usize) -> Vector3 {
+    let [x, y, z] = mol.separation(i, j);
+    vector3!(x, y, z)
+}
+
+fn draw_line(start: Vector3, end: Vector3, color: Color) {
+    unsafe { raylib_sys::DrawLine3D(start, end, color) }
+}
+
+pub fn draw_molecule(
+    win: &Window,
+    mol: &Molecule,
+    style: Style,
+    colors: &[Color],
+) {
+    for (atom, &color) in mol.atoms.iter().zip(colors) {
+        let elem = atom.element();
+        let radius = match style {
+            Style::BallAndStick => elem.vdw_radius * BALL_SCALE,
+            Style::Spacefill => elem.vdw_radius,
+            Style::Sticks => BOND_RADIUS,
+            Style::Wireframe => continue,
+        };
+        win.draw_sphere(atom.as_vec(), radius, color);
+    }
+
+    if style == Style::Spacefill {
+        return;
+    }
+    let mut neighbors = vec![Vec::new(); mol.atoms.len()];
+    for b in &mol.bonds {
+        neighbors[b.i].push((b.j, b.order));
+        neighbors[b.j].push((b.i, b.order));
+    }
+    for bond in &mol.bonds {
+        let (i, j) = (bond.i, bond.j);
+        let (start, end) = (mol.atoms[i].as_vec(), mol.atoms[j].as_vec());
+        let d = separation(mol, i, j);
+        // a bond to a periodic image is drawn as two halves, each leaving
+        // its atom towards the image of the other
+        let halves = if norm(sub(add(start, d), end)) < IMAGE_TOLERANCE {
+            vec![(start, end, (0.0, 1.0))]
+        } else {
+            vec![
+                (start, add(start, d), (0.0, 0.5)),
+                (sub(end, d), end, (0.5, 1.0)),
+            ]
+        };
+        let offset = scale(bond_plane(mol, &neighbors, i, j), MULTI_SPACING);
+        let (lines, radius) = match bond.order {
+            BondOrder::Single => (vec![(0.0, false)], BOND_RADIUS),
+            BondOrder::Double => {
+                (vec![(-0.5, false), (0.5, false)], MULTI_RADIUS)
+            }
+            BondOrder::Triple => (
+                vec![(-1.0, false), (0.0, false), (1.0, false)],
+                MULTI_RADIUS,
+            ),
+            BondOrder::Aromatic => {
+                (vec![(0.0, false), (1.0, true)], MULTI_RADIUS)
+            }
+        };
+        for &(a, b, clip) in &halves {
+            for &(s, dashed) in &lines {
+                let o = scale(offset, s);
+                draw_bond_line(
+                    win,
+                    style,
+                    (add(a, o), add(b, o)),
+                    (dashed, clip),
+                    radius,
+                    (colors[i], colors[j]),
+                );
+            }
+        }
+    }
+}
+
+/// a unit vector perpendicular to the bond from atom `i` to atom `j`, lying
+/// in the plane of one of their other neighbors if they have any. For ring
+/// bonds this points into the ring
+fn bond_plane(
+    mol: &Molecule,
+    neighbors: &[Vec<(usize, BondOrder)>],
+    i: usize,
+    j: usize,
+) -> Vector3 {
+    let axis =
+        normalize(separation(mol, i, j)).unwrap_or(vector3!(1.0, 0.0, 0.0));
+    let mut candidates: Vec<(bool, Vector3)> = neighbors[i]
+        .iter()
+        .filter(|&&(k, _)| k != j)
+        .map(|&(k, order)| (order, separation(mol, i, k)))
+        .chain(
+            neighbors[j]
+                .iter()
+                .filter(|&&(k, _)| k != i)
+                .map(|&(k, order)| (order, separation(mol, j, k))),
+        )
+        .map(|(order, v)| (order == BondOrder::Single, v))
+        .collect();
+    // prefer neighbors across other multiple bonds, so that aromatic bonds
+    // lean into the ring rather than towards a substituent
+    candidates.sort_by_key(|&(single, _)| single);
+    for (_, v) in candidates {
+        let perp = sub(v, scale(axis, dot(v, axis)));
+        if let Some(dir) = normalize(perp) {
+            return dir;
+        }
+    }
+    // no neighbors to define a plane, so any perpendicular will do
+    let other = if axis.x.abs() < 0.9 {
+        vector3!(1.0, 0.0, 0.0)
+    } else {
+        vector3!(0.0, 1.0, 0.0)
+    };
+    normalize(cross(axis, other)).unwrap()
+}
+
+/// draw one cylinder or line of a bond from `ends.0` to `ends.1`, split in
+/// half so that the sticks and wireframe styles can color each half like its
+/// atom. Dashed lines are drawn as [DASHES] separate pieces, and only the
+/// pieces within the fractions `clip` of the way along are drawn
+fn draw_bond_line(
+    win: &Window,
+    style: Style,
+    ends: (Vector3, Vector3),
+    (dashed, clip): (bool, (f32, f32)),
+    radius: f32,
+    colors: (Color, Color),
+) {
+    let pieces: Vec<(f32, f32)> = if dashed {
+        let n = 2 * DASHES - 1;
+        (0..n)
+            .step_by(2)
+            .map(|k| (k as f32 / n as f32, (k + 1) as f32 / n as f32))
+            .collect()
+    } else {
+        vec![(0.0, 0.5), (0.5, 1.0)]
+    };
+    for (t0, t1) in pieces {
+        if !(clip.0..clip.1).contains(&((t0 + t1) / 2.0)) {
+            continue;
+        }
+        let (a, b) = (lerp(ends.0, ends.1, t0), lerp(ends.0, ends.1, t1));
+        let color = if (t0 + t1) / 2.0 < 0.5 {
+            colors.0
+        } else {
+            colors.1
+        };
+        match style {
+            Style::BallAndStick => {
+                win.draw_cylinder(a, b, radius, donkey::colors::LIGHTGRAY)
+            }
+            Style::Sticks => win.draw_cylinder(a, b, radius, color),
+            Style::Wireframe => draw_line(a, b, color),
+            Style::Spacefill => {}
+        }
+    }
+}
+
+/// draw the edges of `cell` as lines
+pub fn draw_cell(cell: &Cell) {
+    let v = |p: [f32; 3]| vector3!(p[0], p[1], p[2]);
+    for (a, b) in cell.edges() {
+        draw_line(v(a), v(b), donkey::colors::color(CELL_COLOR));
+    }
+}
+
+/// draw an arrow from `start` to `end` as a cylinder capped with a cone
+pub fn draw_arrow(start: Vector3, end: Vector3, color: Color) {
+    let d = sub(end, start);
+    let len = norm(d);
+    if len == 0.0 {
+        return;
+    }
+    let head = ARROW_HEAD.min(0.5 * len);
+    let neck = sub(end, scale(d, head / len));
+    unsafe {
+        raylib_sys::DrawCylinderEx(
+            start,
+            neck,
+            ARROW_RADIUS,
+            ARROW_RADIUS,
+            8,
+            color,
+        );
+        raylib_sys::DrawCylinderEx(
+            neck,
+            end,
+            2.5 * ARROW_RADIUS,
+            0.0,
+            8,
+            color,
+        );
+    }
+}
+
+/// draw translucent `meshes`, each in its own color, as seen from `eye`. The
+/// triangles are sorted back to front so that they blend correctly, drawn
+/// from both sides, and shaded by how squarely they face the viewer
+pub fn draw_meshes(meshes: &[(&Mesh, Color)], eye: Vector3) {
+    let v = |p: [f32; 3]| vector3!(p[0], p[1], p[2]);
+    let mut order: Vec<(f32, usize, usize)> = Vec::new();
+    for (m, (mesh, _)) in meshes.iter().enumerate() {
+        for (t, tri) in mesh.triangles.iter().enumerate() {
+            let center = tri.iter().fold(vector3!(0.0, 0.0, 0.0), |c, &i| {
+                add(c, v(mesh.vertices[i as usize]))
+            });
+            let d = sub(scale(center, 1.0 / 3.0), eye);
+            order.push((dot(d, d), m, t));
+        }
+    }
+    order.sort_by(|a, b| b.0.total_cmp(&a.0));
+    for (_, m, t) in order {
+        let (mesh, color) = meshes[m];
+        let tri = mesh.triangles[t].map(|i| i as usize);
+        let [a, b, c] = tri.map(|i| v(mesh.vertices[i]));
+        let normal = tri
+            .iter()
+            .fold(vector3!(0.0, 0.0, 0.0), |n, &i| add(n, v(mesh.normals[i])));
+        let view = normalize(sub(eye, a)).unwrap_or(vector3!(0.0, 0.0, 1.0));
+        let facing = normalize(normal).map_or(1.0, |n| dot(n, view).abs());
+        let shade = |x: u8| (x as f32 * (0.35 + 0.65 * facing)) as u8;
+        let color = Color {
+            r: shade(color.r),
+            g: shade(color.g),
+            b: shade(color.b),
+            a: color.a,
+        };
+        unsafe {
+            raylib_sys::DrawTriangle3D(a, b, c, color);
+            raylib_sys::DrawTriangle3D(a, c, b, color);
+        }
+    }
+}
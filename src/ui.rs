@@ -0,0 +1,43 @@
+//! Thin wrappers around the raylib input and 2D drawing calls not covered
+//! by donkey
+
+use std::ffi::CString;
+
+use raylib_sys::{Color, KeyboardKey, MouseButton_MOUSE_BUTTON_LEFT};
+
+pub const FONT_SIZE: i32 = 20;
+
+pub fn key_pressed(key: KeyboardKey) -> bool {
+    unsafe { raylib_sys::IsKeyPressed(key as i32) }
+}
+
+/// the screen position of a left click this frame, if any
+pub fn clicked() -> Option<(i32, i32)> {
+    unsafe {
+        if !raylib_sys::IsMouseButtonPressed(
+            MouseButton_MOUSE_BUTTON_LEFT as i32,
+        ) {
+            return None;
+        }
+        let p = raylib_sys::GetMousePosition();
+        Some((p.x as i32, p.y as i32))
+    }
+}
+
+/// the width and height of the window in pixels
+pub fn screen_size() -> (i32, i32) {
+    unsafe { (raylib_sys::GetScreenWidth(), raylib_sys::GetScreenHeight()) }
+}
+
+/// seconds taken to draw the last frame
+pub fn frame_time() -> f32 {
+    unsafe { raylib_sys::GetFrameTime() }
+}
+
+/// draw `text` in screen coordinates with its top left corner at `x`, `y`
+pub fn draw_text(text: &str, x: i32, y: i32, color: Color) {
+    // interior nul bytes can only come from the input file, so just drop
+    // them
+    let text = CString::new(text.replace('\0', "")).unwrap();
+    unsafe { raylib_sys::DrawText(text.as_ptr(), x, y, FONT_SIZE, color) }
+}
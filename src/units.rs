@@ -0,0 +1,89 @@
+//! Length units. Coordinates are always stored in Å once loaded, and
+//! [Molecule::units] records what the input file used.
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::{molecule::Molecule, neighbors::Grid};
+
+/// CODATA 2018
+pub const BOHR_TO_ANGSTROM: f32 = 0.529_177_2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Unit {
+    #[default]
+    Angstrom,
+    Bohr,
+}
+
+impl Unit {
+    /// the factor converting lengths in `self` to Å
+    pub fn to_angstrom(self) -> f32 {
+        match self {
+            Unit::Angstrom => 1.0,
+            Unit::Bohr => BOHR_TO_ANGSTROM,
+        }
+    }
+}
+
+impl Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Unit::Angstrom => write!(f, "angstrom"),
+            Unit::Bohr => write!(f, "bohr"),
+        }
+    }
+}
+
+impl FromStr for Unit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "angstrom" | "ang" | "a" => Ok(Unit::Angstrom),
+            "bohr" | "au" | "a0" => Ok(Unit::Bohr),
+            _ => Err(format!("unknown unit `{s}`")),
+        }
+    }
+}
+
+/// ratio of nearest-neighbor distance to the sum of covalent radii above
+/// which coordinates are assumed to be in bohr. Bonded atoms sit close to a
+/// ratio of 1 in Å and 1/[BOHR_TO_ANGSTROM] ≈ 1.89 in bohr, so split the
+/// difference geometrically
+const BOHR_THRESHOLD: f32 = 1.37;
+
+/// guess the units of `mol` by comparing the distance from each atom to its
+/// nearest neighbor with the sum of their covalent radii (in Å). Atoms with
+/// no neighbors near enough to be bonded in either unit are ignored, and the
+/// guess defaults to Å when there are too few atoms to tell
+pub fn detect(mol: &Molecule) -> Unit {
+    let radius = |i: usize| mol.atoms[i].element().covalent_radius;
+    let max_radius = (0..mol.atoms.len()).map(radius).fold(0.0, f32::max);
+    // comfortably past a bohr-scaled bond between the largest atoms
+    let cutoff = 2.0 * max_radius * 1.5 / BOHR_TO_ANGSTROM;
+    if cutoff <= 0.0 {
+        return Unit::Angstrom;
+    }
+
+    let points = mol.positions();
+    let mut nearest = vec![f32::INFINITY; points.len()];
+    Grid::new(&points, cutoff).for_each_pair(&points, cutoff, |i, j, d| {
+        if mol.atoms[i].w == 0 || mol.atoms[j].w == 0 {
+            return;
+        }
+        let ratio = d / (radius(i) + radius(j));
+        nearest[i] = nearest[i].min(ratio);
+        nearest[j] = nearest[j].min(ratio);
+    });
+    let mut ratios: Vec<f32> =
+        nearest.into_iter().filter(|r| r.is_finite()).collect();
+    if ratios.is_empty() {
+        return Unit::Angstrom;
+    }
+    ratios.sort_by(f32::total_cmp);
+    if ratios[ratios.len() / 2] > BOHR_THRESHOLD {
+        Unit::Bohr
+    } else {
+        Unit::Angstrom
+    }
+}
@@ -0,0 +1,73 @@
+//! Small helpers for arithmetic on raylib vectors
+
+use std::ops::{Add, Mul, Sub};
+
+use donkey::vector3;
+use raylib_sys::Vector3;
+
+pub fn add(a: Vector3, b: Vector3) -> Vector3 {
+    vector3!(a.x + b.x, a.y + b.y, a.z + b.z)
+}
+
+pub fn sub(a: Vector3, b: Vector3) -> Vector3 {
+    vector3!(a.x - b.x, a.y - b.y, a.z - b.z)
+}
+
+pub fn scale(a: Vector3, s: f32) -> Vector3 {
+    vector3!(a.x * s, a.y * s, a.z * s)
+}
+
+pub fn dot(a: Vector3, b: Vector3) -> f32 {
+    a.x * b.x + a.y * b.y + a.z * b.z
+}
+
+pub fn cross(a: Vector3, b: Vector3) -> Vector3 {
+    vector3!(
+        a.y * b.z - a.z * b.y,
+        a.z * b.x - a.x * b.z,
+        a.x * b.y - a.y * b.x
+    )
+}
+
+pub fn norm(a: Vector3) -> f32 {
+    dot(a, a).sqrt()
+}
+
+/// `a` scaled to unit length, or `None` if it is too short to have a
+/// meaningful direction
+pub fn normalize(a: Vector3) -> Option<Vector3> {
+    let n = norm(a);
+    (n > 1e-6).then(|| scale(a, 1.0 / n))
+}
+
+/// the point a fraction `t` of the way from `a` to `b`
+pub fn lerp(a: Vector3, b: Vector3, t: f32) -> Vector3 {
+    add(a, scale(sub(b, a), t))
+}
+
+/// `a - b` for plain arrays, in either precision
+pub fn sub3<T>(a: [T; 3], b: [T; 3]) -> [T; 3]
+where
+    T: Copy + Sub<Output = T>,
+{
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+/// the dot product of plain arrays, in either precision
+pub fn dot3<T>(a: [T; 3], b: [T; 3]) -> T
+where
+    T: Copy + Add<Output = T> + Mul<Output = T>,
+{
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross3<T>(a: [T; 3], b: [T; 3]) -> [T; 3]
+where
+    T: Copy + Sub<Output = T> + Mul<Output = T>,
+{
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
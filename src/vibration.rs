@@ -0,0 +1,212 @@
+//! Normal modes of vibration from frequency calculations
+
+use crate::{molecule::Molecule, vector};
+
+/// converts the square root of a mass-weighted Hessian eigenvalue in
+/// Eh/(bohr² amu) to a wavenumber in cm⁻¹
+const HESSIAN_TO_WAVENUMBER: f64 = 5140.487;
+
+/// a single normal mode of a [Molecule]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NormalMode {
+    /// wavenumber in cm⁻¹, negative for imaginary modes
+    pub frequency: f32,
+    /// reduced mass in amu, if the program reported it
+    pub reduced_mass: Option<f32>,
+    /// IR intensity in km/mol, if the program reported it
+    pub ir_intensity: Option<f32>,
+    /// the Cartesian displacement of each atom, in whatever normalization the
+    /// program printed
+    pub displacements: Vec<[f32; 3]>,
+}
+
+/// the eigenvalues and eigenvectors (as the columns of the returned matrix)
+/// of the symmetric `n` by `n` row-major matrix `a`, by cyclic Jacobi
+/// rotations
+fn eigen(mut a: Vec<f64>, n: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut v = vec![0.0; n * n];
+    for i in 0..n {
+        v[i * n + i] = 1.0;
+    }
+    let scale: f64 = a.iter().map(|x| x * x).sum::<f64>().sqrt();
+    for _ in 0..100 {
+        let off: f64 = (0..n)
+            .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i * n + j] * a[i * n + j])
+            .sum();
+        if off.sqrt() <= 1e-12 * scale {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                let apq = a[p * n + q];
+                if apq == 0.0 {
+                    continue;
+                }
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + theta.hypot(1.0));
+                let c = 1.0 / t.hypot(1.0);
+                let s = t * c;
+                for k in 0..n {
+                    let (akp, akq) = (a[k * n + p], a[k * n + q]);
+                    a[k * n + p] = c * akp - s * akq;
+                    a[k * n + q] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[p * n + k], a[q * n + k]);
+                    a[p * n + k] = c * apk - s * aqk;
+                    a[q * n + k] = s * apk + c * aqk;
+                }
+                for k in 0..n {
+                    let (vkp, vkq) = (v[k * n + p], v[k * n + q]);
+                    v[k * n + p] = c * vkp - s * vkq;
+                    v[k * n + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    ((0..n).map(|i| a[i * n + i]).collect(), v)
+}
+
+/// the number of translations and rotations of `mol`: 5 if it is linear and
+/// 6 otherwise, or 3 for a single atom
+fn rigid_modes(mol: &Molecule) -> usize {
+    let Some(first) = mol.atoms.first() else {
+        return 0;
+    };
+    let origin = first.as_vec();
+    let far = mol
+        .atoms
+        .iter()
+        .map(|a| vector::sub(a.as_vec(), origin))
+        .max_by(|a, b| vector::norm(*a).total_cmp(&vector::norm(*b)));
+    let Some(axis) = far.and_then(vector::normalize) else {
+        return 3;
+    };
+    let linear = mol.atoms.iter().all(|a| {
+        let d = vector::sub(a.as_vec(), origin);
+        vector::norm(vector::cross(d, axis)) < 1e-3
+    });
+    if linear {
+        5
+    } else {
+        6
+    }
+}
+
+/// the normal modes of `mol` from its Cartesian Hessian in Eh/bohr², stored
+/// row-major as 3N by 3N, leaving out the translations and rotations. The
+/// displacements are normalized, and the modes sorted by frequency
+pub fn from_hessian(mol: &Molecule, hessian: &[f64]) -> Vec<NormalMode> {
+    let n = 3 * mol.atoms.len();
+    assert_eq!(hessian.len(), n * n);
+    let sqrt_mass: Vec<f64> = mol
+        .atoms
+        .iter()
+        .flat_map(|a| [(a.element().mass as f64).sqrt(); 3])
+        .collect();
+    let weighted = (0..n * n)
+        .map(|k| hessian[k] / (sqrt_mass[k / n] * sqrt_mass[k % n]))
+        .collect();
+    let (values, vectors) = eigen(weighted, n);
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| values[a].abs().total_cmp(&values[b].abs()));
+    order.drain(..rigid_modes(mol).min(n));
+    let mut modes: Vec<NormalMode> = order
+        .into_iter()
+        .map(|k| {
+            let value = values[k];
+            let frequency =
+                value.signum() * value.abs().sqrt() * HESSIAN_TO_WAVENUMBER;
+            let cart: Vec<f64> =
+                (0..n).map(|i| vectors[i * n + k] / sqrt_mass[i]).collect();
+            let norm = cart.iter().map(|x| x * x).sum::<f64>().sqrt();
+            NormalMode {
+                frequency: frequency as f32,
+                // the mass-weighted eigenvector has unit length
+                reduced_mass: Some((1.0 / (norm * norm)) as f32),
+                ir_intensity: None,
+                displacements: cart
+                    .chunks(3)
+                    .map(|d| [d[0], d[1], d[2]].map(|x| (x / norm) as f32))
+                    .collect(),
+            }
+        })
+        .collect();
+    modes.sort_by(|a, b| a.frequency.total_cmp(&b.frequency));
+    modes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{element::ELEMENTS, molecule::Atom};
+
+    fn diatomic(w: [u8; 2], k: f64) -> (Molecule, Vec<f64>) {
+        let mol = Molecule {
+            atoms: vec![
+                Atom { x: 0.0, y: 0.0, z: 0.0, w: w[0] },
+                Atom { x: 0.0, y: 0.0, z: 1.0, w: w[1] },
+            ],
+            ..Default::default()
+        };
+        // a spring along z between the two atoms
+        let mut hessian = vec![0.0; 36];
+        for (i, j, sign) in
+            [(2, 2, 1.0), (5, 5, 1.0), (2, 5, -1.0), (5, 2, -1.0)]
+        {
+            hessian[i * 6 + j] = sign * k;
+        }
+        (mol, hessian)
+    }
+
+    #[test]
+    fn eigen_of_symmetric_matrix() {
+        let a = vec![2.0, 1.0, 0.0, 1.0, 2.0, 0.0, 0.0, 0.0, 5.0];
+        let (mut values, v) = eigen(a.clone(), 3);
+        // A v = λ v for every column
+        for (k, &lambda) in values.iter().enumerate() {
+            for i in 0..3 {
+                let av: f64 = (0..3).map(|j| a[i * 3 + j] * v[j * 3 + k]).sum();
+                assert!((av - lambda * v[i * 3 + k]).abs() < 1e-10);
+            }
+        }
+        values.sort_by(f64::total_cmp);
+        for (got, want) in values.iter().zip([1.0, 3.0, 5.0]) {
+            assert!((got - want).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn diatomic_stretch() {
+        let k = 0.5;
+        let (mol, hessian) = diatomic([8, 1], k);
+        let modes = from_hessian(&mol, &hessian);
+        assert_eq!(modes.len(), 1);
+        let [m1, m2] = [8, 1].map(|w| ELEMENTS[w].mass as f64);
+        let mu = m1 * m2 / (m1 + m2);
+        let mode = &modes[0];
+        let expected = (k / mu).sqrt() * HESSIAN_TO_WAVENUMBER;
+        assert!((mode.frequency as f64 - expected).abs() < 1e-2);
+        // as Gaussian defines it, from the normalized Cartesian displacements
+        // rather than the textbook μ
+        let reduced = m1 * m2 * (m1 + m2) / (m1 * m1 + m2 * m2);
+        assert!((mode.reduced_mass.unwrap() as f64 - reduced).abs() < 1e-4);
+        // the atoms move apart along the bond, the lighter one further
+        let [a, b] = [mode.displacements[0], mode.displacements[1]];
+        assert!(a[0] == 0.0 && a[1] == 0.0 && b[0] == 0.0 && b[1] == 0.0);
+        assert!(a[2] * b[2] < 0.0);
+        assert!(((b[2] / a[2]).abs() as f64 - m1 / m2).abs() < 1e-3);
+        let norm: f32 = a[2] * a[2] + b[2] * b[2];
+        assert!((norm - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn negative_curvature_is_imaginary() {
+        let (mol, hessian) = diatomic([1, 1], -0.2);
+        let modes = from_hessian(&mol, &hessian);
+        assert_eq!(modes.len(), 1);
+        assert!(modes[0].frequency < 0.0);
+    }
+}
@@ -0,0 +1,34 @@
+//! Volumetric data such as densities, electrostatic potentials and orbitals,
+//! sampled on a regular grid
+
+/// values on a grid of points `origin + i * axes[0] + j * axes[1] + k *
+/// axes[2]`, stored with `k` varying fastest
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VolumeGrid {
+    /// what the values are, such as `density` or `MO 5`
+    pub name: String,
+    /// position of the first point in Å
+    pub origin: [f32; 3],
+    /// step between neighboring points along each axis, in Å
+    pub axes: [[f32; 3]; 3],
+    /// number of points along each axis
+    pub shape: [usize; 3],
+    pub values: Vec<f32>,
+}
+
+impl VolumeGrid {
+    /// the smallest and largest values
+    pub fn range(&self) -> (f32, f32) {
+        self.values
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| {
+                (lo.min(v), hi.max(v))
+            })
+    }
+
+    /// the spacing of the points along each axis in Å
+    pub fn spacing(&self) -> [f32; 3] {
+        self.axes
+            .map(|a| a.iter().map(|x| x * x).sum::<f32>().sqrt())
+    }
+}